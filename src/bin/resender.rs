@@ -1,11 +1,11 @@
 use anyhow::Result;
 use argh::FromArgs;
+use openapi_fuzzer::framing::HttpVersion;
+use openapi_fuzzer::payload::{StoredPayload, REQUEST_ID_HEADER};
 use serde::Deserialize;
 use std::fs;
 use std::path::PathBuf;
 use std::str::FromStr;
-use ureq::{self, OrAnyStatus, Response};
-use url::Url;
 
 #[derive(FromArgs, Debug)]
 /// OpenAPI fuzzer
@@ -30,10 +30,10 @@ impl FromStr for Header {
         if parts.len() != 2 {
             return Err("invalid header format".to_string());
         }
-        Ok(Header {
-            0: parts[0].to_string().to_lowercase(),
-            1: parts[1].to_string(),
-        })
+        Ok(Header(
+            parts[0].to_string().to_lowercase(),
+            parts[1].trim().to_string(),
+        ))
     }
 }
 
@@ -43,76 +43,73 @@ impl From<Header> for (String, String) {
     }
 }
 
-#[derive(Debug, Deserialize)]
-struct Payload {
-    url: Url,
-    method: String,
-    path: String,
-    query_params: Vec<(String, String)>,
-    path_params: Vec<(String, String)>,
-    headers: Vec<(String, String)>,
-    body: Vec<serde_json::Value>,
-}
-
 #[derive(Debug, Deserialize)]
 struct ResultFile {
-    payload: Payload,
+    payload: StoredPayload,
 }
 
-impl Payload {
-    fn send(&mut self, extra_headers: Vec<(String, String)>) -> Result<Response> {
-        let mut path_with_params = self.path.to_owned();
-        for (name, value) in self.path_params.iter() {
-            path_with_params = path_with_params.replace(&format!("{{{}}}", name), value);
-        }
-        let mut request = ureq::request_url(
-            &self.method,
-            &self.url.join(path_with_params.trim_start_matches('/'))?,
-        );
-
-        for (param, value) in self.query_params.iter() {
-            request = request.query(param, value)
-        }
-
-        for (name, value) in extra_headers {
-            let index = self
-                .headers
-                .iter()
-                .position(|(header_name, _)| header_name.to_lowercase() == name);
-            match index {
-                Some(i) => self.headers[i] = (name, value),
-                None => self.headers.push((name, value)),
-            }
-        }
+// Longer response bodies are cut off when they are printed
+const BODY_EXCERPT: usize = 4096;
 
-        for (header, value) in self.headers.iter() {
-            request = request.set(header, value)
-        }
+/// Body pretty-printed when it is JSON
+fn pretty(body: &str) -> String {
+    match serde_json::from_str::<serde_json::Value>(body) {
+        Ok(json) => serde_json::to_string_pretty(&json).unwrap_or_else(|_| body.to_string()),
+        Err(_) => body.to_string(),
+    }
+}
 
-        if !self.body.is_empty() {
-            Ok(request.send_json(self.body[0].clone()).or_any_status()?)
-        } else {
-            request.call().or_any_status().map_err(|e| e.into())
-        }
+/// Beginning of the body, with the number of bytes that were left out
+fn excerpt(body: &str) -> String {
+    if body.len() <= BODY_EXCERPT {
+        return body.to_string();
+    }
+    let mut end = BODY_EXCERPT;
+    while !body.is_char_boundary(end) {
+        end -= 1;
     }
+    format!("{}\n... {} more bytes", &body[..end], body.len() - end)
 }
 
 fn main() -> Result<()> {
     let args: Args = argh::from_env();
     let mut result: ResultFile = serde_json::from_str(&fs::read_to_string(&args.file)?)?;
+    let extra_headers = args
+        .header
+        .into_iter()
+        .map(|h| h.into())
+        .collect::<Vec<_>>();
+    result.payload.set_headers(&extra_headers);
+    let payload = result.payload.payload();
+
+    println!("> {} {}", payload.method, payload.sent_url()?);
+    println!("> {}: {}", REQUEST_ID_HEADER, payload.request_id);
+    for (name, value) in &payload.headers {
+        println!("> {}: {}", name, value);
+    }
+    match (payload.body.first(), &payload.duplicate_key) {
+        // Pretty-printing would drop the repeated key
+        (Some(body), Some(duplicate)) => println!("\n{}", duplicate.encode(body)),
+        (Some(body), None) => println!("\n{}", serde_json::to_string_pretty(body)?),
+        (None, _) => (),
+    }
+    if let (Some(compression), false) = (payload.compression, payload.body.is_empty()) {
+        println!("\nsent as {}", compression);
+    }
+    if let Some(framing) = payload.framing {
+        println!("\nframed as {}", framing);
+    }
+    if payload.http_version != HttpVersion::default() {
+        println!("\nover {}", payload.http_version);
+    }
 
-    let resp = result
-        .payload
-        .send(args.header.into_iter().map(|h| h.into()).collect())?;
-
-    println!("{:?}", &resp);
-    if resp.content_type().to_lowercase().contains("json") {
-        println!(
-            "{}",
-            serde_json::to_string_pretty(&resp.into_json::<serde_json::Value>()?)?
-        )
-    } else {
-        println!("{}", &resp.into_string()?);
+    let resp = payload.send(payload.method)?;
+    println!("\n< {}", resp.status);
+    for (name, value) in &resp.headers {
+        println!("< {}: {}", name, value);
+    }
+    if !resp.body.is_empty() {
+        println!("\n{}", excerpt(&pretty(&resp.body)));
     }
     Ok(())
 }
@@ -0,0 +1,61 @@
+use std::cell::Cell;
+
+/// Limits of a generated request body as a whole, so that schemas with wide
+/// objects and nested arrays cannot make a single request huge
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BodyBudget {
+    /// Bytes of the strings and property names together, every property and
+    /// item takes one more for its separator
+    pub bytes: usize,
+    /// Properties and array items across all nested objects and arrays
+    pub elements: usize,
+}
+
+impl Default for BodyBudget {
+    fn default() -> BodyBudget {
+        BodyBudget {
+            bytes: 1024 * 1024,
+            elements: 10_000,
+        }
+    }
+}
+
+/// What is left of the budget while a body is generated. Objects and arrays
+/// stop growing once it is used up and strings are cut short.
+#[derive(Debug)]
+pub(crate) struct Remaining {
+    bytes: Cell<usize>,
+    elements: Cell<usize>,
+}
+
+impl Remaining {
+    pub(crate) fn new(budget: BodyBudget) -> Remaining {
+        Remaining {
+            bytes: Cell::new(budget.bytes),
+            elements: Cell::new(budget.elements),
+        }
+    }
+
+    /// Takes a property with the name or an array item, without a name, from
+    /// the budget. False when there is no room left for it.
+    pub(crate) fn element(&self, name: &str) -> bool {
+        let bytes = name.len() + 1;
+        if self.elements.get() == 0 || self.bytes.get() < bytes {
+            return false;
+        }
+        self.elements.set(self.elements.get() - 1);
+        self.bytes.set(self.bytes.get() - bytes);
+        true
+    }
+
+    /// The string cut to the bytes that are left, which it takes
+    pub(crate) fn truncate(&self, mut value: String) -> String {
+        let mut length = value.len().min(self.bytes.get());
+        while !value.is_char_boundary(length) {
+            length -= 1;
+        }
+        value.truncate(length);
+        self.bytes.set(self.bytes.get() - length);
+        value
+    }
+}
@@ -0,0 +1,196 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, bail, Context, Result};
+use openapi_utils::SpecExt;
+use openapiv3::OpenAPI;
+use url::Url;
+
+use crate::checker::Checker;
+use crate::config::{OperationConfig, Operations};
+use crate::fuzzer::{Api, Check, Finding, Fuzzer};
+use crate::leaks::Detectors;
+use crate::lint::{self, Level};
+use crate::payload::PayloadOptions;
+use crate::servers;
+use crate::spec;
+use crate::status::Policies;
+use crate::strategy::Strategy;
+
+/// Builds a fuzzer without the terminal interface, which ends after a budget
+/// of requests, e.g. to fuzz a test server from an integration test and fail
+/// the test when there are findings
+#[derive(Debug, Default)]
+pub struct FuzzerBuilder {
+    spec: Option<PathBuf>,
+    schema: Option<OpenAPI>,
+    urls: Vec<Url>,
+    max_requests: Option<u32>,
+    checks: Option<Vec<Check>>,
+    checkers: Vec<Box<dyn Checker>>,
+    headers: Vec<(String, String)>,
+    params: Vec<(String, String)>,
+    strategies: Vec<Strategy>,
+    ignored_status_codes: Vec<u16>,
+    stateful: bool,
+    results_dir: Option<PathBuf>,
+}
+
+impl FuzzerBuilder {
+    pub fn new() -> FuzzerBuilder {
+        FuzzerBuilder::default()
+    }
+
+    /// Path of the specification file
+    pub fn spec(mut self, path: impl Into<PathBuf>) -> FuzzerBuilder {
+        self.spec = Some(path.into());
+        self
+    }
+
+    /// Specification that was loaded already, instead of a file
+    pub fn schema(mut self, schema: OpenAPI) -> FuzzerBuilder {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// Url of the api, may be given more times to spread the requests across
+    /// the servers. Defaults to the first server of the specification.
+    pub fn url(mut self, url: Url) -> FuzzerBuilder {
+        self.urls.push(servers::with_trailing_slash(url));
+        self
+    }
+
+    /// Number of requests sent before fuzzing ends, 1000 by default
+    pub fn max_requests(mut self, max_requests: u32) -> FuzzerBuilder {
+        self.max_requests = Some(max_requests);
+        self
+    }
+
+    /// Checks run on the responses, all of them by default
+    pub fn checks(mut self, checks: impl IntoIterator<Item = Check>) -> FuzzerBuilder {
+        self.checks = Some(checks.into_iter().collect());
+        self
+    }
+
+    /// Checker run on every response after the ones of the fuzzer, e.g. to
+    /// detect findings specific to the api under test
+    pub fn checker(mut self, checker: impl Checker + 'static) -> FuzzerBuilder {
+        self.checkers.push(Box::new(checker));
+        self
+    }
+
+    /// Header sent with every request, e.g. an authorization
+    pub fn header(mut self, name: &str, value: &str) -> FuzzerBuilder {
+        self.headers.push((name.to_lowercase(), value.to_string()));
+        self
+    }
+
+    /// Fixed value of the parameters of the name in all operations, e.g. the
+    /// id of a real tenant
+    pub fn param(mut self, name: &str, value: &str) -> FuzzerBuilder {
+        self.params.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// String generation strategy, may be given more times. Defaults to
+    /// random strings.
+    pub fn strategy(mut self, strategy: Strategy) -> FuzzerBuilder {
+        self.strategies.push(strategy);
+        self
+    }
+
+    /// Status code that is not reported as finding
+    pub fn ignore_status_code(mut self, status: u16) -> FuzzerBuilder {
+        self.ignored_status_codes.push(status);
+        self
+    }
+
+    /// Reuses the ids of created resources in later requests
+    pub fn stateful(mut self, stateful: bool) -> FuzzerBuilder {
+        self.stateful = stateful;
+        self
+    }
+
+    /// Folder the findings are saved to, `results` by default
+    pub fn results_dir(mut self, results_dir: impl Into<PathBuf>) -> FuzzerBuilder {
+        self.results_dir = Some(results_dir.into());
+        self
+    }
+
+    pub fn build(self) -> Result<Fuzzer> {
+        let (schema, name) = match (self.schema, &self.spec) {
+            (Some(schema), _) => (schema, "spec".to_string()),
+            (None, Some(path)) => (
+                spec::load(path, &[])
+                    .with_context(|| format!("unable to load {}", path.display()))?,
+                path.file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| "spec".to_string()),
+            ),
+            (None, None) => bail!("no specification given"),
+        };
+        if let Some(issue) = lint::check_references(&schema)
+            .into_iter()
+            .find(|issue| issue.level == Level::Error)
+        {
+            bail!("the specification cannot be fuzzed: {}", issue);
+        }
+        let schema = schema.deref_all();
+        let urls = match self.urls.is_empty() {
+            true => vec![servers::resolve(
+                schema
+                    .servers
+                    .first()
+                    .ok_or_else(|| anyhow!("no url given and the specification has no servers"))?,
+                None,
+                &[],
+            )?],
+            false => self.urls,
+        };
+
+        let mut operations = Operations::default();
+        operations.insert(
+            "*",
+            OperationConfig {
+                checks: self.checks,
+                ..OperationConfig::default()
+            },
+        );
+        let strategies = match self.strategies.is_empty() {
+            true => vec![Strategy::Random],
+            false => self.strategies,
+        };
+        let fuzzer = Fuzzer::new(
+            vec![Api { name, schema, urls }],
+            self.ignored_status_codes,
+            Policies::default(),
+            Detectors::new(&Default::default())?,
+            None,
+            PayloadOptions {
+                extra_headers: self.headers,
+                params: self.params,
+                strategies,
+                operations,
+                stateful: self.stateful,
+                ..PayloadOptions::default()
+            },
+            Default::default(),
+        )
+        .max_requests(self.max_requests.unwrap_or(1000));
+        let fuzzer = self
+            .checkers
+            .into_iter()
+            .fold(fuzzer, |fuzzer, checker| fuzzer.checker(checker));
+        Ok(match self.results_dir {
+            Some(results_dir) => fuzzer.results_dir(results_dir),
+            None => fuzzer,
+        })
+    }
+
+    /// Fuzzes the api until the budget of requests is spent and returns the
+    /// findings
+    pub fn run(self) -> Result<Vec<Finding>> {
+        let mut fuzzer = self.build()?;
+        fuzzer.run()?;
+        Ok(fuzzer.findings().to_vec())
+    }
+}
@@ -0,0 +1,347 @@
+use std::{
+    collections::BTreeMap,
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+
+use anyhow::{Context, Result};
+use openapi_utils::ReferenceOrExt;
+use openapiv3::{OpenAPI, Operation, PathItem, ReferenceOr, Schema};
+use rand::seq::SliceRandom;
+use serde_json::{json, Value};
+use url::Url;
+
+use crate::payload::{self, Payload};
+use crate::validate;
+
+/// Callback of an operation, the server sends the requests of its path item
+/// to the url given by the runtime expression, e.g. `{$request.body#/url}`
+#[derive(Debug, Clone)]
+pub struct Callback {
+    pub name: String,
+    pub expression: String,
+    pub item: PathItem,
+}
+
+/// Callbacks declared by the operation. openapiv3 does not know the field,
+/// it ends up in the extensions.
+pub fn callbacks(operation: &Operation) -> Vec<Callback> {
+    let callbacks = match operation
+        .extensions
+        .get("callbacks")
+        .and_then(Value::as_object)
+    {
+        Some(callbacks) => callbacks,
+        None => return Vec::new(),
+    };
+    callbacks
+        .iter()
+        .filter_map(|(name, callback)| Some((name, callback.as_object()?)))
+        .flat_map(|(name, callback)| {
+            callback.iter().filter_map(move |(expression, item)| {
+                Some(Callback {
+                    name: name.clone(),
+                    expression: expression.clone(),
+                    item: serde_json::from_value(item.clone()).ok()?,
+                })
+            })
+        })
+        .collect()
+}
+
+/// Sets the value at the JSON pointer, creating the last key if needed
+fn set_pointer(value: &mut Value, pointer: &str, new: Value) {
+    if let Some(target) = value.pointer_mut(pointer) {
+        *target = new;
+        return;
+    }
+    if let Some((parent, key)) = pointer.rsplit_once('/') {
+        if let Some(Value::Object(object)) = value.pointer_mut(parent) {
+            object.insert(key.replace("~1", "/").replace("~0", "~"), new);
+        }
+    }
+}
+
+/// Puts the url of the listener where the expression of the callback points
+/// to in the request, e.g. into a property of the body or a query parameter
+pub fn inject_url(payload: &mut Payload, callback: &Callback, url: &str) {
+    let expression = callback
+        .expression
+        .trim_start_matches('{')
+        .trim_end_matches('}');
+    if let Some(pointer) = expression.strip_prefix("$request.body#") {
+        if let Some(body) = payload.body.first_mut() {
+            set_pointer(body, pointer, json!(url));
+        }
+    } else if let Some(name) = expression.strip_prefix("$request.query.") {
+        for (_, value) in payload
+            .query_params
+            .iter_mut()
+            .filter(|(param, _)| *param == name)
+        {
+            *value = url.to_string();
+        }
+    } else if let Some(name) = expression.strip_prefix("$request.header.") {
+        payload
+            .headers
+            .retain(|(header, _)| !header.eq_ignore_ascii_case(name));
+        payload.headers.push((name.to_string(), url.to_string()));
+    }
+}
+
+/// Request the listener received
+#[derive(Debug)]
+pub struct Received {
+    /// Identifies the request of the fuzzer that registered the callback
+    pub token: String,
+    pub method: String,
+    pub body: String,
+}
+
+fn read_request(stream: &mut TcpStream) -> Result<Received> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().unwrap_or_default();
+    let token = target
+        .trim_start_matches('/')
+        .split(['/', '?'])
+        .nth(1)
+        .unwrap_or_default()
+        .to_string();
+
+    let mut length = 0;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Ok(Received {
+        token,
+        method,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    })
+}
+
+fn serve(listener: TcpListener, sender: Sender<Received>) {
+    for mut stream in listener.incoming().flatten() {
+        let received = match read_request(&mut stream) {
+            Ok(received) => received,
+            Err(_) => continue,
+        };
+        // Failed deliveries exercise the retry handling of the server
+        let status = *[200, 200, 200, 204, 400, 410, 500, 503]
+            .choose(&mut rand::thread_rng())
+            .unwrap_or(&200);
+        let _ = write!(
+            stream,
+            "HTTP/1.1 {} Callback\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            status
+        );
+        if sender.send(received).is_err() {
+            return;
+        }
+    }
+}
+
+/// Local HTTP server receiving the callback requests
+#[derive(Debug)]
+pub struct Listener {
+    /// Url the server sends the callbacks to
+    pub url: Url,
+    receiver: Receiver<Received>,
+}
+
+impl Listener {
+    /// Listens on the address, callbacks are sent to `url` when it is given,
+    /// e.g. when the server reaches the fuzzer through another host name
+    pub fn start(address: &str, url: Option<Url>) -> Result<Listener> {
+        let listener = TcpListener::bind(address)
+            .with_context(|| format!("unable to listen on {}", address))?;
+        let url = match url {
+            Some(url) => url,
+            None => Url::parse(&format!("http://{}/", listener.local_addr()?))?,
+        };
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || serve(listener, sender));
+        Ok(Listener { url, receiver })
+    }
+
+    /// Url of the callback for one request of the fuzzer
+    pub fn callback_url(&self, token: &str) -> String {
+        format!("{}callback/{}", self.url, token)
+    }
+
+    /// Url of a canary the server should never fetch
+    pub fn canary_url(&self, token: &str) -> String {
+        format!("{}canary/{}", self.url, token)
+    }
+
+    pub fn received(&self) -> Vec<Received> {
+        self.receiver.try_iter().collect()
+    }
+}
+
+/// Looks up a schema of the components when the callback refers to one, the
+/// callbacks are not dereferenced with the rest of the specification
+fn resolve<'a>(schema: &'a ReferenceOr<Schema>, spec: &'a OpenAPI) -> Option<&'a Schema> {
+    match schema {
+        ReferenceOr::Item(schema) => Some(schema),
+        ReferenceOr::Reference { reference } => spec
+            .components
+            .as_ref()?
+            .schemas
+            .get(reference.rsplit('/').next()?)
+            .map(|schema| schema.to_item_ref()),
+    }
+}
+
+/// Why the received request does not conform to the callback, if it does not
+pub fn check(callback: &Callback, received: &Received, spec: &OpenAPI) -> Option<String> {
+    let method = received.method.to_uppercase();
+    let operation = match payload::operations(&callback.item)
+        .into_iter()
+        .find(|(declared, _)| *declared == method)
+    {
+        Some((_, operation)) => operation,
+        None => {
+            return Some(format!(
+                "callback {} was sent with undeclared method {}",
+                callback.name, method
+            ))
+        }
+    };
+    let schema = operation
+        .request_body
+        .as_ref()
+        .and_then(|body| match body {
+            ReferenceOr::Item(body) => Some(body),
+            ReferenceOr::Reference { .. } => None,
+        })
+        .and_then(|body| {
+            body.content
+                .iter()
+                .find(|(media_type, _)| media_type.contains("json"))
+        })
+        .and_then(|(_, media)| resolve(media.schema.as_ref()?, spec))?;
+    let body = match serde_json::from_str::<Value>(&received.body) {
+        Ok(body) => body,
+        Err(e) => {
+            return Some(format!(
+                "callback {} body is not JSON: {}",
+                callback.name, e
+            ))
+        }
+    };
+    let mut errors = Vec::new();
+    validate::validate(schema, &body, "$", &mut errors);
+    match errors.is_empty() {
+        true => None,
+        false => Some(format!(
+            "callback {} body does not conform to its schema: {}",
+            callback.name,
+            errors.join(", ")
+        )),
+    }
+}
+
+/// Callback registered by a request of the fuzzer
+#[derive(Debug)]
+pub struct Pending {
+    pub api: usize,
+    pub url: Url,
+    pub path: String,
+    pub method: String,
+    /// Payload and curl command of the request
+    pub finding: Value,
+    pub callback: Callback,
+}
+
+/// Callbacks registered by the requests of the fuzzer, which are matched to
+/// the received ones by the token in their url
+#[derive(Debug)]
+pub struct Registry {
+    listener: Listener,
+    pending: BTreeMap<String, Pending>,
+    /// Number of callbacks that were registered so far
+    count: u64,
+}
+
+impl Registry {
+    pub fn new(listener: Listener) -> Registry {
+        Registry {
+            listener,
+            pending: BTreeMap::new(),
+            count: 0,
+        }
+    }
+
+    /// Points the callbacks of the operation to the listener
+    pub fn register(
+        &mut self,
+        api: usize,
+        operation: &Operation,
+        payload: &mut Payload,
+    ) -> Result<()> {
+        // Only the most recent callbacks are kept
+        const MAX_PENDING: usize = 10_000;
+        let callbacks = callbacks(operation);
+        if callbacks.is_empty() {
+            return Ok(());
+        }
+        let mut tokens = Vec::new();
+        for callback in &callbacks {
+            self.count += 1;
+            let token = format!("{:016x}", self.count);
+            inject_url(payload, callback, &self.listener.callback_url(&token));
+            tokens.push(token);
+        }
+        let finding = json!({
+            "request_id": payload.request_id,
+            "payload": payload,
+            "curl": payload.to_curl()?,
+        });
+        for (token, callback) in tokens.into_iter().zip(callbacks) {
+            self.pending.insert(
+                token,
+                Pending {
+                    api,
+                    url: payload.url.clone(),
+                    path: payload.path.to_string(),
+                    method: payload.method.to_string(),
+                    finding: finding.clone(),
+                    callback,
+                },
+            );
+        }
+        while self.pending.len() > MAX_PENDING {
+            self.pending.pop_first();
+        }
+        Ok(())
+    }
+
+    /// Callbacks received since the last time together with the requests
+    /// that registered them
+    pub fn received(&self) -> Vec<(Received, &Pending)> {
+        self.listener
+            .received()
+            .into_iter()
+            .filter_map(|received| {
+                let pending = self.pending.get(&received.token)?;
+                Some((received, pending))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,1295 @@
+use std::{
+    fmt,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use openapi_utils::{ParameterExt, ReferenceOrExt};
+use openapiv3::{
+    APIKeyLocation, Operation, PathItem, ReferenceOr, Response as ResponseSpec, Schema, SchemaKind,
+    SecurityScheme, Type,
+};
+use rand::seq::SliceRandom;
+use serde_json::{Number, Value};
+
+use crate::checks;
+use crate::fuzzer::{Api, Check, Response};
+use crate::hooks::Hook;
+use crate::leaks::{self, Detectors};
+use crate::payload::{self, Payload};
+use crate::plugin::Plugin;
+use crate::script::Script;
+use crate::state;
+use crate::status::{Policies, Policy};
+use crate::strategy::{self, Strategy};
+use crate::validate;
+
+/// Request and response a checker looks at
+pub struct Exchange<'a> {
+    pub api: &'a Api,
+    pub payload: &'a Payload<'a>,
+    pub resp: &'a Response,
+    /// Time from sending the request until the response was read
+    pub elapsed: Duration,
+    /// Response the specification documents for the status code
+    pub documented: Option<&'a ResponseSpec>,
+    send: &'a dyn Fn(&Payload, &str) -> Result<Response>,
+}
+
+impl<'a> Exchange<'a> {
+    pub(crate) fn new(
+        api: &'a Api,
+        payload: &'a Payload<'a>,
+        resp: &'a Response,
+        elapsed: Duration,
+        send: &'a dyn Fn(&Payload, &str) -> Result<Response>,
+    ) -> Exchange<'a> {
+        Exchange {
+            api,
+            payload,
+            resp,
+            elapsed,
+            documented: checks::documented_response(payload.responses, resp.status),
+            send,
+        }
+    }
+
+    /// Sends the payload again the way the fuzzer sends its requests
+    pub fn send(&self, payload: &Payload) -> Result<Response> {
+        (self.send)(payload, payload.method)
+    }
+
+    /// Sends the payload with another method
+    pub fn send_as(&self, payload: &Payload, method: &str) -> Result<Response> {
+        (self.send)(payload, method)
+    }
+
+    /// Path item and operation of the request in the specification
+    pub fn operation(&self) -> Option<(&'a PathItem, &'a Operation)> {
+        let item = self.api.schema.paths.get(self.payload.path)?.to_item_ref();
+        payload::operations(item)
+            .into_iter()
+            .find(|(method, _)| *method == self.payload.method)
+            .map(|(_, operation)| (item, operation))
+    }
+
+    /// Methods the specification declares for the path of the request
+    pub fn declared_methods(&self) -> Vec<&'a str> {
+        self.api
+            .schema
+            .paths
+            .get(self.payload.path)
+            .map(|item| {
+                payload::operations(item.to_item_ref())
+                    .into_iter()
+                    .map(|(method, _)| method)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Finding of a checker
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    /// Kind of the finding and folder it is saved to, e.g. `error-leak`
+    pub kind: String,
+    pub reason: Option<String>,
+}
+
+impl Issue {
+    pub fn new(kind: impl Into<String>, reason: impl Into<String>) -> Issue {
+        Issue {
+            kind: kind.into(),
+            reason: Some(reason.into()),
+        }
+    }
+}
+
+/// Detector of findings in the responses of the fuzzed requests
+pub trait Checker: fmt::Debug {
+    /// Name of the checker, e.g. `error-leak`
+    fn name(&self) -> &str;
+
+    /// Check of the `checks` of the config that turns it on and off, checkers
+    /// without one always run
+    fn toggle(&self) -> Option<Check> {
+        None
+    }
+
+    /// The finding in the exchange, if there is one
+    fn check(&self, exchange: &Exchange) -> Result<Option<Issue>>;
+}
+
+impl<C: Checker + ?Sized> Checker for Box<C> {
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+
+    fn toggle(&self) -> Option<Check> {
+        (**self).toggle()
+    }
+
+    fn check(&self, exchange: &Exchange) -> Result<Option<Issue>> {
+        (**self).check(exchange)
+    }
+}
+
+/// Checkers run on every response in the order they were registered, the
+/// first finding is reported
+#[derive(Debug, Default)]
+pub struct Checkers(Vec<Box<dyn Checker>>);
+
+impl Checkers {
+    /// The checkers of the fuzzer, which do not depend on its extensions
+    pub fn builtin(
+        ignored_status_codes: Vec<u16>,
+        status_policies: Policies,
+        detectors: Detectors,
+    ) -> Checkers {
+        let mut checkers = Checkers::default();
+        checkers.register(AuthRequired);
+        checkers.register(Compression);
+        checkers.register(Status {
+            ignored_status_codes,
+            status_policies,
+        });
+        checkers.register(ErrorLeak);
+        checkers.register(Assertion);
+        checkers.register(SensitiveData(detectors));
+        checkers.register(ContentType);
+        checkers.register(Header);
+        checkers.register(Undocumented);
+        checkers.register(Truncation);
+        checkers.register(Reflection);
+        checkers.register(TimeBased);
+        checkers.register(AcceptedInvalid);
+        checkers.register(MassAssignment);
+        checkers.register(PathTraversal);
+        checkers.register(OpenRedirect);
+        checkers.register(NumericPrecision);
+        checkers.register(Idempotency);
+        checkers.register(AllowHeader);
+        checkers.register(HeadConsistency);
+        checkers
+    }
+
+    /// Runs the checker after the ones registered before it
+    pub fn register(&mut self, checker: impl Checker + 'static) {
+        self.0.push(Box::new(checker));
+    }
+
+    /// First finding of the checkers enabled for the operation
+    pub fn check(&self, exchange: &Exchange) -> Result<Option<Issue>> {
+        let config = &exchange.payload.config;
+        for checker in &self.0 {
+            if checker
+                .toggle()
+                .is_some_and(|check| !config.check_enabled(check))
+            {
+                continue;
+            }
+            if let Some(issue) = checker.check(exchange)? {
+                return Ok(Some(issue));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Successful responses without credentials to operations that declare a
+/// security requirement. GET, HEAD and OPTIONS requests sent with credentials
+/// are sent again without them.
+#[derive(Debug)]
+struct AuthRequired;
+
+impl AuthRequired {
+    /// Security schemes the operation requires one of, none when it can be
+    /// called without credentials
+    fn required_schemes<'a>(exchange: &Exchange<'a>) -> Vec<(&'a str, Option<&'a SecurityScheme>)> {
+        let security = exchange
+            .operation()
+            .and_then(|(_, operation)| operation.security.as_ref())
+            .or(exchange.api.schema.security.as_ref());
+        let requirements = match security {
+            Some(requirements) if !requirements.is_empty() => requirements,
+            _ => return Vec::new(),
+        };
+        // An empty requirement makes the security optional
+        if requirements
+            .iter()
+            .any(|requirement| requirement.is_empty())
+        {
+            return Vec::new();
+        }
+        let declared = exchange
+            .api
+            .schema
+            .components
+            .as_ref()
+            .map(|components| &components.security_schemes);
+        let mut schemes: Vec<(&str, Option<&SecurityScheme>)> = Vec::new();
+        for name in requirements
+            .iter()
+            .flat_map(|requirement| requirement.keys())
+        {
+            if schemes.iter().any(|(scheme, _)| scheme == name) {
+                continue;
+            }
+            let scheme = declared
+                .and_then(|declared| declared.get(name))
+                .and_then(|scheme| match scheme {
+                    ReferenceOr::Item(scheme) => Some(scheme),
+                    ReferenceOr::Reference { .. } => None,
+                });
+            schemes.push((name, scheme));
+        }
+        schemes
+    }
+
+    /// The payload without the Authorization and Cookie headers and the API
+    /// keys of the schemes, None when it was sent without credentials
+    fn without_credentials<'a>(
+        payload: &Payload<'a>,
+        schemes: &[(&str, Option<&SecurityScheme>)],
+    ) -> Option<Payload<'a>> {
+        let mut headers = vec!["authorization".to_string(), "cookie".to_string()];
+        let mut query_params = Vec::new();
+        for (_, scheme) in schemes {
+            match scheme {
+                Some(SecurityScheme::APIKey {
+                    location: APIKeyLocation::Header,
+                    name,
+                }) => headers.push(name.to_lowercase()),
+                Some(SecurityScheme::APIKey {
+                    location: APIKeyLocation::Query,
+                    name,
+                }) => query_params.push(name.as_str()),
+                _ => (),
+            }
+        }
+        let mut stripped = payload.clone();
+        stripped
+            .headers
+            .retain(|(name, _)| !headers.contains(&name.to_lowercase()));
+        stripped
+            .query_params
+            .retain(|(name, _)| !query_params.contains(name));
+        let sent = stripped.headers.len() != payload.headers.len()
+            || stripped.query_params.len() != payload.query_params.len();
+        sent.then_some(stripped)
+    }
+}
+
+impl Checker for AuthRequired {
+    fn name(&self) -> &str {
+        "auth-required"
+    }
+
+    fn toggle(&self) -> Option<Check> {
+        Some(Check::AuthRequired)
+    }
+
+    fn check(&self, exchange: &Exchange) -> Result<Option<Issue>> {
+        let successful = |status: u16| (200..300).contains(&status);
+        if !successful(exchange.resp.status) {
+            return Ok(None);
+        }
+        let schemes = Self::required_schemes(exchange);
+        if schemes.is_empty() {
+            return Ok(None);
+        }
+        let names = schemes
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>()
+            .join(" or ");
+        let reason = match Self::without_credentials(exchange.payload, &schemes) {
+            None => format!(
+                "status {} without credentials, the operation requires {}",
+                exchange.resp.status, names
+            ),
+            // Requests that change state are not repeated without credentials
+            Some(_) if !matches!(exchange.payload.method, "GET" | "HEAD" | "OPTIONS") => {
+                return Ok(None)
+            }
+            Some(stripped) => match exchange.send(&stripped) {
+                Ok(resp) if successful(resp.status) => format!(
+                    "status {} once the credentials were removed, the operation requires {}",
+                    resp.status, names
+                ),
+                _ => return Ok(None),
+            },
+        };
+        Ok(Some(Issue::new(self.name(), reason)))
+    }
+}
+
+/// Server errors for bodies of the gzip strategy the server cannot
+/// decompress, which it should reject with 400 or 415. A server error for a
+/// valid stream counts when the body is answered otherwise uncompressed.
+#[derive(Debug)]
+struct Compression;
+
+impl Checker for Compression {
+    fn name(&self) -> &str {
+        "compression"
+    }
+
+    fn toggle(&self) -> Option<Check> {
+        Some(Check::Compression)
+    }
+
+    fn check(&self, exchange: &Exchange) -> Result<Option<Issue>> {
+        let payload = exchange.payload;
+        let status = exchange.resp.status;
+        let compression = match payload.compression {
+            Some(compression) if status >= 500 => compression,
+            _ => return Ok(None),
+        };
+        if compression.invalid() {
+            let reason = format!(
+                "status {} for {}, instead of 400 or 415",
+                status, compression
+            );
+            return Ok(Some(Issue::new(self.name(), reason)));
+        }
+        let mut uncompressed = payload.clone();
+        uncompressed.compression = None;
+        uncompressed
+            .headers
+            .retain(|(name, _)| !name.eq_ignore_ascii_case("content-encoding"));
+        Ok(match exchange.send(&uncompressed) {
+            Ok(resp) if resp.status < 500 => {
+                let reason = format!(
+                    "status {} for {}, but {} for the same body uncompressed",
+                    status, compression, resp.status
+                );
+                Some(Issue::new(self.name(), reason))
+            }
+            _ => None,
+        })
+    }
+}
+
+/// Status codes that are not documented or indicate a server error
+#[derive(Debug)]
+struct Status {
+    ignored_status_codes: Vec<u16>,
+    status_policies: Policies,
+}
+
+impl Checker for Status {
+    fn name(&self) -> &str {
+        "status"
+    }
+
+    fn toggle(&self) -> Option<Check> {
+        Some(Check::Status)
+    }
+
+    fn check(&self, exchange: &Exchange) -> Result<Option<Issue>> {
+        let status = exchange.resp.status;
+        // the policy of the status class decides whether documented status
+        // codes are OK, ignored and expected status codes always are
+        let unexpected = match self.status_policies.for_status(status) {
+            Policy::Ignore => false,
+            Policy::Always => true,
+            Policy::Undocumented | Policy::Missing => exchange.documented.is_none(),
+        };
+        let expected = self.ignored_status_codes.contains(&status)
+            || exchange
+                .payload
+                .config
+                .expected_status_codes
+                .contains(&status);
+        Ok((unexpected && !expected).then(|| Issue {
+            kind: status.to_string(),
+            reason: None,
+        }))
+    }
+}
+
+/// Stack traces, database errors and debug pages, which are reported for any
+/// status code as many servers return them with 200
+#[derive(Debug)]
+struct ErrorLeak;
+
+impl Checker for ErrorLeak {
+    fn name(&self) -> &str {
+        "error-leak"
+    }
+
+    fn toggle(&self) -> Option<Check> {
+        Some(Check::ErrorLeak)
+    }
+
+    fn check(&self, exchange: &Exchange) -> Result<Option<Issue>> {
+        Ok(leaks::find_error_leak(&exchange.resp.body)
+            .map(|reason| Issue::new(self.name(), reason)))
+    }
+}
+
+#[derive(Debug)]
+struct Assertion;
+
+impl Checker for Assertion {
+    fn name(&self) -> &str {
+        "assertion"
+    }
+
+    fn toggle(&self) -> Option<Check> {
+        Some(Check::Assertion)
+    }
+
+    fn check(&self, exchange: &Exchange) -> Result<Option<Issue>> {
+        let assertions = &exchange.payload.config.assertions;
+        Ok(checks::failed_assertion(assertions, exchange.resp)
+            .map(|reason| Issue::new(self.name(), reason)))
+    }
+}
+
+#[derive(Debug)]
+struct SensitiveData(Detectors);
+
+impl Checker for SensitiveData {
+    fn name(&self) -> &str {
+        "sensitive-data"
+    }
+
+    fn toggle(&self) -> Option<Check> {
+        Some(Check::SensitiveData)
+    }
+
+    fn check(&self, exchange: &Exchange) -> Result<Option<Issue>> {
+        let sent = exchange.payload.string_values();
+        Ok(self
+            .0
+            .find(&exchange.resp.body, sent)
+            .map(|reason| Issue::new(self.name(), reason)))
+    }
+}
+
+#[derive(Debug)]
+struct ContentType;
+
+impl Checker for ContentType {
+    fn name(&self) -> &str {
+        "content-type"
+    }
+
+    fn toggle(&self) -> Option<Check> {
+        Some(Check::ContentType)
+    }
+
+    fn check(&self, exchange: &Exchange) -> Result<Option<Issue>> {
+        Ok(exchange
+            .documented
+            .and_then(|documented| checks::content_type_mismatch(documented, exchange.resp))
+            .map(|reason| Issue::new(self.name(), reason)))
+    }
+}
+
+#[derive(Debug)]
+struct Header;
+
+impl Checker for Header {
+    fn name(&self) -> &str {
+        "header"
+    }
+
+    fn toggle(&self) -> Option<Check> {
+        Some(Check::Header)
+    }
+
+    fn check(&self, exchange: &Exchange) -> Result<Option<Issue>> {
+        Ok(exchange
+            .documented
+            .and_then(|documented| checks::header_mismatch(documented, exchange.resp))
+            .map(|reason| Issue::new(self.name(), reason)))
+    }
+}
+
+#[derive(Debug)]
+struct Undocumented;
+
+impl Checker for Undocumented {
+    fn name(&self) -> &str {
+        "undocumented"
+    }
+
+    fn toggle(&self) -> Option<Check> {
+        Some(Check::Undocumented)
+    }
+
+    fn check(&self, exchange: &Exchange) -> Result<Option<Issue>> {
+        Ok(exchange
+            .documented
+            .and_then(|documented| checks::undocumented_fields(documented, exchange.resp))
+            .map(|reason| Issue::new(self.name(), reason)))
+    }
+}
+
+/// Values of the control strategy reflected cut short at the control
+/// character
+#[derive(Debug)]
+struct Truncation;
+
+impl Checker for Truncation {
+    fn name(&self) -> &str {
+        "truncation"
+    }
+
+    fn toggle(&self) -> Option<Check> {
+        Some(Check::Truncation)
+    }
+
+    fn check(&self, exchange: &Exchange) -> Result<Option<Issue>> {
+        if exchange.payload.strategy != Strategy::Control {
+            return Ok(None);
+        }
+        let sent = exchange.payload.string_values();
+        Ok(strategy::find_truncation(sent, &exchange.resp.body)
+            .map(|reason| Issue::new(self.name(), reason)))
+    }
+}
+
+#[derive(Debug)]
+struct Reflection;
+
+impl Checker for Reflection {
+    fn name(&self) -> &str {
+        "reflection"
+    }
+
+    fn toggle(&self) -> Option<Check> {
+        Some(Check::Reflection)
+    }
+
+    fn check(&self, exchange: &Exchange) -> Result<Option<Issue>> {
+        if exchange.payload.strategy != Strategy::Reflection {
+            return Ok(None);
+        }
+        let content_type = exchange.resp.header("content-type").unwrap_or_default();
+        let sent = exchange.payload.string_values();
+        Ok(
+            strategy::find_reflection(sent, &exchange.resp.body, content_type)
+                .map(|reason| Issue::new(self.name(), reason)),
+        )
+    }
+}
+
+/// Responses of the timing strategy delayed by its sleep primitives. Slow
+/// responses may have other causes, so the request is sent again without and
+/// with the delay, a response that is only slow with it means the server
+/// executed the sleep primitive.
+#[derive(Debug)]
+struct TimeBased;
+
+impl Checker for TimeBased {
+    fn name(&self) -> &str {
+        "time-based"
+    }
+
+    fn toggle(&self) -> Option<Check> {
+        Some(Check::TimeBased)
+    }
+
+    fn check(&self, exchange: &Exchange) -> Result<Option<Issue>> {
+        let threshold = strategy::SLEEP.mul_f64(0.8);
+        if exchange.payload.strategy != Strategy::Timing || exchange.elapsed < threshold {
+            return Ok(None);
+        }
+        let time = |payload: &Payload| {
+            let started = Instant::now();
+            exchange.send(payload).ok().map(|_| started.elapsed())
+        };
+        let mut undelayed = exchange.payload.clone();
+        if !undelayed.replace_strings(strategy::without_delay) {
+            return Ok(None);
+        }
+        let (without, with) = match (time(&undelayed), time(exchange.payload)) {
+            (Some(without), Some(with)) => (without, with),
+            _ => return Ok(None),
+        };
+        Ok((with.saturating_sub(without) >= threshold).then(|| {
+            let reason = format!(
+                "response took {:.1}s with a sleep of {}s and {:.1}s without it",
+                with.as_secs_f64(),
+                strategy::SLEEP.as_secs(),
+                without.as_secs_f64()
+            );
+            Issue::new(self.name(), reason)
+        }))
+    }
+}
+
+/// Successful responses to requests of the invalid strategy that clearly
+/// violate the schema, which the server should have rejected
+#[derive(Debug)]
+struct AcceptedInvalid;
+
+/// Type of the schema when the parameter value cannot be one of it, any
+/// value can be a string
+fn parameter_type_mismatch(schema: &Schema, value: &str) -> Option<&'static str> {
+    let (matches, expected) = match &schema.schema_kind {
+        SchemaKind::Type(Type::Integer(_)) => (value.parse::<i64>().is_ok(), "an integer"),
+        SchemaKind::Type(Type::Number(_)) => (value.parse::<f64>().is_ok(), "a number"),
+        SchemaKind::Type(Type::Boolean {}) => (matches!(value, "true" | "false"), "a boolean"),
+        _ => (true, ""),
+    };
+    (!matches).then_some(expected)
+}
+
+impl AcceptedInvalid {
+    fn violations(exchange: &Exchange) -> Vec<String> {
+        let payload = exchange.payload;
+        let (item, operation) = match exchange.operation() {
+            Some(operation) => operation,
+            None => return Vec::new(),
+        };
+        let mut violations = Vec::new();
+        let sent = payload.query_params.iter().chain(&payload.path_params);
+        for (name, value) in sent {
+            let parameter = payload::merge_parameters(item, operation)
+                .into_iter()
+                .find(|parameter| parameter.name() == *name);
+            let schema = match parameter.and_then(payload::parameter_schema) {
+                Some(schema) => schema,
+                None => continue,
+            };
+            if let Some(expected) = parameter_type_mismatch(schema, value) {
+                violations.push(format!(
+                    "parameter {}: expected {}, found {:?}",
+                    name, expected, value
+                ));
+            }
+        }
+        // Form fields are text, so only JSON bodies can have the wrong type
+        let schema = operation
+            .request_body
+            .as_ref()
+            .filter(|_| payload.form.is_none())
+            .and_then(|body| {
+                body.to_item_ref()
+                    .content
+                    .iter()
+                    .find(|(content, _)| content.contains("json"))
+            })
+            .and_then(|(_, media)| media.schema.as_ref());
+        if let (Some(schema), Some(body)) = (schema, payload.body.first()) {
+            violations.extend(validate::clear_violations(
+                schema.to_item_ref(),
+                body,
+                "body",
+            ));
+        }
+        violations
+    }
+}
+
+impl Checker for AcceptedInvalid {
+    fn name(&self) -> &str {
+        "accepted-invalid"
+    }
+
+    fn toggle(&self) -> Option<Check> {
+        Some(Check::AcceptedInvalid)
+    }
+
+    fn check(&self, exchange: &Exchange) -> Result<Option<Issue>> {
+        let status = exchange.resp.status;
+        if exchange.payload.strategy != Strategy::Invalid || !(200..300).contains(&status) {
+            return Ok(None);
+        }
+        let violations = Self::violations(exchange);
+        Ok((!violations.is_empty()).then(|| {
+            let reason = format!(
+                "status {} for input that violates the schema: {}",
+                status,
+                violations.join(", ")
+            );
+            Issue::new(self.name(), reason)
+        }))
+    }
+}
+
+/// Privileged properties of the mass-assignment strategy that the resource
+/// returns once it was created or updated with them
+#[derive(Debug)]
+struct MassAssignment;
+
+impl MassAssignment {
+    /// Privileged properties of the body that the schema of the request body
+    /// does not declare
+    fn assigned(exchange: &Exchange) -> Vec<(&'static str, Value)> {
+        let payload = exchange.payload;
+        let body = match payload.body.first() {
+            Some(body) => body,
+            None => return Vec::new(),
+        };
+        let schema = exchange
+            .operation()
+            .and_then(|(_, operation)| payload::body_schema(operation, payload.form.as_ref()));
+        let schema = match schema {
+            Some(schema) => schema,
+            None => return Vec::new(),
+        };
+        strategy::privileged_properties()
+            .into_iter()
+            .filter(|(property, value)| {
+                body.get(property) == Some(value) && !payload::declares(schema, property)
+            })
+            .collect()
+    }
+
+    /// GET request of the resource that the request updated, or created in
+    /// its collection. The id of a created resource is taken from the
+    /// Location header or the body of the response.
+    fn read_back<'a>(exchange: &Exchange<'a>) -> Option<Payload<'a>> {
+        let payload = exchange.payload;
+        let paths = &exchange.api.schema.paths;
+        let (path, path_params) = match payload.method {
+            "POST" => {
+                let (path, param) = paths.keys().find_map(|path| {
+                    let param = path
+                        .strip_prefix(payload.path)?
+                        .strip_prefix("/{")?
+                        .strip_suffix('}')?;
+                    (!param.contains('/')).then_some((path.as_str(), param))
+                })?;
+                let id = match exchange.resp.header("location") {
+                    Some(location) => location
+                        .trim_end_matches('/')
+                        .rsplit('/')
+                        .next()?
+                        .to_string(),
+                    None => {
+                        let body = serde_json::from_str::<Value>(&exchange.resp.body).ok()?;
+                        let properties = body.as_object()?.keys().cloned().collect::<Vec<_>>();
+                        state::value_to_string(body.get(state::id_property(param, &properties))?)?
+                    }
+                };
+                let mut path_params = payload.path_params.clone();
+                path_params.push((param, id));
+                (path, path_params)
+            }
+            _ => (payload.path, payload.path_params.clone()),
+        };
+        let get = paths.get(path)?.to_item_ref().get.as_ref()?;
+        let mut read = payload.clone();
+        read.method = "GET";
+        read.path = path;
+        read.path_params = path_params;
+        read.query_params.clear();
+        read.body.clear();
+        read.form = None;
+        read.framing = None;
+        read.responses = &get.responses;
+        Some(read)
+    }
+}
+
+impl Checker for MassAssignment {
+    fn name(&self) -> &str {
+        "mass-assignment"
+    }
+
+    fn toggle(&self) -> Option<Check> {
+        Some(Check::MassAssignment)
+    }
+
+    fn check(&self, exchange: &Exchange) -> Result<Option<Issue>> {
+        let payload = exchange.payload;
+        let successful = |status: u16| (200..300).contains(&status);
+        if payload.strategy != Strategy::MassAssignment
+            || !matches!(payload.method, "POST" | "PUT" | "PATCH")
+            || !successful(exchange.resp.status)
+        {
+            return Ok(None);
+        }
+        let assigned = Self::assigned(exchange);
+        if assigned.is_empty() {
+            return Ok(None);
+        }
+        let read = match Self::read_back(exchange) {
+            Some(read) => read,
+            None => return Ok(None),
+        };
+        let body = match exchange.send(&read) {
+            Ok(resp) if successful(resp.status) => resp.body,
+            _ => return Ok(None),
+        };
+        let body = match serde_json::from_str::<Value>(&body) {
+            Ok(body) => body,
+            Err(_) => return Ok(None),
+        };
+        // Resources may be wrapped in an envelope such as `{"data": {...}}`
+        let objects = std::iter::once(&body)
+            .chain(
+                body.as_object()
+                    .into_iter()
+                    .flat_map(|object| object.values()),
+            )
+            .filter(|value| value.is_object())
+            .collect::<Vec<_>>();
+        let persisted = assigned
+            .iter()
+            .filter(|(property, value)| {
+                objects
+                    .iter()
+                    .any(|object| object.get(property) == Some(value))
+            })
+            .map(|(property, value)| format!("{}={}", property, value))
+            .collect::<Vec<_>>();
+        Ok((!persisted.is_empty()).then(|| {
+            let reason = format!(
+                "properties that the request body does not declare are persisted, {} returned by GET {}",
+                persisted.join(", "),
+                read.request_url()
+                    .map(|url| url.path().to_string())
+                    .unwrap_or_else(|_| read.path.to_string())
+            );
+            Issue::new(self.name(), reason)
+        }))
+    }
+}
+
+/// Path parameters of the traversal strategy that are answered with the
+/// content of a file, or successfully where a plain value is not
+#[derive(Debug)]
+struct PathTraversal;
+
+impl Checker for PathTraversal {
+    fn name(&self) -> &str {
+        "path-traversal"
+    }
+
+    fn toggle(&self) -> Option<Check> {
+        Some(Check::PathTraversal)
+    }
+
+    fn check(&self, exchange: &Exchange) -> Result<Option<Issue>> {
+        let payload = exchange.payload;
+        let status = exchange.resp.status;
+        let successful = |status: u16| (200..300).contains(&status);
+        if payload.strategy != Strategy::Traversal || !successful(status) {
+            return Ok(None);
+        }
+        let probes = payload
+            .path_params
+            .iter()
+            .filter(|(_, value)| strategy::is_traversal(value))
+            .map(|(name, value)| format!("{} {:?}", name, value))
+            .collect::<Vec<_>>();
+        if probes.is_empty() {
+            return Ok(None);
+        }
+        if let Some(file) = strategy::find_file_content(&exchange.resp.body) {
+            let reason = format!(
+                "status {} with the content of {} for path parameter {}",
+                status,
+                file,
+                probes.join(", ")
+            );
+            return Ok(Some(Issue::new(self.name(), reason)));
+        }
+        let mut plain = payload.clone();
+        for (_, value) in plain.path_params.iter_mut() {
+            if strategy::is_traversal(value) {
+                *value = "openapi-fuzzer".to_string();
+            }
+        }
+        Ok(match exchange.send(&plain) {
+            Ok(resp) if !successful(resp.status) => {
+                let reason = format!(
+                    "status {} for path parameter {}, but {} for a plain value",
+                    status,
+                    probes.join(", "),
+                    resp.status
+                );
+                Some(Issue::new(self.name(), reason))
+            }
+            _ => None,
+        })
+    }
+}
+
+// Host of the urls put into redirect parameters, reserved so that it never
+// resolves
+const REDIRECT_HOST: &str = "openapi-fuzzer.example";
+
+// Urls of the host, some of them in shapes that only naive checks of the
+// server take for relative ones
+const REDIRECT_PROBES: &[&str] = &[
+    "https://openapi-fuzzer.example/",
+    "//openapi-fuzzer.example/",
+    "/\\openapi-fuzzer.example/",
+    "https:openapi-fuzzer.example",
+    "\\\\openapi-fuzzer.example/",
+];
+
+/// Whether a parameter or property of the name probably holds where the
+/// server redirects to, e.g. `returnTo` or `redirect_uri`
+fn redirect_parameter(name: &str) -> bool {
+    let name = name.to_lowercase();
+    matches!(name.as_str(), "next" | "to" | "back" | "r" | "u")
+        || [
+            "redirect", "return", "continue", "dest", "goto", "target", "forward", "url", "uri",
+            "callback",
+        ]
+        .iter()
+        .any(|part| name.contains(part))
+}
+
+/// Replaces the strings of properties hinting at a redirect target in the
+/// value and its nested objects and arrays by the probe
+fn redirect_properties(value: &mut Value, pointer: String, probe: &str, found: &mut Vec<String>) {
+    match value {
+        Value::Array(items) => {
+            for (index, item) in items.iter_mut().enumerate() {
+                redirect_properties(item, format!("{}/{}", pointer, index), probe, found);
+            }
+        }
+        Value::Object(properties) => {
+            for (property, value) in properties.iter_mut() {
+                let nested = format!(
+                    "{}/{}",
+                    pointer,
+                    property.replace('~', "~0").replace('/', "~1")
+                );
+                if value.is_string() && redirect_parameter(property) {
+                    *value = Value::String(probe.to_string());
+                    found.push(format!("body property {}", nested));
+                } else {
+                    redirect_properties(value, nested, probe, found);
+                }
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Redirects to an external host put into the redirect parameters of
+/// operations that document redirects. A request answered with a redirect is
+/// sent again with a url of the host in its redirect query parameters and body
+/// properties, nested ones included.
+#[derive(Debug)]
+struct OpenRedirect;
+
+impl Checker for OpenRedirect {
+    fn name(&self) -> &str {
+        "open-redirect"
+    }
+
+    fn toggle(&self) -> Option<Check> {
+        Some(Check::OpenRedirect)
+    }
+
+    fn check(&self, exchange: &Exchange) -> Result<Option<Issue>> {
+        let payload = exchange.payload;
+        // Redirects are only left unfollowed for operations documenting them,
+        // and only requests that redirect are probed
+        if !checks::redirects(payload.responses) || !(300..400).contains(&exchange.resp.status) {
+            return Ok(None);
+        }
+        let probe = *REDIRECT_PROBES
+            .choose(&mut rand::thread_rng())
+            .expect("at least one probe");
+        let mut redirected = payload.clone();
+        let mut parameters = Vec::new();
+        for (name, value) in redirected.query_params.iter_mut() {
+            if redirect_parameter(name) {
+                *value = probe.to_string();
+                parameters.push(format!("query parameter {}", name));
+            }
+        }
+        if let Some(body) = redirected.body.first_mut() {
+            redirect_properties(body, String::new(), probe, &mut parameters);
+        }
+        if parameters.is_empty() {
+            return Ok(None);
+        }
+        let resp = match exchange.send(&redirected) {
+            Ok(resp) => resp,
+            Err(_) => return Ok(None),
+        };
+        let location = match resp.header("location") {
+            Some(location) if (300..400).contains(&resp.status) => location,
+            _ => return Ok(None),
+        };
+        // Locations are resolved the way browsers do, which take backslashes
+        // for slashes
+        let target = redirected.full_url()?.join(location).ok();
+        if target.as_ref().and_then(|url| url.host_str()) != Some(REDIRECT_HOST) {
+            return Ok(None);
+        }
+        let reason = format!(
+            "status {} redirects to {} for {} {:?}",
+            resp.status,
+            location,
+            parameters.join(", "),
+            probe
+        );
+        Ok(Some(Issue::new(self.name(), reason)))
+    }
+}
+
+/// Numbers of the value by their JSON pointer
+fn numbers<'v>(value: &'v Value, pointer: String, found: &mut Vec<(String, &'v Number)>) {
+    match value {
+        Value::Number(number) => found.push((pointer, number)),
+        Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                numbers(item, format!("{}/{}", pointer, index), found);
+            }
+        }
+        Value::Object(properties) => {
+            for (property, value) in properties {
+                let property = property.replace('~', "~0").replace('/', "~1");
+                numbers(value, format!("{}/{}", pointer, property), found);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// How the number returned for the one that was sent lost its value, only
+/// changes that a narrower type explains count, other ones may be deliberate
+fn precision_loss(sent: &Number, returned: &Number) -> Option<&'static str> {
+    let integer = |number: &Number| {
+        number
+            .as_i64()
+            .map(i128::from)
+            .or_else(|| number.as_u64().map(i128::from))
+    };
+    if let Some(sent) = integer(sent) {
+        if let Some(returned) = integer(returned) {
+            let narrowed = [
+                (sent as i32 as i128, "wrapped around to 32 bits"),
+                (sent as u32 as i128, "wrapped around to unsigned 32 bits"),
+                (sent as i16 as i128, "wrapped around to 16 bits"),
+                (sent as u16 as i128, "wrapped around to unsigned 16 bits"),
+                (
+                    sent.clamp(i32::MIN.into(), i32::MAX.into()),
+                    "clamped to 32 bits",
+                ),
+                (sent as f64 as i128, "rounded to a double"),
+                (sent as f32 as i128, "rounded to a single precision float"),
+            ];
+            return match sent == returned {
+                true => None,
+                false => narrowed
+                    .iter()
+                    .find(|(value, _)| *value == returned)
+                    .map(|(_, loss)| *loss),
+            };
+        }
+        // The same integer may be written as a float, e.g. `5.0` for `5`
+        let returned = returned.as_f64()?;
+        return if returned.fract() == 0.0 && returned as i128 == sent {
+            None
+        } else if sent as f64 == returned {
+            Some("rounded to a double")
+        } else if sent as f32 as f64 == returned {
+            Some("rounded to a single precision float")
+        } else {
+            None
+        };
+    }
+    let (sent, returned_float) = (sent.as_f64()?, returned.as_f64()?);
+    if sent == returned_float {
+        None
+    } else if sent as f32 as f64 == returned_float {
+        Some("rounded to a single precision float")
+    } else if integer(returned).is_some() && sent.trunc() == returned_float {
+        Some("truncated to an integer")
+    } else {
+        None
+    }
+}
+
+/// Numbers of the body that a successful response echoes at the same place
+/// with a value that a narrower type explains, e.g. an int64 wrapped around to
+/// 32 bits or rounded to a double
+#[derive(Debug)]
+struct NumericPrecision;
+
+impl Checker for NumericPrecision {
+    fn name(&self) -> &str {
+        "numeric-precision"
+    }
+
+    fn toggle(&self) -> Option<Check> {
+        Some(Check::NumericPrecision)
+    }
+
+    fn check(&self, exchange: &Exchange) -> Result<Option<Issue>> {
+        let status = exchange.resp.status;
+        let body = match exchange.payload.body.first() {
+            Some(body) if (200..300).contains(&status) => body,
+            _ => return Ok(None),
+        };
+        let echoed = match serde_json::from_str::<Value>(&exchange.resp.body) {
+            Ok(echoed) => echoed,
+            Err(_) => return Ok(None),
+        };
+        let mut sent = Vec::new();
+        numbers(body, String::new(), &mut sent);
+        let mismatches = sent
+            .into_iter()
+            .filter_map(|(pointer, number)| {
+                let returned = match echoed.pointer(&pointer)? {
+                    Value::Number(returned) => returned,
+                    _ => return None,
+                };
+                let loss = precision_loss(number, returned)?;
+                Some(format!(
+                    "body#{} sent as {} was returned as {}, {}",
+                    pointer, number, returned, loss
+                ))
+            })
+            .collect::<Vec<_>>();
+        if mismatches.is_empty() {
+            return Ok(None);
+        }
+        let reason = format!("status {} with {}", status, mismatches.join("; "));
+        Ok(Some(Issue::new(self.name(), reason)))
+    }
+}
+
+/// Safe methods are sent again and have to be answered the same way
+#[derive(Debug)]
+struct Idempotency;
+
+impl Checker for Idempotency {
+    fn name(&self) -> &str {
+        "idempotency"
+    }
+
+    fn toggle(&self) -> Option<Check> {
+        Some(Check::Idempotency)
+    }
+
+    fn check(&self, exchange: &Exchange) -> Result<Option<Issue>> {
+        if !matches!(exchange.payload.method, "GET" | "HEAD") {
+            return Ok(None);
+        }
+        let repeated = exchange.send(exchange.payload).ok();
+        Ok(repeated
+            .and_then(|repeated| checks::idempotency_mismatch(exchange.resp, &repeated))
+            .map(|reason| Issue::new(self.name(), reason)))
+    }
+}
+
+#[derive(Debug)]
+struct AllowHeader;
+
+impl Checker for AllowHeader {
+    fn name(&self) -> &str {
+        "allow-header"
+    }
+
+    fn toggle(&self) -> Option<Check> {
+        Some(Check::AllowHeader)
+    }
+
+    fn check(&self, exchange: &Exchange) -> Result<Option<Issue>> {
+        if exchange.payload.method != "OPTIONS" {
+            return Ok(None);
+        }
+        Ok(
+            checks::allow_mismatch(&exchange.declared_methods(), exchange.resp)
+                .map(|reason| Issue::new(self.name(), reason)),
+        )
+    }
+}
+
+/// A HEAD request is compared with the GET request of the same url when the
+/// path declares both
+#[derive(Debug)]
+struct HeadConsistency;
+
+impl Checker for HeadConsistency {
+    fn name(&self) -> &str {
+        "head-consistency"
+    }
+
+    fn toggle(&self) -> Option<Check> {
+        Some(Check::HeadConsistency)
+    }
+
+    fn check(&self, exchange: &Exchange) -> Result<Option<Issue>> {
+        if exchange.payload.method != "HEAD" || !exchange.declared_methods().contains(&"GET") {
+            return Ok(None);
+        }
+        let get = exchange.send_as(exchange.payload, "GET").ok();
+        Ok(get
+            .and_then(|get| checks::head_mismatch(exchange.resp, &get))
+            .map(|reason| Issue::new(self.name(), reason)))
+    }
+}
+
+/// Findings of the `post_response` hook of the script
+#[derive(Debug)]
+pub(crate) struct ScriptCheck(pub(crate) Rc<Script>);
+
+impl Checker for ScriptCheck {
+    fn name(&self) -> &str {
+        "script"
+    }
+
+    fn toggle(&self) -> Option<Check> {
+        Some(Check::Script)
+    }
+
+    fn check(&self, exchange: &Exchange) -> Result<Option<Issue>> {
+        Ok(self
+            .0
+            .post_response(exchange.payload, exchange.resp)?
+            .map(|reason| Issue::new(self.name(), reason)))
+    }
+}
+
+/// Findings of the `check` function of a plugin
+#[derive(Debug)]
+pub(crate) struct PluginCheck(pub(crate) Rc<Plugin>);
+
+impl Checker for PluginCheck {
+    fn name(&self) -> &str {
+        "plugin"
+    }
+
+    fn toggle(&self) -> Option<Check> {
+        Some(Check::Plugin)
+    }
+
+    fn check(&self, exchange: &Exchange) -> Result<Option<Issue>> {
+        Ok(self
+            .0
+            .check(exchange.payload, exchange.resp)?
+            .map(|reason| Issue::new(self.name(), reason)))
+    }
+}
+
+/// Findings of the post hook command
+#[derive(Debug)]
+pub(crate) struct HookCheck(pub(crate) Hook);
+
+impl Checker for HookCheck {
+    fn name(&self) -> &str {
+        "hook"
+    }
+
+    fn toggle(&self) -> Option<Check> {
+        Some(Check::Hook)
+    }
+
+    fn check(&self, exchange: &Exchange) -> Result<Option<Issue>> {
+        Ok(self
+            .0
+            .post_response(exchange.payload, exchange.resp)?
+            .map(|reason| Issue::new(self.name(), reason)))
+    }
+}
@@ -0,0 +1,73 @@
+use std::{
+    collections::BTreeSet,
+    fs::{self, File},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::fuzzer::{Finding, Stats};
+use crate::schedule::Progress;
+
+/// Name of the checkpoint file in the results directory
+pub const CHECKPOINT_FILE: &str = "checkpoint.json";
+
+/// Operation of a specification, by the index of the specification, the
+/// path and the method
+pub type OperationKey = (usize, String, String);
+
+/// State of one operation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperationState {
+    pub operation: OperationKey,
+    pub progress: Progress,
+    /// Status codes the operation was answered with and the number of
+    /// responses
+    pub received: BTreeSet<u16>,
+    pub responses: u32,
+}
+
+/// State of an interrupted campaign, which is saved periodically so that it
+/// can be resumed instead of starting from scratch
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub requests: u32,
+    pub elapsed: Duration,
+    pub operations: Vec<OperationState>,
+    pub stats: Stats,
+    pub findings: Vec<Finding>,
+    /// Requests of each operation that increased the coverage of the server
+    pub corpus: Vec<(OperationKey, Vec<Value>)>,
+}
+
+impl Checkpoint {
+    fn path(dir: &Path) -> PathBuf {
+        dir.join(CHECKPOINT_FILE)
+    }
+
+    /// Reads the checkpoint of the results directory
+    pub fn load(dir: &Path) -> Result<Checkpoint> {
+        let path = Checkpoint::path(dir);
+        let file =
+            File::open(&path).with_context(|| format!("unable to open {}", path.display()))?;
+        serde_json::from_reader(file)
+            .with_context(|| format!("invalid checkpoint {}", path.display()))
+    }
+
+    /// Writes the checkpoint to the results directory, the previous one is
+    /// only replaced once the new one is complete
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        fs::create_dir_all(dir)?;
+        let path = Checkpoint::path(dir);
+        let partial = path.with_extension("json.partial");
+        let file = File::create(&partial)
+            .with_context(|| format!("unable to create {}", partial.display()))?;
+        serde_json::to_writer(file, self)?;
+        fs::rename(&partial, &path)
+            .with_context(|| format!("unable to write {}", path.display()))?;
+        Ok(())
+    }
+}
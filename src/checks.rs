@@ -0,0 +1,541 @@
+use std::collections::BTreeSet;
+
+use openapi_utils::ReferenceOrExt;
+use openapiv3::{
+    AdditionalProperties, ParameterSchemaOrContent, ReferenceOr, Response as ResponseSpec,
+    Responses, Schema, SchemaKind, StatusCode, Type,
+};
+use serde_json::Value;
+
+use crate::config::Assertion;
+use crate::fuzzer::Response;
+use crate::validate;
+
+/// Finds the response documented for the status code. An exact code takes
+/// precedence over a range such as `4XX`, which takes precedence over `default`.
+pub fn documented_response(responses: &Responses, status: u16) -> Option<&ResponseSpec> {
+    responses
+        .responses
+        .get(&StatusCode::Code(status))
+        .or_else(|| responses.responses.get(&StatusCode::Range(status / 100)))
+        .or(responses.default.as_ref())
+        .map(|response| response.to_item_ref())
+}
+
+/// Whether the responses document a redirect, e.g. `302` or `3XX`
+pub(crate) fn redirects(responses: &Responses) -> bool {
+    responses.responses.keys().any(|status| match status {
+        StatusCode::Code(code) => (300..400).contains(code),
+        StatusCode::Range(range) => *range == 3,
+    })
+}
+
+/// Media type without parameters in lower case, e.g. `application/json` for
+/// `application/json; charset=utf-8`
+pub(crate) fn essence(media_type: &str) -> String {
+    media_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase()
+}
+
+/// Whether the media type matches a declared one, which may be a range such
+/// as `application/*` or `*/*`
+fn media_type_matches(declared: &str, actual: &str) -> bool {
+    let declared = essence(declared);
+    match declared.strip_suffix("/*") {
+        Some("*") => true,
+        Some(kind) => actual.split('/').next() == Some(kind),
+        None => declared == actual,
+    }
+}
+
+/// Reports responses whose Content-Type is not one of the media types declared
+/// for them, e.g. an HTML error page of a JSON API
+pub fn content_type_mismatch(spec: &ResponseSpec, resp: &Response) -> Option<String> {
+    if spec.content.is_empty() {
+        return None;
+    }
+    let declared = spec.content.keys().cloned().collect::<Vec<_>>().join(", ");
+    match resp.header("content-type") {
+        Some(content_type) => {
+            let actual = essence(content_type);
+            match spec
+                .content
+                .keys()
+                .any(|media_type| media_type_matches(media_type, &actual))
+            {
+                true => None,
+                false => Some(format!(
+                    "content type {} is not one of {}",
+                    actual, declared
+                )),
+            }
+        }
+        None if !resp.body.is_empty() => Some(format!(
+            "content type is missing, expected one of {}",
+            declared
+        )),
+        None => None,
+    }
+}
+
+/// Interprets a header value according to the type of its schema, such that
+/// it can be validated like JSON
+fn header_value(schema: &Schema, raw: &str) -> Value {
+    let raw = raw.trim();
+    match &schema.schema_kind {
+        SchemaKind::Type(Type::Integer(_)) | SchemaKind::Type(Type::Number(_)) => {
+            serde_json::from_str::<serde_json::Number>(raw)
+                .map(Value::Number)
+                .unwrap_or_else(|_| Value::String(raw.to_string()))
+        }
+        SchemaKind::Type(Type::Boolean {}) => match raw {
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            _ => Value::String(raw.to_string()),
+        },
+        SchemaKind::Type(Type::Array(array)) => match &array.items {
+            ReferenceOr::Item(items) => Value::Array(
+                raw.split(',')
+                    .map(|item| header_value(items, item))
+                    .collect(),
+            ),
+            ReferenceOr::Reference { .. } => Value::String(raw.to_string()),
+        },
+        _ => Value::String(raw.to_string()),
+    }
+}
+
+/// Reports headers declared for the response which are required but missing
+/// or whose value does not conform to their schema
+pub fn header_mismatch(spec: &ResponseSpec, resp: &Response) -> Option<String> {
+    for (name, header) in &spec.headers {
+        let header = match header {
+            ReferenceOr::Item(header) => header,
+            ReferenceOr::Reference { .. } => continue,
+        };
+        // Content-Type is described by the media types instead
+        if name.eq_ignore_ascii_case("content-type") {
+            continue;
+        }
+        let value = match resp.header(&name.to_lowercase()) {
+            Some(value) => value,
+            None if header.required => return Some(format!("required header {} is missing", name)),
+            None => continue,
+        };
+        if let ParameterSchemaOrContent::Schema(ReferenceOr::Item(schema)) = &header.format {
+            let mut errors = Vec::new();
+            validate::validate(schema, &header_value(schema, value), name, &mut errors);
+            if !errors.is_empty() {
+                return Some(format!("header {}", errors.join(", ")));
+            }
+        }
+    }
+    None
+}
+
+/// Collects the properties declared by the schema and its compositions.
+/// Returns whether other properties are allowed explicitly or cannot be
+/// known because of a reference.
+fn declared_properties<'s>(
+    schema: &'s Schema,
+    properties: &mut Vec<(&'s str, &'s Schema)>,
+) -> bool {
+    match &schema.schema_kind {
+        SchemaKind::Type(Type::Object(object)) => collect_properties(
+            &object.properties,
+            &object.additional_properties,
+            properties,
+        ),
+        SchemaKind::Any(any) => {
+            collect_properties(&any.properties, &any.additional_properties, properties)
+        }
+        SchemaKind::AllOf { all_of: schemas }
+        | SchemaKind::OneOf { one_of: schemas }
+        | SchemaKind::AnyOf { any_of: schemas } => {
+            let mut open = false;
+            for schema in schemas {
+                match schema {
+                    ReferenceOr::Item(schema) => open |= declared_properties(schema, properties),
+                    ReferenceOr::Reference { .. } => open = true,
+                }
+            }
+            open
+        }
+        _ => false,
+    }
+}
+
+fn collect_properties<'s>(
+    declared: impl IntoIterator<Item = (&'s String, &'s ReferenceOr<Box<Schema>>)>,
+    additional: &Option<AdditionalProperties>,
+    properties: &mut Vec<(&'s str, &'s Schema)>,
+) -> bool {
+    let mut open = matches!(
+        additional,
+        Some(AdditionalProperties::Schema(_)) | Some(AdditionalProperties::Any(true))
+    );
+    for (name, property) in declared {
+        match property {
+            ReferenceOr::Item(property) => properties.push((name, property)),
+            ReferenceOr::Reference { .. } => open = true,
+        }
+    }
+    open
+}
+
+fn items_schema(schema: &Schema) -> Option<&Schema> {
+    let items = match &schema.schema_kind {
+        SchemaKind::Type(Type::Array(array)) => &array.items,
+        SchemaKind::Any(any) => any.items.as_ref()?,
+        _ => return None,
+    };
+    match items {
+        ReferenceOr::Item(items) => Some(items),
+        ReferenceOr::Reference { .. } => None,
+    }
+}
+
+fn find_undocumented(schema: &Schema, value: &Value, location: &str, found: &mut BTreeSet<String>) {
+    match value {
+        Value::Object(object) => {
+            let mut properties = Vec::new();
+            let open = declared_properties(schema, &mut properties);
+            for (name, value) in object {
+                let location = format!("{}.{}", location, name);
+                let declared = properties
+                    .iter()
+                    .filter(|(property, _)| property == name)
+                    .collect::<Vec<_>>();
+                // Objects without any declared property are free-form
+                if declared.is_empty() && !open && !properties.is_empty() {
+                    found.insert(location);
+                } else if let Some((_, schema)) = declared.first() {
+                    find_undocumented(schema, value, &location, found);
+                }
+            }
+        }
+        Value::Array(array) => {
+            if let Some(items) = items_schema(schema) {
+                for item in array {
+                    find_undocumented(items, item, &format!("{}[*]", location), found);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Reports properties of a JSON response that are not declared in its schema,
+/// which may be drift of the specification or data that was exposed by
+/// accident
+pub fn undocumented_fields(spec: &ResponseSpec, resp: &Response) -> Option<String> {
+    let content_type = essence(resp.header("content-type")?);
+    if !content_type.contains("json") {
+        return None;
+    }
+    let schema = spec
+        .content
+        .iter()
+        .find(|(media_type, _)| media_type_matches(media_type, &content_type))
+        .and_then(|(_, media)| match &media.schema {
+            Some(ReferenceOr::Item(schema)) => Some(schema),
+            _ => None,
+        })?;
+    let body = serde_json::from_str::<Value>(&resp.body).ok()?;
+
+    let mut found = BTreeSet::new();
+    find_undocumented(schema, &body, "$", &mut found);
+    match found.is_empty() {
+        true => None,
+        false => Some(format!(
+            "undocumented properties {}",
+            found.into_iter().collect::<Vec<_>>().join(", ")
+        )),
+    }
+}
+
+/// Why the response does not meet the assertion, if it does not
+fn assertion_failure(assertion: &Assertion, resp: &Response) -> Option<String> {
+    if !assertion.status.is_empty() && !assertion.status.contains(&resp.status) {
+        return None;
+    }
+    if let Some(text) = assertion
+        .body_contains
+        .as_ref()
+        .filter(|text| !resp.body.contains(text.as_str()))
+    {
+        return Some(format!("body does not contain {:?}", text));
+    }
+    if let Some(text) = assertion
+        .body_not_contains
+        .as_ref()
+        .filter(|text| resp.body.contains(text.as_str()))
+    {
+        return Some(format!("body contains {:?}", text));
+    }
+    if let Some(pattern) = assertion
+        .body_matches
+        .as_ref()
+        .filter(|pattern| !pattern.0.is_match(&resp.body))
+    {
+        return Some(format!("body does not match {:?}", pattern.0.as_str()));
+    }
+    if let Some(found) = assertion
+        .body_not_matches
+        .as_ref()
+        .and_then(|pattern| pattern.0.find(&resp.body))
+    {
+        return Some(format!("body matches at {:?}", found.as_str()));
+    }
+    if let Some(name) = assertion
+        .header_present
+        .as_ref()
+        .filter(|name| resp.header(&name.to_lowercase()).is_none())
+    {
+        return Some(format!("header {} is missing", name));
+    }
+    if let Some(name) = assertion
+        .header_absent
+        .as_ref()
+        .filter(|name| resp.header(&name.to_lowercase()).is_some())
+    {
+        return Some(format!("header {} is present", name));
+    }
+    None
+}
+
+/// Checks the response against the user defined assertions
+pub fn failed_assertion(assertions: &[Assertion], resp: &Response) -> Option<String> {
+    assertions.iter().find_map(|assertion| {
+        assertion_failure(assertion, resp)
+            .map(|failure| format!("assertion {} failed, {}", assertion.name, failure))
+    })
+}
+
+/// First place where the structure of two JSON values differs. Scalars only
+/// have to agree on their type, timestamps and similar values may change
+/// between two responses.
+fn shape_difference(first: &Value, second: &Value, location: &str) -> Option<String> {
+    match (first, second) {
+        (Value::Object(first), Value::Object(second)) => {
+            if let Some(key) = first
+                .keys()
+                .chain(second.keys())
+                .find(|key| first.contains_key(*key) != second.contains_key(*key))
+            {
+                return Some(format!(
+                    "{}.{} is only in one of the responses",
+                    location, key
+                ));
+            }
+            first.iter().find_map(|(key, value)| {
+                shape_difference(value, &second[key], &format!("{}.{}", location, key))
+            })
+        }
+        (Value::Array(first), Value::Array(second)) if first.len() != second.len() => {
+            Some(format!(
+                "{} has {} and {} items",
+                location,
+                first.len(),
+                second.len()
+            ))
+        }
+        (Value::Array(first), Value::Array(second)) => first
+            .iter()
+            .zip(second)
+            .enumerate()
+            .find_map(|(i, (first, second))| {
+                shape_difference(first, second, &format!("{}[{}]", location, i))
+            }),
+        (Value::Null, Value::Null)
+        | (Value::Bool(_), Value::Bool(_))
+        | (Value::Number(_), Value::Number(_))
+        | (Value::String(_), Value::String(_)) => None,
+        _ => Some(format!("{} changed its type", location)),
+    }
+}
+
+/// Why the responses to the same request of a safe method differ, which
+/// reveals hidden side effects or nondeterministic failures
+pub fn idempotency_mismatch(first: &Response, second: &Response) -> Option<String> {
+    if first.status != second.status {
+        return Some(format!(
+            "the same request was answered with {} and then {}",
+            first.status, second.status
+        ));
+    }
+    if let (Ok(first), Ok(second)) = (
+        serde_json::from_str::<Value>(&first.body),
+        serde_json::from_str::<Value>(&second.body),
+    ) {
+        return shape_difference(&first, &second, "$").map(|difference| {
+            format!("the same request was answered differently, {}", difference)
+        });
+    }
+    match lengths_differ(&first.body, &second.body) {
+        true => Some(format!(
+            "the same request was answered with bodies of {} and {} bytes",
+            first.body.len(),
+            second.body.len()
+        )),
+        false => None,
+    }
+}
+
+/// Bodies that are not JSON may contain dates or tokens, only a large
+/// difference of their length counts
+fn lengths_differ(first: &str, second: &str) -> bool {
+    let (shorter, longer) = match first.len() < second.len() {
+        true => (first.len(), second.len()),
+        false => (second.len(), first.len()),
+    };
+    longer - shorter > 64 && longer - shorter > longer / 10
+}
+
+/// How the response to a request changed since the snapshot of its response
+/// was taken, e.g. by a new version of the api
+pub fn regression(snapshot: &Response, resp: &Response) -> Option<String> {
+    if snapshot.status != resp.status {
+        return Some(format!(
+            "the status code changed from {} to {}",
+            snapshot.status, resp.status
+        ));
+    }
+    let content_type = |resp: &Response| resp.header("content-type").map(essence);
+    if content_type(snapshot) != content_type(resp) {
+        return Some(format!(
+            "the content type changed from {} to {}",
+            content_type(snapshot).as_deref().unwrap_or("none"),
+            content_type(resp).as_deref().unwrap_or("none")
+        ));
+    }
+    if let (Ok(before), Ok(after)) = (
+        serde_json::from_str::<Value>(&snapshot.body),
+        serde_json::from_str::<Value>(&resp.body),
+    ) {
+        return shape_difference(&before, &after, "$")
+            .map(|difference| format!("the body changed, {}", difference));
+    }
+    match lengths_differ(&snapshot.body, &resp.body) {
+        true => Some(format!(
+            "the body changed from {} to {} bytes",
+            snapshot.body.len(),
+            resp.body.len()
+        )),
+        false => None,
+    }
+}
+
+/// Why the response to a HEAD request is inconsistent with the response to the
+/// GET request of the same url, which should only differ by not having a body
+pub fn head_mismatch(head: &Response, get: &Response) -> Option<String> {
+    if head.status != get.status {
+        return Some(format!(
+            "HEAD was answered with {} and GET with {}",
+            head.status, get.status
+        ));
+    }
+    if !head.body.is_empty() {
+        return Some(format!(
+            "HEAD was answered with a body of {} bytes",
+            head.body.len()
+        ));
+    }
+    if let (Some(head_type), Some(get_type)) =
+        (head.header("content-type"), get.header("content-type"))
+    {
+        if essence(head_type) != essence(get_type) {
+            return Some(format!(
+                "HEAD was answered with content type {} and GET with {}",
+                head_type, get_type
+            ));
+        }
+    }
+    let length = head
+        .header("content-length")?
+        .trim()
+        .parse::<usize>()
+        .ok()?;
+    // Compressed or chunked bodies of GET do not tell their length
+    if get.header("content-encoding").is_some() || get.header("content-length").is_none() {
+        return None;
+    }
+    match length == get.body.len() {
+        true => None,
+        false => Some(format!(
+            "HEAD announced a body of {} bytes and GET sent {} bytes",
+            length,
+            get.body.len()
+        )),
+    }
+}
+
+/// Why the methods announced in the Allow or Access-Control-Allow-Methods
+/// header of an OPTIONS response differ from the methods declared for the
+/// path. OPTIONS and HEAD of a path with GET are allowed without being
+/// declared, as many servers implement them for every path.
+pub fn allow_mismatch(declared: &[&str], resp: &Response) -> Option<String> {
+    ["allow", "access-control-allow-methods"]
+        .iter()
+        .find_map(|name| {
+            let announced = resp
+                .header(name)?
+                .split(',')
+                .map(|method| method.trim().to_uppercase())
+                .filter(|method| !method.is_empty())
+                .collect::<BTreeSet<_>>();
+            if announced.contains("*") {
+                return None;
+            }
+            let missing = declared
+                .iter()
+                .filter(|method| **method != "OPTIONS" && !announced.contains(**method))
+                .copied()
+                .collect::<Vec<_>>();
+            let undeclared = announced
+                .iter()
+                .filter(|method| !declared.contains(&method.as_str()))
+                .filter(|method| {
+                    !(*method == "OPTIONS" || *method == "HEAD" && declared.contains(&"GET"))
+                })
+                .map(String::as_str)
+                .collect::<Vec<_>>();
+            match (missing.is_empty(), undeclared.is_empty()) {
+                (true, true) => None,
+                (false, true) => Some(format!("{} does not list {}", name, missing.join(", "))),
+                (true, false) => Some(format!(
+                    "{} lists undeclared {}",
+                    name,
+                    undeclared.join(", ")
+                )),
+                (false, false) => Some(format!(
+                    "{} does not list {} and lists undeclared {}",
+                    name,
+                    missing.join(", "),
+                    undeclared.join(", ")
+                )),
+            }
+        })
+}
+
+/// Why the response to a preflight request from a hostile origin is a CORS
+/// misconfiguration, other sites could read the responses of the api
+pub fn cors_misconfiguration(origin: &str, resp: &Response) -> Option<String> {
+    let allowed = resp.header("access-control-allow-origin")?.trim();
+    let credentials = resp
+        .header("access-control-allow-credentials")
+        .is_some_and(|value| value.trim().eq_ignore_ascii_case("true"));
+    match allowed {
+        "*" if credentials => Some("wildcard origin is allowed with credentials".to_string()),
+        allowed if allowed == origin && credentials => Some(format!(
+            "origin {} is reflected and allowed with credentials",
+            origin
+        )),
+        allowed if allowed == origin => Some(format!("origin {} is reflected", origin)),
+        _ => None,
+    }
+}
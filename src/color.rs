@@ -0,0 +1,51 @@
+use std::{
+    env,
+    io::{self, IsTerminal},
+};
+
+use crate::severity::Severity;
+
+/// Colors of the lines printed to the terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    /// Serious findings, failed requests and errors of the specification
+    Red,
+    /// Other findings and warnings
+    Yellow,
+    /// Progress and summaries
+    Cyan,
+}
+
+impl Color {
+    fn code(self) -> &'static str {
+        match self {
+            Color::Red => "31",
+            Color::Yellow => "33",
+            Color::Cyan => "36",
+        }
+    }
+
+    /// Color of findings of the severity, high and critical ones stand out
+    /// the most
+    pub fn for_severity(severity: Severity) -> Color {
+        match severity {
+            Severity::High | Severity::Critical => Color::Red,
+            _ => Color::Yellow,
+        }
+    }
+}
+
+/// Whether colors are turned off with a non-empty `NO_COLOR`, see
+/// https://no-color.org
+pub fn disabled() -> bool {
+    env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
+
+/// Text in the color when it is printed to a terminal and colors are not
+/// turned off
+pub fn paint(color: Color, text: impl AsRef<str>) -> String {
+    match disabled() || !io::stderr().is_terminal() {
+        true => text.as_ref().to_string(),
+        false => format!("\x1b[{}m{}\x1b[0m", color.code(), text.as_ref()),
+    }
+}
@@ -0,0 +1,88 @@
+use std::{fmt, io::Write};
+
+use flate2::write::GzEncoder;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+// Sizes of the header and the trailer of a gzip stream without optional fields
+const HEADER_SIZE: usize = 10;
+const TRAILER_SIZE: usize = 8;
+
+/// How the body of a request of the gzip strategy is compressed, it is
+/// announced with `Content-Encoding: gzip` in every case
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Compression {
+    /// Valid gzip stream of the body
+    Gzip,
+    /// First half of the gzip stream
+    Truncated,
+    /// Gzip stream with bytes of its compressed data flipped
+    Corrupted,
+    /// Gzip stream whose trailer has a wrong checksum of the body
+    BadChecksum,
+    /// Body that is not compressed at all
+    Uncompressed,
+}
+
+const COMPRESSIONS: &[Compression] = &[
+    Compression::Gzip,
+    Compression::Truncated,
+    Compression::Corrupted,
+    Compression::BadChecksum,
+    Compression::Uncompressed,
+];
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = match self {
+            Compression::Gzip => "a valid gzip body",
+            Compression::Truncated => "a truncated gzip body",
+            Compression::Corrupted => "a corrupted gzip body",
+            Compression::BadChecksum => "a gzip body with a wrong checksum",
+            Compression::Uncompressed => "an uncompressed body announced as gzip",
+        };
+        write!(f, "{}", description)
+    }
+}
+
+impl Compression {
+    pub fn random() -> Compression {
+        *COMPRESSIONS
+            .choose(&mut rand::thread_rng())
+            .expect("at least one compression")
+    }
+
+    /// Whether a server has to reject the body as it cannot decompress it
+    pub fn invalid(self) -> bool {
+        self != Compression::Gzip
+    }
+
+    /// The body compressed this way, the same body always gives the same
+    /// bytes so that curl commands send what the fuzzer sent
+    pub fn compress(self, body: &[u8]) -> Vec<u8> {
+        if self == Compression::Uncompressed {
+            return body.to_vec();
+        }
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder
+            .write_all(body)
+            .expect("unable to compress in memory");
+        let mut gzip = encoder.finish().expect("unable to compress in memory");
+        let data = HEADER_SIZE..gzip.len() - TRAILER_SIZE;
+        match self {
+            Compression::Truncated => gzip.truncate(gzip.len() / 2),
+            Compression::Corrupted => {
+                let mut indices = vec![data.start, (data.start + data.end) / 2, data.end - 1];
+                // Short data would be flipped back
+                indices.dedup();
+                for index in indices {
+                    gzip[index] ^= 0xff;
+                }
+            }
+            Compression::BadChecksum => gzip[data.end] ^= 0xff,
+            Compression::Gzip | Compression::Uncompressed => (),
+        }
+        gzip
+    }
+}
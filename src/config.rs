@@ -0,0 +1,391 @@
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use openapiv3::Operation;
+use rand::{seq::SliceRandom, Rng};
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use url::Url;
+
+use crate::fuzzer::Check;
+use crate::jsonpath::JsonPath;
+use crate::overlay;
+use crate::spec::{self, Format};
+
+/// Replaces `${NAME}` by the value of the environment variable `NAME`, or by
+/// `default` for `${NAME:-default}` when it is not set
+pub fn substitute_env(value: &str) -> Result<String, String> {
+    let mut result = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("unterminated variable in `{}`", value))?;
+        let variable = &rest[start + 2..start + end];
+        let (name, default) = match variable.find(":-") {
+            Some(index) => (&variable[..index], Some(&variable[index + 2..])),
+            None => (variable, None),
+        };
+        result.push_str(&rest[..start]);
+        match (env::var(name), default) {
+            (Ok(value), _) => result.push_str(&value),
+            (Err(_), Some(default)) => result.push_str(default),
+            (Err(_), None) => return Err(format!("environment variable {} is not set", name)),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Substitutes environment variables in all strings of the document
+fn substitute_all(value: &mut Value) -> Result<()> {
+    match value {
+        Value::String(string) => *string = substitute_env(string).map_err(|e| anyhow!(e))?,
+        Value::Array(array) => array.iter_mut().try_for_each(substitute_all)?,
+        Value::Object(object) => object.values_mut().try_for_each(substitute_all)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Reads a TOML, YAML or JSON document
+fn read_document(path: &Path) -> Result<Value> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("unable to read {}", path.display()))?;
+    let is_toml =
+        matches!(path.extension(), Some(extension) if extension.eq_ignore_ascii_case("toml"));
+    match is_toml {
+        true => toml::from_str::<toml::Value>(&content)
+            .map_err(anyhow::Error::from)
+            .and_then(|document| Ok(serde_json::to_value(document)?)),
+        false => spec::parse_document(&content, Format::detect(&path.to_string_lossy(), &content)),
+    }
+}
+
+/// Regular expression given as a string
+#[derive(Debug, Clone)]
+pub struct Pattern(pub Regex);
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        Regex::new(&pattern)
+            .map(Pattern)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Condition that every response of the operation has to meet, all the given
+/// conditions have to hold
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Assertion {
+    /// Name of the assertion in the reason of its findings
+    pub name: String,
+    /// Status codes of the responses that are checked, all of them when empty
+    #[serde(default)]
+    pub status: Vec<u16>,
+    pub body_contains: Option<String>,
+    pub body_not_contains: Option<String>,
+    pub body_matches: Option<Pattern>,
+    pub body_not_matches: Option<Pattern>,
+    pub header_present: Option<String>,
+    pub header_absent: Option<String>,
+}
+
+/// Settings of a single operation overriding the global ones
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct OperationConfig {
+    /// Maximum number of requests per second sent to the operation
+    pub rate_limit: Option<f64>,
+    /// Requests the operation gets before the scheduler may skip it
+    pub min_requests: Option<u32>,
+    /// Requests after which the operation is not fuzzed anymore
+    pub max_requests: Option<u32>,
+    /// Seconds spent on the operation before the scheduler may skip it
+    pub min_time: Option<f64>,
+    /// Seconds after which the operation is not fuzzed anymore
+    pub max_time: Option<f64>,
+    /// Headers sent with every request, e.g. a different authorization, an
+    /// empty value removes the header
+    pub headers: BTreeMap<String, String>,
+    /// Fixed values of parameters by their name
+    pub params: BTreeMap<String, String>,
+    /// Status codes that are not reported as findings
+    pub expected_status_codes: Vec<u16>,
+    /// Checks run on the responses, all of them when not given
+    pub checks: Option<Vec<Check>>,
+    /// Custom conditions checked on every response
+    pub assertions: Vec<Assertion>,
+    /// Values selected from successful responses that are used for the
+    /// parameters of the same name in later requests
+    pub extract: BTreeMap<String, JsonPath>,
+}
+
+impl OperationConfig {
+    /// Fills in the settings of `other` which are not set yet
+    fn merge(&mut self, other: &OperationConfig) {
+        self.rate_limit = self.rate_limit.or(other.rate_limit);
+        self.min_requests = self.min_requests.or(other.min_requests);
+        self.max_requests = self.max_requests.or(other.max_requests);
+        self.min_time = self.min_time.or(other.min_time);
+        self.max_time = self.max_time.or(other.max_time);
+        for (name, value) in &other.headers {
+            self.headers
+                .entry(name.to_lowercase())
+                .or_insert_with(|| value.clone());
+        }
+        for (name, value) in &other.params {
+            self.params
+                .entry(name.clone())
+                .or_insert_with(|| value.clone());
+        }
+        self.expected_status_codes
+            .extend(other.expected_status_codes.iter().copied());
+        if self.checks.is_none() {
+            self.checks = other.checks.clone();
+        }
+        self.assertions.extend(other.assertions.iter().cloned());
+        for (name, path) in &other.extract {
+            self.extract
+                .entry(name.clone())
+                .or_insert_with(|| path.clone());
+        }
+    }
+
+    /// Whether the check runs on the responses of the operation, all checks
+    /// do unless `checks` lists them
+    pub fn check_enabled(&self, check: Check) -> bool {
+        match &self.checks {
+            Some(checks) => checks.contains(&check),
+            None => true,
+        }
+    }
+}
+
+/// Overrides keyed by operationId, `METHOD /path` or `/path`, `*` applies
+/// to all operations
+#[derive(Debug, Default, Deserialize)]
+pub struct Operations(BTreeMap<String, OperationConfig>);
+
+impl Operations {
+    /// Adds the settings for the operationId, `METHOD /path`, `/path` or `*`
+    pub fn insert(&mut self, key: impl Into<String>, config: OperationConfig) {
+        self.0.insert(key.into(), config);
+    }
+
+    /// Settings of the operation, where the ones given for its operationId
+    /// take precedence over its method and path, which take precedence over
+    /// its path and finally over the ones of all operations
+    pub fn for_operation(
+        &self,
+        path: &str,
+        method: &str,
+        operation: &Operation,
+    ) -> OperationConfig {
+        let mut config = OperationConfig::default();
+        let keys = [
+            operation.operation_id.clone(),
+            Some(format!("{} {}", method, path)),
+            Some(path.to_string()),
+            Some("*".to_string()),
+        ];
+        for key in keys.iter().flatten() {
+            if let Some(overrides) = self.0.get(key) {
+                config.merge(overrides);
+            }
+        }
+        config
+    }
+
+    /// Runs only the checks that are enabled for all operations unless they
+    /// list their own, the disabled ones are removed from all of them
+    pub fn select_checks(&mut self, enabled: Option<Vec<Check>>, disabled: &[Check]) {
+        let all = self.0.entry("*".to_string()).or_default();
+        if let Some(enabled) = enabled {
+            all.checks = Some(enabled);
+        }
+        if disabled.is_empty() {
+            return;
+        }
+        all.checks.get_or_insert_with(Check::all);
+        for config in self.0.values_mut() {
+            if let Some(checks) = &mut config.checks {
+                checks.retain(|check| !disabled.contains(check));
+            }
+        }
+    }
+}
+
+/// Settings of the config file, named like the command line flags
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    pub spec: Vec<PathBuf>,
+    pub overlay: Vec<PathBuf>,
+    pub skip_deprecated: bool,
+    pub only_deprecated: bool,
+    pub tag: Vec<String>,
+    pub exclude_tag: Vec<String>,
+    pub include_path: Vec<String>,
+    pub exclude_path: Vec<String>,
+    pub methods: Vec<String>,
+    pub operation: Vec<String>,
+    pub url: Vec<Url>,
+    pub server: Option<usize>,
+    pub all_servers: bool,
+    pub server_var: BTreeMap<String, String>,
+    pub stateful: bool,
+    pub callback_listen: Option<String>,
+    pub callback_url: Option<Url>,
+    pub oob_listen: Option<String>,
+    pub oob_url: Option<Url>,
+    #[serde(alias = "ignore-status")]
+    pub ignore_status_code: Vec<u16>,
+    pub status_policy: BTreeMap<String, String>,
+    pub checks: Vec<String>,
+    pub disable_check: Vec<String>,
+    pub severity: BTreeMap<String, String>,
+    pub fail_on: Option<String>,
+    pub header: BTreeMap<String, String>,
+    pub param: BTreeMap<String, String>,
+    pub strategy: Vec<String>,
+    pub max_body_bytes: Option<usize>,
+    pub max_body_elements: Option<usize>,
+    pub nesting_depth: Option<usize>,
+    pub until_coverage: Option<String>,
+    pub timeout: Option<u64>,
+    /// A string or a number, as `1.1` is one in YAML and TOML
+    pub http_version: Option<Value>,
+    pub warmup: Option<u32>,
+    pub verbose: bool,
+    pub coverage_url: Option<Url>,
+    pub target_cmd: Option<String>,
+    pub target_log: Option<PathBuf>,
+    pub target_log_cmd: Option<String>,
+    pub artifact_cmd: Option<String>,
+    pub db: Option<PathBuf>,
+    pub fingerprint: Vec<String>,
+    pub drift: Option<PathBuf>,
+    pub spec_old: Option<PathBuf>,
+    pub seeds: Option<PathBuf>,
+    pub script: Option<PathBuf>,
+    pub plugin: Vec<PathBuf>,
+    pub values: Option<PathBuf>,
+    pub pre_hook: Option<String>,
+    pub post_hook: Option<String>,
+    /// Patterns of sensitive data detected in responses by their name
+    pub sensitive_data: BTreeMap<String, String>,
+    /// Requests deleting the resources created during stateful fuzzing by
+    /// their item path, e.g. `POST /pets/{petId}/archive`, an empty one keeps
+    /// them. Resources are deleted by the DELETE of their item path otherwise.
+    pub cleanup: BTreeMap<String, String>,
+    pub operations: Operations,
+}
+
+impl Config {
+    /// Loads a TOML, YAML or JSON config file. The settings of the profile
+    /// take precedence over the ones outside of `profiles`. Relative paths
+    /// are relative to the file.
+    pub fn load(path: &Path, profile: Option<&str>) -> Result<Config> {
+        let mut document =
+            read_document(path).with_context(|| format!("invalid config {}", path.display()))?;
+        let profiles = document
+            .as_object_mut()
+            .and_then(|document| document.remove("profiles"))
+            .unwrap_or_default();
+        if let Some(name) = profile {
+            let settings = profiles.get(name).ok_or_else(|| {
+                let names = profiles
+                    .as_object()
+                    .map(|profiles| profiles.keys().cloned().collect::<Vec<_>>())
+                    .unwrap_or_default();
+                anyhow!(
+                    "no profile {} in {}, available are: {}",
+                    name,
+                    path.display(),
+                    names.join(", ")
+                )
+            })?;
+            overlay::merge_patch(&mut document, settings);
+        }
+        substitute_all(&mut document)
+            .with_context(|| format!("invalid config {}", path.display()))?;
+        let mut config: Config = serde_json::from_value(document)
+            .with_context(|| format!("invalid config {}", path.display()))?;
+
+        let base = path.parent().unwrap_or_else(|| Path::new(""));
+        for file in config
+            .spec
+            .iter_mut()
+            .chain(config.overlay.iter_mut())
+            .chain(config.script.iter_mut())
+            .chain(config.plugin.iter_mut())
+            .chain(config.values.iter_mut())
+            .chain(config.target_log.iter_mut())
+            .chain(config.db.iter_mut())
+            .chain(config.drift.iter_mut())
+            .chain(config.spec_old.iter_mut())
+            .chain(config.seeds.iter_mut())
+        {
+            if file.is_relative() && file.as_os_str() != "-" {
+                *file = base.join(&file);
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// Known-good values of parameters by their name or `operationId.name`, e.g.
+/// existing tenant ids, which get requests past the existence checks of the
+/// api
+#[derive(Debug, Default, Clone)]
+pub struct Values(BTreeMap<String, Vec<String>>);
+
+impl Values {
+    /// Loads a TOML, YAML or JSON file mapping the parameters to lists of
+    /// values
+    pub fn load(path: &Path) -> Result<Values> {
+        let error = || format!("invalid values file {}", path.display());
+        let document: BTreeMap<String, Vec<Value>> =
+            serde_json::from_value(read_document(path).with_context(error)?).with_context(error)?;
+        Ok(Values(
+            document
+                .into_iter()
+                .map(|(name, values)| {
+                    let values = values
+                        .into_iter()
+                        .map(|value| match value {
+                            Value::String(value) => value,
+                            value => value.to_string(),
+                        })
+                        .collect();
+                    (name, values)
+                })
+                .collect(),
+        ))
+    }
+
+    /// One of the values of the parameter, the ones given for the operation
+    /// take precedence. Some requests still fuzz the parameter.
+    pub fn sample(&self, operation: &Operation, name: &str) -> Option<String> {
+        let mut rng = rand::thread_rng();
+        if self.0.is_empty() || rng.gen_bool(0.1) {
+            return None;
+        }
+        operation
+            .operation_id
+            .as_ref()
+            .and_then(|id| self.0.get(&format!("{}.{}", id, name)))
+            .or_else(|| self.0.get(name))
+            .and_then(|values| values.choose(&mut rng))
+            .cloned()
+    }
+}
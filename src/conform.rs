@@ -0,0 +1,172 @@
+use std::borrow::Borrow;
+
+use openapi_utils::ReferenceOrExt;
+use openapiv3::{
+    OpenAPI, ReferenceOr, Schema, SchemaKind, StringFormat, Type, VariantOrUnknownOrEmpty,
+};
+use rand::{seq::SliceRandom, Rng};
+use serde_json::{json, Map, Value};
+
+use crate::budget::{BodyBudget, Remaining};
+use crate::payload;
+
+// Schemas nested deeper than this are generated as null
+const MAX_DEPTH: usize = 8;
+
+/// Resolves a reference to the schemas of the components, parts of the
+/// specification such as webhooks are not dereferenced with the rest of it
+fn resolve<'a, T>(schema: &'a ReferenceOr<T>, spec: &'a OpenAPI) -> Option<&'a Schema>
+where
+    T: Borrow<Schema>,
+{
+    match schema {
+        ReferenceOr::Item(schema) => Some(schema.borrow()),
+        ReferenceOr::Reference { reference } => spec
+            .components
+            .as_ref()?
+            .schemas
+            .get(reference.rsplit('/').next()?)
+            .map(|schema| schema.to_item_ref()),
+    }
+}
+
+fn random_text(rng: &mut impl Rng, min: usize, max: usize) -> String {
+    const ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+    let length = rng.gen_range(min..=max.max(min));
+    (0..length)
+        .map(|_| *ALPHABET.choose(rng).unwrap_or(&b'a') as char)
+        .collect()
+}
+
+fn string_value(format: &VariantOrUnknownOrEmpty<StringFormat>, min: usize, max: usize) -> String {
+    let mut rng = rand::thread_rng();
+    match format {
+        VariantOrUnknownOrEmpty::Item(StringFormat::Date) => "2021-03-14".to_string(),
+        VariantOrUnknownOrEmpty::Item(StringFormat::DateTime) => "2021-03-14T15:09:26Z".to_string(),
+        VariantOrUnknownOrEmpty::Item(StringFormat::Byte) => "ZnV6emVy".to_string(),
+        VariantOrUnknownOrEmpty::Unknown(format) => match format.as_str() {
+            "email" => format!("{}@example.com", random_text(&mut rng, 3, 8)),
+            "uuid" => format!(
+                "{:08x}-{:04x}-4{:03x}-8{:03x}-{:012x}",
+                rng.gen::<u32>(),
+                rng.gen::<u16>(),
+                rng.gen_range(0..0x1000),
+                rng.gen_range(0..0x1000),
+                rng.gen_range(0..0x1_0000_0000_0000_u64)
+            ),
+            "uri" | "url" => format!("https://example.com/{}", random_text(&mut rng, 1, 8)),
+            "hostname" => "example.com".to_string(),
+            "ipv4" => "192.0.2.1".to_string(),
+            "ipv6" => "2001:db8::1".to_string(),
+            _ => random_text(&mut rng, min, max),
+        },
+        _ => random_text(&mut rng, min, max),
+    }
+}
+
+/// Generates a value that conforms to the schema, examples, defaults and
+/// enumerations are preferred over generated values
+pub fn generate(schema: &Schema, spec: &OpenAPI) -> Value {
+    generate_nested(schema, spec, &Remaining::new(BodyBudget::default()), 0)
+}
+
+/// Generates a value that conforms to a schema of the specification whose
+/// references were resolved already, within what is left of the budget
+pub(crate) fn generate_resolved(schema: &Schema, budget: &Remaining) -> Value {
+    generate_nested(schema, &OpenAPI::default(), budget, 0)
+}
+
+fn generate_nested(schema: &Schema, spec: &OpenAPI, budget: &Remaining, depth: usize) -> Value {
+    let mut rng = rand::thread_rng();
+    if depth > MAX_DEPTH {
+        return Value::Null;
+    }
+    if let Some(value) = schema
+        .schema_data
+        .example
+        .as_ref()
+        .or(schema.schema_data.default.as_ref())
+    {
+        return value.clone();
+    }
+    let nested = |schema: &ReferenceOr<Box<Schema>>| {
+        resolve(schema, spec)
+            .map(|schema| generate_nested(schema, spec, budget, depth + 1))
+            .unwrap_or(Value::Null)
+    };
+    match &schema.schema_kind {
+        SchemaKind::Type(Type::String(string)) => match string.enumeration.choose(&mut rng) {
+            Some(value) => json!(value),
+            None => json!(budget.truncate(string_value(
+                &string.format,
+                string.min_length.unwrap_or(1),
+                string.max_length.unwrap_or(12).min(64)
+            ))),
+        },
+        SchemaKind::Type(Type::Integer(integer)) => match integer.enumeration.choose(&mut rng) {
+            Some(value) => json!(value),
+            None => {
+                let minimum = integer.minimum.unwrap_or(0) + integer.exclusive_minimum as i64;
+                let maximum = integer.maximum.unwrap_or(minimum.saturating_add(1000))
+                    - integer.exclusive_maximum as i64;
+                let value = rng.gen_range(minimum..=maximum.max(minimum));
+                match integer.multiple_of.filter(|multiple| *multiple > 0) {
+                    Some(multiple) => json!(value - value.rem_euclid(multiple)),
+                    None => json!(value),
+                }
+            }
+        },
+        SchemaKind::Type(Type::Number(number)) => match number.enumeration.choose(&mut rng) {
+            Some(value) => json!(value),
+            None => {
+                let minimum = number.minimum.unwrap_or(0.0);
+                let maximum = number.maximum.unwrap_or(minimum + 1000.0).max(minimum);
+                json!(minimum + (maximum - minimum) * rng.gen_range(0.01..0.99))
+            }
+        },
+        SchemaKind::Type(Type::Boolean {}) => json!(rng.gen::<bool>()),
+        SchemaKind::Type(Type::Array(array)) => {
+            let items = resolve(&array.items, spec);
+            (0..payload::array_length(array, 3))
+                .take_while(|_| budget.element(""))
+                .map(|_| {
+                    items
+                        .map(|items| generate_nested(items, spec, budget, depth + 1))
+                        .unwrap_or(Value::Null)
+                })
+                .collect()
+        }
+        SchemaKind::Type(Type::Object(object)) => Value::Object(
+            object
+                .properties
+                .iter()
+                .take_while(|(name, _)| budget.element(name))
+                .map(|(name, schema)| (name.clone(), nested(schema)))
+                .collect(),
+        ),
+        SchemaKind::Any(any) => Value::Object(
+            any.properties
+                .iter()
+                .take_while(|(name, _)| budget.element(name))
+                .map(|(name, schema)| (name.clone(), nested(schema)))
+                .collect(),
+        ),
+        SchemaKind::OneOf { one_of: schemas } | SchemaKind::AnyOf { any_of: schemas } => schemas
+            .choose(&mut rng)
+            .and_then(|schema| resolve(schema, spec))
+            .map(|schema| generate_nested(schema, spec, budget, depth + 1))
+            .unwrap_or(Value::Null),
+        SchemaKind::AllOf { all_of } => {
+            // The properties of all the schemas are merged
+            let mut merged = Map::new();
+            for schema in all_of.iter().filter_map(|schema| resolve(schema, spec)) {
+                match generate_nested(schema, spec, budget, depth + 1) {
+                    Value::Object(object) => merged.extend(object),
+                    value if all_of.len() == 1 => return value,
+                    _ => {}
+                }
+            }
+            Value::Object(merged)
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{self, File},
+    path::Path,
+    str::FromStr,
+};
+
+use anyhow::{Context, Result};
+use openapiv3::{Responses, StatusCode};
+use serde::Serialize;
+
+/// Responses received for an operation compared to its documented ones
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Observed {
+    documented: Vec<StatusCode>,
+    /// Any status code is documented by a `default` response
+    default: bool,
+    received: BTreeSet<u16>,
+    responses: u32,
+}
+
+impl Observed {
+    pub(crate) fn new(responses: &Responses) -> Observed {
+        Observed {
+            documented: responses.responses.keys().cloned().collect(),
+            default: responses.default.is_some(),
+            ..Observed::default()
+        }
+    }
+
+    pub(crate) fn receive(&mut self, status: u16) {
+        self.received.insert(status);
+        self.responses += 1;
+    }
+
+    /// Whether a response with the documented status code or range was
+    /// received
+    pub(crate) fn has_received(&self, status: &StatusCode) -> bool {
+        match *status {
+            StatusCode::Code(code) => self.received.contains(&code),
+            StatusCode::Range(class) => self.received.iter().any(|code| code / 100 == class),
+        }
+    }
+
+    pub(crate) fn documented(&self) -> &[StatusCode] {
+        &self.documented
+    }
+
+    pub(crate) fn received(&self) -> (&BTreeSet<u16>, u32) {
+        (&self.received, self.responses)
+    }
+
+    /// Continues with the responses of an earlier campaign
+    pub(crate) fn restore(&mut self, received: BTreeSet<u16>, responses: u32) {
+        self.received = received;
+        self.responses = responses;
+    }
+}
+
+/// Coverage after which fuzzing ends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Goal {
+    /// Every operation received this many responses
+    Responses(u32),
+    /// Every documented status code was received
+    Statuses,
+}
+
+impl FromStr for Goal {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "statuses" => Ok(Goal::Statuses),
+            count => count.parse().map(Goal::Responses).map_err(|_| {
+                format!(
+                    "unknown coverage goal `{}`, expected a number of responses or `statuses`",
+                    s
+                )
+            }),
+        }
+    }
+}
+
+impl Goal {
+    pub(crate) fn is_reached(&self, observed: &Observed) -> bool {
+        match *self {
+            Goal::Responses(count) => observed.responses >= count,
+            Goal::Statuses => observed
+                .documented
+                .iter()
+                .all(|status| observed.has_received(status)),
+        }
+    }
+}
+
+/// Coverage of a single operation
+#[derive(Debug, Clone, Serialize)]
+pub struct OperationCoverage {
+    pub path: String,
+    pub method: String,
+    /// Number of responses received
+    pub responses: u32,
+    /// Documented status codes or ranges such as `4XX` and whether they
+    /// were received
+    pub documented: BTreeMap<String, bool>,
+    /// Received status codes the operation does not document
+    pub undocumented: BTreeSet<u16>,
+}
+
+impl OperationCoverage {
+    pub(crate) fn new(path: &str, method: &str, observed: &Observed) -> OperationCoverage {
+        let documented = observed
+            .documented
+            .iter()
+            .map(|status| (status.to_string(), observed.has_received(status)))
+            .collect();
+        let undocumented = match observed.default {
+            true => BTreeSet::new(),
+            false => observed
+                .received
+                .iter()
+                .filter(|&&code| {
+                    !observed.documented.iter().any(|status| match *status {
+                        StatusCode::Code(documented) => documented == code,
+                        StatusCode::Range(class) => code / 100 == class,
+                    })
+                })
+                .copied()
+                .collect(),
+        };
+        OperationCoverage {
+            path: path.to_string(),
+            method: method.to_string(),
+            responses: observed.responses,
+            documented,
+            undocumented,
+        }
+    }
+}
+
+/// Which operations were exercised and which of their documented status
+/// codes were received
+#[derive(Debug, Clone, Serialize)]
+pub struct Coverage {
+    pub operations: Vec<OperationCoverage>,
+}
+
+impl Coverage {
+    /// Number of exercised operations and received documented status codes
+    /// together with their totals
+    pub fn summary(&self) -> String {
+        let exercised = self
+            .operations
+            .iter()
+            .filter(|operation| operation.responses > 0)
+            .count();
+        let documented = self
+            .operations
+            .iter()
+            .map(|operation| operation.documented.len())
+            .sum::<usize>();
+        let received = self
+            .operations
+            .iter()
+            .flat_map(|operation| operation.documented.values())
+            .filter(|received| **received)
+            .count();
+        format!(
+            "coverage: {} of {} operations exercised, {} of {} documented status codes received",
+            exercised,
+            self.operations.len(),
+            received,
+            documented
+        )
+    }
+
+    /// One line for every operation that was not exercised or missed some
+    /// of its documented status codes
+    pub fn gaps(&self) -> Vec<String> {
+        self.operations
+            .iter()
+            .filter_map(|operation| {
+                if operation.responses == 0 {
+                    return Some(format!(
+                        "  {} {}: never exercised",
+                        operation.method, operation.path
+                    ));
+                }
+                let never = operation
+                    .documented
+                    .iter()
+                    .filter(|(_, received)| !**received)
+                    .map(|(status, _)| status.as_str())
+                    .collect::<Vec<_>>();
+                if never.is_empty() {
+                    return None;
+                }
+                Some(format!(
+                    "  {} {}: {} responses, never received {}",
+                    operation.method,
+                    operation.path,
+                    operation.responses,
+                    never.join(", ")
+                ))
+            })
+            .collect()
+    }
+
+    /// Writes the coverage matrix as JSON
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file =
+            File::create(path).with_context(|| format!("unable to create {}", path.display()))?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,280 @@
+use std::{
+    path::Path,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::Value;
+
+use crate::fuzzer::Finding;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY,
+    started INTEGER NOT NULL,
+    finished INTEGER,
+    specs TEXT NOT NULL,
+    requests INTEGER NOT NULL DEFAULT 0
+);
+CREATE TABLE IF NOT EXISTS findings (
+    id INTEGER PRIMARY KEY,
+    fingerprint TEXT NOT NULL UNIQUE,
+    api TEXT NOT NULL,
+    method TEXT NOT NULL,
+    path TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    reason TEXT,
+    first_run INTEGER NOT NULL REFERENCES runs(id),
+    last_run INTEGER NOT NULL REFERENCES runs(id),
+    occurrences INTEGER NOT NULL DEFAULT 1,
+    file TEXT NOT NULL,
+    details TEXT NOT NULL,
+    response TEXT
+);
+CREATE TABLE IF NOT EXISTS requests (
+    id INTEGER PRIMARY KEY,
+    run INTEGER NOT NULL REFERENCES runs(id),
+    api TEXT NOT NULL,
+    method TEXT NOT NULL,
+    path TEXT NOT NULL,
+    status INTEGER,
+    reason TEXT NOT NULL,
+    request TEXT NOT NULL
+);
+";
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Path of the operation findings are told apart by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FingerprintPath {
+    /// Findings of all paths of a method are the same
+    None,
+    /// The path as declared, e.g. `/pets/{petId}`
+    Template,
+    /// The path with its parameters filled in, e.g. `/pets/42`
+    Concrete,
+}
+
+/// Attributes of a finding that decide whether it is the same as another
+/// one, findings always need the same specification, method and kind of
+/// check to be the same
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fingerprint {
+    /// The reason without digits, which usually are generated values
+    pub reason: bool,
+    /// Status codes, otherwise findings of undocumented or server error
+    /// status codes are the same whatever the code is
+    pub status: bool,
+    pub path: FingerprintPath,
+}
+
+impl Default for Fingerprint {
+    fn default() -> Fingerprint {
+        Fingerprint {
+            reason: true,
+            status: true,
+            path: FingerprintPath::Template,
+        }
+    }
+}
+
+/// Comma separated attributes out of `reason`, `status` and `path` or
+/// `concrete-path`, e.g. `status,concrete-path`, or `none` for none of them
+impl FromStr for Fingerprint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fingerprint = Fingerprint {
+            reason: false,
+            status: false,
+            path: FingerprintPath::None,
+        };
+        for attribute in s.split(',') {
+            match attribute.trim() {
+                "reason" => fingerprint.reason = true,
+                "status" => fingerprint.status = true,
+                "path" => fingerprint.path = FingerprintPath::Template,
+                "concrete-path" => fingerprint.path = FingerprintPath::Concrete,
+                "none" => {}
+                attribute => {
+                    return Err(format!(
+                        "unknown fingerprint attribute `{}`, expected reason, status, path or \
+                         concrete-path",
+                        attribute
+                    ))
+                }
+            }
+        }
+        Ok(fingerprint)
+    }
+}
+
+impl Fingerprint {
+    /// Hash of the attributes of the finding, the concrete path is taken from
+    /// the payload in its details. The FNV-1a hash of the default attributes
+    /// stays the same across versions of the fuzzer.
+    fn of(&self, finding: &Finding, details: &Value) -> String {
+        let reason = match self.reason {
+            true => finding
+                .reason
+                .as_deref()
+                .unwrap_or_default()
+                .replace(|c: char| c.is_ascii_digit(), ""),
+            false => String::new(),
+        };
+        let kind = match finding.kind.parse::<u16>() {
+            Ok(_) if !self.status => "status",
+            _ => finding.kind.as_str(),
+        };
+        let path = match self.path {
+            FingerprintPath::None => String::new(),
+            FingerprintPath::Template => finding.path.clone(),
+            FingerprintPath::Concrete => concrete_path(&finding.path, details),
+        };
+        let key = [finding.api.as_str(), &finding.method, &path, kind, &reason].join("\n");
+        let hash = key.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+        format!("{:016x}", hash)
+    }
+}
+
+/// Path with the values of the path parameters of the payload of the finding
+fn concrete_path(path: &str, details: &Value) -> String {
+    let params = details["payload"]["path_params"].as_array();
+    params
+        .into_iter()
+        .flatten()
+        .filter_map(|param| Some((param[0].as_str()?, param[1].as_str()?)))
+        .fold(path.to_string(), |path, (name, value)| {
+            path.replace(&format!("{{{}}}", name), value)
+        })
+}
+
+/// SQLite database keeping the runs, their requests of interest and their
+/// deduplicated findings across campaigns
+#[derive(Debug)]
+pub struct Database {
+    connection: Connection,
+    run: i64,
+    fingerprint: Fingerprint,
+}
+
+impl Database {
+    /// Opens or creates the database and starts a run of the specifications
+    pub fn open(path: &Path, specs: &[String]) -> Result<Database> {
+        let error = || format!("unable to open database {}", path.display());
+        let connection = Connection::open(path).with_context(error)?;
+        connection.execute_batch(SCHEMA).with_context(error)?;
+        connection.execute(
+            "INSERT INTO runs (started, specs) VALUES (?1, ?2)",
+            params![now(), specs.join(", ")],
+        )?;
+        let run = connection.last_insert_rowid();
+        Ok(Database {
+            connection,
+            run,
+            fingerprint: Fingerprint::default(),
+        })
+    }
+
+    /// Deduplicates the findings by the attributes instead of the default
+    /// ones
+    pub fn fingerprint(mut self, fingerprint: Fingerprint) -> Database {
+        self.fingerprint = fingerprint;
+        self
+    }
+
+    /// Stores the finding, or counts it when an earlier one is the same.
+    /// Returns whether it was not found before.
+    pub fn record_finding(&self, finding: &Finding, details: &Value) -> Result<bool> {
+        let fingerprint = self.fingerprint.of(finding, details);
+        let file = finding.file.to_string_lossy();
+        let updated = self.connection.execute(
+            "UPDATE findings SET last_run = ?1, occurrences = occurrences + 1, file = ?2, \
+             details = ?3 WHERE fingerprint = ?4",
+            params![self.run, file, details.to_string(), fingerprint],
+        )?;
+        if updated > 0 {
+            return Ok(false);
+        }
+        self.connection.execute(
+            "INSERT INTO findings (fingerprint, api, method, path, kind, reason, first_run, \
+             last_run, file, details) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7, ?8, ?9)",
+            params![
+                fingerprint,
+                finding.api,
+                finding.method,
+                finding.path,
+                finding.kind,
+                finding.reason,
+                self.run,
+                file,
+                details.to_string()
+            ],
+        )?;
+        Ok(true)
+    }
+
+    /// Stores the response that caused the finding, which was recorded last
+    /// with its file
+    pub fn record_response(&self, finding: &Finding, response: &Value) -> Result<()> {
+        self.connection.execute(
+            "UPDATE findings SET response = ?1 WHERE file = ?2",
+            params![response.to_string(), finding.file.to_string_lossy()],
+        )?;
+        Ok(())
+    }
+
+    /// Stores a request worth looking at, the first one of an operation
+    /// answered with a status code or one that increased the coverage
+    pub fn record_request(
+        &self,
+        (api, method, path): (&str, &str, &str),
+        status: u16,
+        reason: &str,
+        request: &Value,
+    ) -> Result<()> {
+        self.connection.execute(
+            "INSERT INTO requests (run, api, method, path, status, reason, request) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                self.run,
+                api,
+                method,
+                path,
+                status,
+                reason,
+                request.to_string()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Ends the run after the requests, returns the number of its findings
+    /// and how many of them were new
+    pub fn finish(&self, requests: u32) -> Result<(u32, u32)> {
+        self.connection.execute(
+            "UPDATE runs SET finished = ?1, requests = ?2 WHERE id = ?3",
+            params![now(), requests, self.run],
+        )?;
+        let counts = self
+            .connection
+            .query_row(
+                "SELECT COUNT(*), SUM(first_run = ?1) FROM findings WHERE last_run = ?1",
+                params![self.run],
+                |row| Ok((row.get(0)?, row.get::<_, Option<u32>>(1)?.unwrap_or(0))),
+            )
+            .optional()?
+            .unwrap_or((0, 0));
+        Ok(counts)
+    }
+}
@@ -0,0 +1,41 @@
+use std::collections::BTreeSet;
+
+use openapi_utils::ReferenceOrExt;
+use openapiv3::{OpenAPI, PathItem};
+use serde_json::{json, Value};
+
+use crate::payload;
+
+/// Everything that changes the requests of the operation, references are
+/// expected to be resolved so that changed schemas show up in it
+fn fingerprint(item: &PathItem, method: &str) -> Option<Value> {
+    payload::operations(item)
+        .into_iter()
+        .find(|(name, _)| *name == method)
+        .map(|(_, operation)| {
+            json!({
+                "operation": operation,
+                "parameters": item.parameters,
+                "servers": item.servers,
+            })
+        })
+}
+
+/// Operations of the new specification, by their path and method, that were
+/// added or changed since the old one. Both specifications need their
+/// references resolved.
+pub fn changed_operations(old: &OpenAPI, new: &OpenAPI) -> BTreeSet<(String, String)> {
+    let servers_changed = json!(old.servers) != json!(new.servers);
+    let mut changed = BTreeSet::new();
+    for (path, item) in &new.paths {
+        let item = item.to_item_ref();
+        let old_item = old.paths.get(path).map(|item| item.to_item_ref());
+        for (method, _) in payload::operations(item) {
+            let before = old_item.and_then(|old_item| fingerprint(old_item, method));
+            if servers_changed || before != fingerprint(item, method) {
+                changed.insert((path.clone(), method.to_string()));
+            }
+        }
+    }
+    changed
+}
@@ -0,0 +1,169 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{self, File},
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::checks;
+use crate::fuzzer::Response;
+
+// Fuzzed requests are answered in many ways, only this many shapes of the
+// bodies of a status code are kept
+const MAX_SHAPES: usize = 16;
+
+// Bodies are summarized down to this depth
+const MAX_DEPTH: usize = 3;
+
+/// Responses of an operation with a status code
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatusSummary {
+    pub responses: u32,
+    /// Media types without their parameters, e.g. `application/json`
+    pub content_types: BTreeSet<String>,
+    /// Structure of the bodies without their values, e.g.
+    /// `{code: number, error: string}`
+    pub shapes: BTreeSet<String>,
+}
+
+/// Normalized responses of every operation by their status code, which are
+/// compared with the ones of the previous run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Behavior {
+    /// Status codes by operation, e.g. `GET /pets`
+    pub operations: BTreeMap<String, BTreeMap<u16, StatusSummary>>,
+}
+
+/// Structure of the JSON value, objects with their sorted properties and
+/// arrays by their first item
+fn json_shape(value: &Value, depth: usize) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(_) => "boolean".to_string(),
+        Value::Number(_) => "number".to_string(),
+        Value::String(_) => "string".to_string(),
+        Value::Array(_) | Value::Object(_) if depth >= MAX_DEPTH => "...".to_string(),
+        Value::Array(items) => match items.first() {
+            Some(item) => format!("[{}]", json_shape(item, depth + 1)),
+            None => "[]".to_string(),
+        },
+        Value::Object(object) => {
+            let properties = object
+                .iter()
+                .map(|(name, value)| format!("{}: {}", name, json_shape(value, depth + 1)))
+                .collect::<Vec<_>>();
+            format!("{{{}}}", properties.join(", "))
+        }
+    }
+}
+
+fn shape(body: &str) -> String {
+    if body.trim().is_empty() {
+        return "empty".to_string();
+    }
+    match serde_json::from_str::<Value>(body) {
+        Ok(value) => json_shape(&value, 0),
+        Err(_) => "text".to_string(),
+    }
+}
+
+/// Items of the set that are not in the other one
+fn added<'a>(set: &'a BTreeSet<String>, other: &'a BTreeSet<String>) -> Vec<&'a str> {
+    set.difference(other).map(String::as_str).collect()
+}
+
+impl Behavior {
+    pub(crate) fn observe(&mut self, operation: String, resp: &Response) {
+        let summary = self
+            .operations
+            .entry(operation)
+            .or_default()
+            .entry(resp.status)
+            .or_default();
+        summary.responses += 1;
+        if let Some(content_type) = resp.header("content-type") {
+            summary.content_types.insert(checks::essence(content_type));
+        }
+        if summary.shapes.len() < MAX_SHAPES {
+            summary.shapes.insert(shape(&resp.body));
+        }
+    }
+
+    /// Loads the summaries of the previous run, nothing when there was none
+    pub fn load(path: &Path) -> Result<Option<Behavior>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let file =
+            File::open(path).with_context(|| format!("unable to open {}", path.display()))?;
+        let behavior = serde_json::from_reader(file)
+            .with_context(|| format!("invalid response summaries in {}", path.display()))?;
+        Ok(Some(behavior))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        serde_json::to_writer_pretty(
+            File::create(path).with_context(|| format!("unable to create {}", path.display()))?,
+            self,
+        )?;
+        Ok(())
+    }
+
+    /// Changes of the responses since the previous run, of the operations
+    /// that were fuzzed in both of them. Status codes are reported once they
+    /// appear or disappear and content types once they appear. Body shapes
+    /// are only reported when none of the earlier ones were received anymore,
+    /// as fuzzed requests vary them.
+    pub fn changes(&self, previous: &Behavior) -> Vec<String> {
+        let mut changes = Vec::new();
+        for (operation, statuses) in &self.operations {
+            let before = match previous.operations.get(operation) {
+                Some(before) => before,
+                None => continue,
+            };
+            for (status, summary) in statuses {
+                let earlier = match before.get(status) {
+                    Some(earlier) => earlier,
+                    None => {
+                        changes.push(format!(
+                            "{}: new status {} in {} responses",
+                            operation, status, summary.responses
+                        ));
+                        continue;
+                    }
+                };
+                for content_type in added(&summary.content_types, &earlier.content_types) {
+                    changes.push(format!(
+                        "{}: status {} is answered with {} now",
+                        operation, status, content_type
+                    ));
+                }
+                if summary.shapes.is_disjoint(&earlier.shapes) {
+                    changes.push(format!(
+                        "{}: bodies of status {} changed from {} to {}",
+                        operation,
+                        status,
+                        added(&earlier.shapes, &summary.shapes).join(" | "),
+                        added(&summary.shapes, &earlier.shapes).join(" | ")
+                    ));
+                }
+            }
+            for status in before
+                .keys()
+                .filter(|status| !statuses.contains_key(status))
+            {
+                changes.push(format!(
+                    "{}: status {} was not received anymore",
+                    operation, status
+                ));
+            }
+        }
+        changes
+    }
+}
@@ -0,0 +1,79 @@
+use std::fs::{self, File};
+
+use anyhow::{Context, Result};
+use openapi_utils::ReferenceOrExt;
+use serde_json::json;
+
+use crate::fuzzer::Api;
+use crate::payload::{self, Payload, PayloadOptions};
+use crate::plugin::Plugin;
+use crate::script::Script;
+use crate::state::Store;
+
+/// Generates the requests of every operation the filter of the options
+/// selects without sending them. They are printed as curl commands and saved
+/// to `results_dir` together with the requests to fuzz, their number is
+/// returned.
+pub fn generate(
+    apis: &[Api],
+    options: &PayloadOptions,
+    requests: usize,
+    script: Option<&Script>,
+    plugins: &[Plugin],
+    results_dir: &str,
+) -> Result<usize> {
+    let mut generated = 0;
+    for api in apis {
+        let store = Store::default();
+        for (path, item) in &api.schema.paths {
+            let item = item.to_item_ref();
+            for (method, operation) in payload::operations(item) {
+                for n in 0..requests {
+                    let url = &api.urls[n % api.urls.len()];
+                    let payload = Payload::for_operation(
+                        url, path, item, method, operation, options, &store,
+                    )?;
+                    let mut payload = match payload {
+                        Some(payload) => payload,
+                        None => break,
+                    };
+                    // Hooks changing the requests, e.g. to sign them, are part
+                    // of what is checked
+                    for plugin in plugins {
+                        plugin.generate(&mut payload)?;
+                    }
+                    if let Some(script) = script {
+                        script.pre_request(&mut payload)?;
+                    }
+                    let curl = payload.to_curl()?;
+                    if n == 0 {
+                        match apis.len() {
+                            1 => println!("# {} {}", method, path),
+                            _ => println!("# {}: {} {}", api.name, method, path),
+                        }
+                    }
+                    println!("{}", curl);
+
+                    let mut dir = results_dir.to_string();
+                    if apis.len() > 1 {
+                        dir += &format!("/{}", api.name);
+                    }
+                    dir += &format!("/{}/{}", path.trim_matches('/').replace('/', "-"), method);
+                    fs::create_dir_all(&dir)?;
+                    let file = format!("{}/{}.json", dir, n);
+                    serde_json::to_writer_pretty(
+                        &File::create(&file)
+                            .with_context(|| format!("unable to create {}", file))?,
+                        &json!({
+                            "spec": api.name,
+                            "payload": payload,
+                            "curl": curl,
+                        }),
+                    )?;
+                    generated += 1;
+                }
+            }
+        }
+    }
+    Ok(generated)
+}
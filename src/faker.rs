@@ -0,0 +1,178 @@
+use anyhow::Result;
+use arbitrary::Unstructured;
+
+const FIRST_NAMES: &[&str] = &[
+    "Emma", "Liam", "Olivia", "Noah", "Sophie", "Lucas", "Mia", "Daan", "Julia", "Sem", "Anna",
+    "Finn",
+];
+const LAST_NAMES: &[&str] = &[
+    "Jansen", "Smith", "de Vries", "Johnson", "Bakker", "Müller", "Visser", "Brown", "Smit",
+    "Garcia",
+];
+const STREETS: &[&str] = &[
+    "Main Street",
+    "Kerkstraat",
+    "Oak Avenue",
+    "Stationsweg",
+    "Maple Road",
+    "Dorpsstraat",
+];
+const CITIES: &[&str] = &[
+    "Amsterdam",
+    "London",
+    "Berlin",
+    "New York",
+    "Utrecht",
+    "Paris",
+    "Toronto",
+];
+const COUNTRIES: &[&str] = &["NL", "GB", "DE", "US", "FR", "CA"];
+const COMPANIES: &[&str] = &["Acme", "Globex", "Initech", "Umbrella", "Hooli", "Stark"];
+const WORDS: &[&str] = &[
+    "the", "quick", "brown", "fox", "jumps", "over", "lazy", "dog", "order", "account", "new",
+    "request", "service", "daily", "update",
+];
+const DOMAINS: &[&str] = &["example.com", "example.org", "example.net"];
+
+/// Lowercase letters of the name, without separators
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn digits(gen: &mut Unstructured, count: usize) -> Result<String> {
+    (0..count)
+        .map(|_| Ok((b'0' + gen.int_in_range(0..=9)?) as char))
+        .collect()
+}
+
+fn sentence(gen: &mut Unstructured) -> Result<String> {
+    let words = (0..gen.int_in_range(3..=9)?)
+        .map(|_| Ok(*gen.choose(WORDS)?))
+        .collect::<Result<Vec<_>>>()?;
+    let mut sentence = words.join(" ");
+    if let Some(first) = sentence.get_mut(0..1) {
+        first.make_ascii_uppercase();
+    }
+    sentence.push('.');
+    Ok(sentence)
+}
+
+fn username(gen: &mut Unstructured) -> Result<String> {
+    Ok(format!(
+        "{}{}",
+        gen.choose(FIRST_NAMES)?.to_lowercase(),
+        gen.int_in_range(1..=99)?
+    ))
+}
+
+fn uuid(gen: &mut Unstructured) -> Result<String> {
+    let mut hex = String::new();
+    for _ in 0..32 {
+        hex.push(char::from_digit(gen.int_in_range(0..=15)?, 16).unwrap_or('0'));
+    }
+    Ok(format!(
+        "{}-{}-4{}-8{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[13..16],
+        &hex[17..20],
+        &hex[20..]
+    ))
+}
+
+fn date(gen: &mut Unstructured) -> Result<String> {
+    Ok(format!(
+        "{}-{:02}-{:02}",
+        gen.int_in_range(1970..=2030)?,
+        gen.int_in_range(1..=12)?,
+        gen.int_in_range(1..=28)?
+    ))
+}
+
+/// Value of the string format, if it is one that servers validate
+fn for_format(format: &str, gen: &mut Unstructured) -> Result<Option<String>> {
+    Ok(Some(match format {
+        "email" => format!("{}@{}", username(gen)?, gen.choose(DOMAINS)?),
+        "uri" | "url" => format!("https://{}/{}", gen.choose(DOMAINS)?, gen.choose(WORDS)?),
+        "uuid" => uuid(gen)?,
+        "date" => date(gen)?,
+        "date-time" => format!(
+            "{}T{:02}:{:02}:{:02}Z",
+            date(gen)?,
+            gen.int_in_range(0..=23)?,
+            gen.int_in_range(0..=59)?,
+            gen.int_in_range(0..=59)?
+        ),
+        "hostname" => gen.choose(DOMAINS)?.to_string(),
+        "ipv4" => format!("192.0.2.{}", gen.int_in_range(1..=254)?),
+        "ipv6" => format!("2001:db8::{:x}", gen.int_in_range(1..=0xffff_u32)?),
+        "byte" => "ZmFrZXI=".to_string(),
+        "password" => format!("Secret-{}!", digits(gen, 6)?),
+        "phone" => format!("+31 6 {}", digits(gen, 8)?),
+        _ => return Ok(None),
+    }))
+}
+
+/// Plausible value of a string named like the property or parameter, e.g. an
+/// email address for `email` or a city for `billingCity`. The format of the
+/// schema takes precedence over the name.
+pub fn value(name: &str, format: &str, gen: &mut Unstructured) -> Result<String> {
+    if let Some(value) = for_format(format, gen)? {
+        return Ok(value);
+    }
+    let name = normalize(name);
+    let has = |parts: &[&str]| parts.iter().any(|part| name.contains(part));
+    let value = if has(&["email", "mail"]) {
+        for_format("email", gen)?.unwrap_or_default()
+    } else if has(&["phone", "mobile", "tel", "fax"]) {
+        for_format("phone", gen)?.unwrap_or_default()
+    } else if has(&["url", "uri", "website", "homepage", "link", "href"]) {
+        for_format("url", gen)?.unwrap_or_default()
+    } else if has(&["password", "secret"]) {
+        for_format("password", gen)?.unwrap_or_default()
+    } else if has(&["firstname", "givenname", "forename"]) {
+        gen.choose(FIRST_NAMES)?.to_string()
+    } else if has(&["lastname", "surname", "familyname"]) {
+        gen.choose(LAST_NAMES)?.to_string()
+    } else if has(&["username", "login", "nickname", "handle"]) {
+        username(gen)?
+    } else if has(&["company", "organization", "organisation", "employer"]) {
+        format!("{} Inc.", gen.choose(COMPANIES)?)
+    } else if has(&["street", "address"]) {
+        format!("{} {}", gen.choose(STREETS)?, gen.int_in_range(1..=250)?)
+    } else if has(&["city", "town"]) {
+        gen.choose(CITIES)?.to_string()
+    } else if has(&["country"]) {
+        gen.choose(COUNTRIES)?.to_string()
+    } else if has(&["zip", "postal", "postcode"]) {
+        digits(gen, 5)?
+    } else if has(&["currency"]) {
+        gen.choose(&["EUR", "USD", "GBP"])?.to_string()
+    } else if has(&["locale", "language", "lang"]) {
+        gen.choose(&["en-US", "nl-NL", "de-DE"])?.to_string()
+    } else if has(&["uuid", "guid"]) {
+        uuid(gen)?
+    } else if has(&["date", "birthday"]) {
+        date(gen)?
+    } else if has(&["name"]) {
+        format!("{} {}", gen.choose(FIRST_NAMES)?, gen.choose(LAST_NAMES)?)
+    } else if has(&[
+        "description",
+        "comment",
+        "message",
+        "text",
+        "bio",
+        "summary",
+        "note",
+    ]) {
+        sentence(gen)?
+    } else if has(&["title", "subject"]) {
+        sentence(gen)?.trim_end_matches('.').to_string()
+    } else {
+        gen.choose(WORDS)?.to_string()
+    };
+    Ok(value)
+}
@@ -0,0 +1,97 @@
+use std::collections::BTreeSet;
+
+use openapiv3::Operation;
+
+use crate::hints::Hints;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Deprecated {
+    #[default]
+    Include,
+    Skip,
+    Only,
+}
+
+/// Matches `text` against a glob pattern, where `*` matches any characters
+/// within one path segment, `**` any number of segments and `?` a single
+/// character other than `/`
+pub fn glob(pattern: &str, text: &str) -> bool {
+    fn matches(pattern: &[char], text: &[char]) -> bool {
+        match pattern {
+            [] => text.is_empty(),
+            // `/internal/**` matches `/internal` as well
+            ['/', '*', '*', rest @ ..] => {
+                matches(rest, text)
+                    || (text.first() == Some(&'/') && matches(&pattern[1..], &text[1..]))
+            }
+            ['*', '*', rest @ ..] => (0..=text.len()).any(|i| matches(rest, &text[i..])),
+            ['*', rest @ ..] => (0..=text.len())
+                .take_while(|&i| i == 0 || text[i - 1] != '/')
+                .any(|i| matches(rest, &text[i..])),
+            ['?', rest @ ..] => {
+                matches!(text.first(), Some(c) if *c != '/') && matches(rest, &text[1..])
+            }
+            [c, rest @ ..] => text.first() == Some(c) && matches(rest, &text[1..]),
+        }
+    }
+    matches(
+        &pattern.chars().collect::<Vec<_>>(),
+        &text.chars().collect::<Vec<_>>(),
+    )
+}
+
+/// Selects the operations of the specification that are fuzzed
+#[derive(Debug, Default)]
+pub struct Filter {
+    pub deprecated: Deprecated,
+    /// Operations need one of these tags unless it is empty
+    pub tags: Vec<String>,
+    pub excluded_tags: Vec<String>,
+    /// Glob patterns of the paths to fuzz, all of them when it is empty
+    pub included_paths: Vec<String>,
+    pub excluded_paths: Vec<String>,
+    /// Methods to fuzz in upper case, all of them when it is empty
+    pub methods: Vec<String>,
+    /// Glob patterns of the operationIds to fuzz, all operations when it is
+    /// empty
+    pub operations: Vec<String>,
+    /// Paths and methods of the operations that changed since an older
+    /// version of the specification, all operations when it is none
+    pub changed: Option<BTreeSet<(String, String)>>,
+}
+
+impl Filter {
+    pub fn matches(&self, path: &str, method: &str, operation: &Operation) -> bool {
+        let deprecated = match self.deprecated {
+            Deprecated::Include => true,
+            Deprecated::Skip => !operation.deprecated,
+            Deprecated::Only => operation.deprecated,
+        };
+        let tags = (self.tags.is_empty()
+            || operation.tags.iter().any(|tag| self.tags.contains(tag)))
+            && !operation
+                .tags
+                .iter()
+                .any(|tag| self.excluded_tags.contains(tag));
+        let paths = (self.included_paths.is_empty()
+            || self
+                .included_paths
+                .iter()
+                .any(|pattern| glob(pattern, path)))
+            && !self
+                .excluded_paths
+                .iter()
+                .any(|pattern| glob(pattern, path));
+        let methods = self.methods.is_empty() || self.methods.iter().any(|m| m == method);
+        let operations = self.operations.is_empty()
+            || matches!(&operation.operation_id, Some(id)
+                if self.operations.iter().any(|pattern| glob(pattern, id)));
+        let changed = self
+            .changed
+            .as_ref()
+            .is_none_or(|changed| changed.contains(&(path.to_string(), method.to_string())));
+        // Spec authors may leave out operations with `x-fuzz-skip`
+        let hinted = !Hints::new(|name| operation.extensions.get(name)).skip;
+        deprecated && tags && paths && methods && operations && changed && hinted
+    }
+}
@@ -0,0 +1,292 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use openapi_utils::ReferenceOrExt;
+use openapiv3::{MediaType, ParameterSchemaOrContent, QueryStyle};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use ureq::OrAnyStatus;
+
+use crate::budget::Remaining;
+use crate::conform;
+
+const URLENCODED: &str = "application/x-www-form-urlencoded";
+
+/// How a property of a form is serialized, from the `encoding` of its media
+/// type
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct PartEncoding {
+    /// Content type of the part, which defaults to one that fits its value
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// Headers of the part of a multipart body, generated from their schemas
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub headers: Vec<(String, String)>,
+    /// `form`, `spaceDelimited`, `pipeDelimited` or `deepObject` in
+    /// urlencoded bodies
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub style: Option<String>,
+    pub explode: bool,
+    /// Reserved characters are not percent-encoded
+    pub allow_reserved: bool,
+}
+
+/// Body of a form media type, the value of the body is serialized by it
+/// instead of being sent as JSON
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Form {
+    pub media_type: String,
+    /// Encodings of the properties by their name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub encoding: BTreeMap<String, PartEncoding>,
+}
+
+/// Whether bodies of the media type are sent as forms
+pub fn is_form(media_type: &str) -> bool {
+    media_type == URLENCODED || media_type.starts_with("multipart/")
+}
+
+/// Sends the body serialized as the form, or as JSON without one, responses
+/// with any status code are returned
+pub fn send(request: ureq::Request, body: &Value, form: Option<&Form>) -> Result<ureq::Response> {
+    match form {
+        Some(form) => {
+            let (content_type, body) = form.encode(body);
+            send_bytes(request, &content_type, &body)
+        }
+        None => Ok(request.send_json(body.clone()).or_any_status()?),
+    }
+}
+
+/// Sends the body encoded already, with the content type unless the request
+/// has one, responses with any status code are returned
+pub fn send_bytes(
+    request: ureq::Request,
+    content_type: &str,
+    body: &[u8],
+) -> Result<ureq::Response> {
+    let resp = match request.header("content-type") {
+        Some(_) => request.send_bytes(body),
+        None => request.set("Content-Type", content_type).send_bytes(body),
+    };
+    Ok(resp.or_any_status()?)
+}
+
+impl Form {
+    pub(crate) fn new(media_type: &str, media: &MediaType, budget: &Remaining) -> Form {
+        let encoding = media
+            .encoding
+            .iter()
+            .map(|(name, encoding)| {
+                let headers = encoding
+                    .headers
+                    .iter()
+                    .filter(|(name, _)| !name.eq_ignore_ascii_case("content-type"))
+                    .filter_map(|(name, header)| match &header.to_item_ref().format {
+                        ParameterSchemaOrContent::Schema(schema) => {
+                            let value =
+                                match conform::generate_resolved(schema.to_item_ref(), budget) {
+                                    Value::String(value) => value,
+                                    value => value.to_string(),
+                                };
+                            Some((name.clone(), value))
+                        }
+                        ParameterSchemaOrContent::Content(_) => None,
+                    })
+                    .collect();
+                // The form style explodes by default, which openapiv3 does not
+                // tell apart from an explicit `explode: false`
+                let explode =
+                    encoding.explode || matches!(encoding.style, None | Some(QueryStyle::Form));
+                let style = encoding.style.as_ref().map(|style| match style {
+                    QueryStyle::Form => "form",
+                    QueryStyle::SpaceDelimited => "spaceDelimited",
+                    QueryStyle::PipeDelimited => "pipeDelimited",
+                    QueryStyle::DeepObject => "deepObject",
+                });
+                let part = PartEncoding {
+                    content_type: encoding.content_type.clone(),
+                    headers,
+                    style: style.map(str::to_string),
+                    explode,
+                    allow_reserved: encoding.allow_reserved,
+                };
+                (name.clone(), part)
+            })
+            .collect();
+        Form {
+            media_type: media_type.to_string(),
+            encoding,
+        }
+    }
+
+    /// Content type header and bytes of the value serialized as the form
+    pub fn encode(&self, body: &Value) -> (String, Vec<u8>) {
+        // Forms consist of properties, other values are sent as a single one
+        // without a name
+        let unnamed = String::new();
+        let properties = match body {
+            Value::Object(object) => object.iter().collect::<Vec<_>>(),
+            value => vec![(&unnamed, value)],
+        };
+        match self.media_type.as_str() {
+            URLENCODED => (self.media_type.clone(), self.urlencoded(&properties)),
+            _ => multipart(&self.media_type, &self.encoding, &properties),
+        }
+    }
+
+    fn urlencoded(&self, properties: &[(&String, &Value)]) -> Vec<u8> {
+        let default = PartEncoding {
+            explode: true,
+            ..PartEncoding::default()
+        };
+        let mut pairs = Vec::new();
+        for (name, value) in properties {
+            let encoding = self.encoding.get(*name).unwrap_or(&default);
+            let escape = |text: &str| percent_encode(text, encoding.allow_reserved);
+            let name = escape(name);
+            let json = encoding
+                .content_type
+                .as_deref()
+                .is_some_and(|content_type| content_type.contains("json"));
+            match value {
+                _ if json => pairs.push(format!("{}={}", name, escape(&value.to_string()))),
+                Value::Array(items) => {
+                    let items = items
+                        .iter()
+                        .map(|item| escape(&text(item)))
+                        .collect::<Vec<_>>();
+                    match (encoding.style.as_deref(), encoding.explode) {
+                        (Some("spaceDelimited"), _) => {
+                            pairs.push(format!("{}={}", name, items.join("%20")))
+                        }
+                        (Some("pipeDelimited"), _) => {
+                            pairs.push(format!("{}={}", name, items.join("%7C")))
+                        }
+                        (_, true) => {
+                            pairs.extend(items.iter().map(|item| format!("{}={}", name, item)))
+                        }
+                        (_, false) => pairs.push(format!("{}={}", name, items.join(","))),
+                    }
+                }
+                Value::Object(object) => {
+                    let entries = object
+                        .iter()
+                        .map(|(key, value)| (escape(key), escape(&text(value))));
+                    match (encoding.style.as_deref(), encoding.explode) {
+                        (Some("deepObject"), _) => pairs.extend(
+                            entries.map(|(key, value)| format!("{}%5B{}%5D={}", name, key, value)),
+                        ),
+                        (_, true) => {
+                            pairs.extend(entries.map(|(key, value)| format!("{}={}", key, value)))
+                        }
+                        (_, false) => {
+                            let flat = entries
+                                .flat_map(|(key, value)| vec![key, value])
+                                .collect::<Vec<_>>();
+                            pairs.push(format!("{}={}", name, flat.join(",")))
+                        }
+                    }
+                }
+                value => pairs.push(format!("{}={}", name, escape(&text(value)))),
+            }
+        }
+        pairs.join("&").into_bytes()
+    }
+}
+
+/// Value as the text of a form field, strings without their quotes
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(string) => string.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// Percent-encodes all but the unreserved characters of RFC 3986, and the
+/// reserved ones when they are allowed
+fn percent_encode(text: &str, allow_reserved: bool) -> String {
+    const RESERVED: &[u8] = b":/?#[]@!$&'()*+,;=";
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric()
+            || b"-._~".contains(&byte)
+            || (allow_reserved && RESERVED.contains(&byte))
+        {
+            encoded.push(byte as char);
+        } else {
+            encoded += &format!("%{:02X}", byte);
+        }
+    }
+    encoded
+}
+
+/// Content type of a part without an encoding, or with a list or range of
+/// them, e.g. `image/*`
+fn part_content_type(declared: Option<&str>, value: &Value) -> String {
+    let declared = declared
+        .and_then(|declared| declared.split(',').next())
+        .map(str::trim)
+        .filter(|declared| !declared.is_empty());
+    match declared {
+        Some("*/*") => "application/octet-stream".to_string(),
+        Some(declared) => match declared.strip_suffix("/*") {
+            Some(kind) => format!("{}/x-fuzz", kind),
+            None => declared.to_string(),
+        },
+        None => match value {
+            Value::Object(_) | Value::Array(_) => "application/json".to_string(),
+            _ => "text/plain".to_string(),
+        },
+    }
+}
+
+fn multipart(
+    media_type: &str,
+    encodings: &BTreeMap<String, PartEncoding>,
+    properties: &[(&String, &Value)],
+) -> (String, Vec<u8>) {
+    let boundary = format!("openapi-fuzzer-{:032x}", rand::random::<u128>());
+    let mut body = Vec::new();
+    for (name, value) in properties {
+        let encoding = encodings.get(*name);
+        let declared = encoding.and_then(|encoding| encoding.content_type.as_deref());
+        // Arrays of primitive values are sent as one part for every item
+        let values = match value {
+            Value::Array(items)
+                if declared.is_none_or(|declared| !declared.contains("json"))
+                    && items
+                        .iter()
+                        .all(|item| !item.is_object() && !item.is_array()) =>
+            {
+                items.iter().collect()
+            }
+            value => vec![*value],
+        };
+        for value in values {
+            let content_type = part_content_type(declared, value);
+            let file = !content_type.starts_with("text/") && !content_type.contains("json");
+            body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+            let mut disposition = format!("Content-Disposition: form-data; name=\"{}\"", name);
+            if file {
+                disposition += &format!("; filename=\"{}\"", name);
+            }
+            body.extend_from_slice(format!("{}\r\n", disposition).as_bytes());
+            body.extend_from_slice(format!("Content-Type: {}\r\n", content_type).as_bytes());
+            for (header, value) in encoding.iter().flat_map(|encoding| &encoding.headers) {
+                body.extend_from_slice(format!("{}: {}\r\n", header, value).as_bytes());
+            }
+            body.extend_from_slice(b"\r\n");
+            let content = match (value, content_type.contains("json")) {
+                (Value::String(string), false) => string.clone(),
+                (value, _) => value.to_string(),
+            };
+            body.extend_from_slice(content.as_bytes());
+            body.extend_from_slice(b"\r\n");
+        }
+    }
+    body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+    (format!("{}; boundary={}", media_type, boundary), body)
+}
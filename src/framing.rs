@@ -0,0 +1,391 @@
+use std::{
+    fmt,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{Shutdown, TcpStream},
+    str::FromStr,
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::fuzzer::{Response, MAX_BODY_SIZE};
+use crate::h2;
+use crate::payload::{Payload, REQUEST_ID_HEADER};
+
+// Servers that wait for the rest of a body they were promised give up by then
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Unusual framing of the body of a request, which proxies and servers may
+/// disagree on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Framing {
+    /// Chunks of random sizes down to a single byte, written with leading
+    /// zeros, upper case digits or chunk extensions
+    OddChunks,
+    /// Chunked body together with a Content-Length that does not match it
+    ChunkedWithLength,
+    /// Two Content-Length headers with different values
+    DuplicateLength,
+    /// Chunked body followed by trailer headers, including ones that are not
+    /// allowed in trailers
+    Trailers,
+    /// Transfer-Encoding spelled unusually, e.g. `Chunked` or
+    /// `chunked, identity`
+    ObfuscatedEncoding,
+    /// Content-Length smaller than the body, the rest of it looks like the
+    /// start of the next request
+    ShortLength,
+    /// Content-Length larger than the body, the server waits for bytes that
+    /// never come
+    LongLength,
+    /// Content-Length of zero followed by the body
+    ZeroLength,
+}
+
+const FRAMINGS: &[Framing] = &[
+    Framing::OddChunks,
+    Framing::ChunkedWithLength,
+    Framing::DuplicateLength,
+    Framing::Trailers,
+    Framing::ObfuscatedEncoding,
+    Framing::ShortLength,
+    Framing::LongLength,
+    Framing::ZeroLength,
+];
+
+/// Error of a framed request the server neither answered nor closed the
+/// connection of in time
+#[derive(Debug)]
+pub struct Hang(pub Framing);
+
+impl fmt::Display for Hang {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "no response within {}s to a body framed as {}",
+            TIMEOUT.as_secs(),
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for Hang {}
+
+/// Version of HTTP the requests are sent with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum HttpVersion {
+    /// Sent over a connection of its own for each request, like framed ones
+    #[serde(rename = "1.0")]
+    Http10,
+    #[default]
+    #[serde(rename = "1.1")]
+    Http11,
+    /// Sent in clear text with prior knowledge, that is without asking the
+    /// server to upgrade from HTTP/1.1, over a connection of its own
+    #[serde(rename = "2")]
+    Http2,
+}
+
+impl HttpVersion {
+    pub(crate) fn is_default(&self) -> bool {
+        *self == HttpVersion::default()
+    }
+}
+
+impl FromStr for HttpVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "1.0" => Ok(HttpVersion::Http10),
+            "1.1" => Ok(HttpVersion::Http11),
+            "2" | "2.0" => Ok(HttpVersion::Http2),
+            _ => Err(format!(
+                "unknown HTTP version `{}`, expected 1.0, 1.1 or 2",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for HttpVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HttpVersion::Http10 => write!(f, "HTTP/1.0"),
+            HttpVersion::Http11 => write!(f, "HTTP/1.1"),
+            HttpVersion::Http2 => write!(f, "HTTP/2"),
+        }
+    }
+}
+
+const OBFUSCATED_ENCODINGS: &[&str] = &[
+    "Transfer-Encoding: Chunked",
+    "Transfer-Encoding: chunked, identity",
+    "Transfer-Encoding:\tchunked",
+    "Transfer-Encoding: chunked\r\nTransfer-Encoding: identity",
+    "Transfer-Encoding : chunked",
+    "Transfer-Encoding: \"chunked\"",
+];
+
+/// Name of the framing as in the `framing` field of payloads
+impl fmt::Display for Framing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = serde_json::to_value(self)
+            .ok()
+            .and_then(|framing| framing.as_str().map(str::to_string))
+            .unwrap_or_default();
+        write!(f, "{}", name)
+    }
+}
+
+impl Framing {
+    pub fn random() -> Framing {
+        *FRAMINGS
+            .choose(&mut rand::thread_rng())
+            .expect("at least one framing")
+    }
+
+    /// Headers announcing the body and the body framed by them
+    fn frame(self, body: &[u8]) -> (Vec<String>, Vec<u8>) {
+        let mut rng = rand::thread_rng();
+        let chunked_header = "Transfer-Encoding: chunked".to_string();
+        match self {
+            Framing::OddChunks => (vec![chunked_header], chunked(body, true, &[])),
+            Framing::ChunkedWithLength => {
+                let length = *[0, body.len() / 2, body.len() + 16]
+                    .choose(&mut rng)
+                    .expect("at least one length");
+                (
+                    vec![chunked_header, format!("Content-Length: {}", length)],
+                    chunked(body, false, &[]),
+                )
+            }
+            Framing::DuplicateLength => {
+                let mut headers = vec![
+                    format!("Content-Length: {}", body.len()),
+                    format!("Content-Length: {}", body.len() / 2 + 1),
+                ];
+                headers.shuffle(&mut rng);
+                (headers, body.to_vec())
+            }
+            Framing::Trailers => {
+                let mut trailers = vec!["X-Fuzz-Trailer: 1"];
+                if rng.gen() {
+                    trailers.push("Content-Length: 0");
+                }
+                (
+                    vec![chunked_header, "Trailer: X-Fuzz-Trailer".to_string()],
+                    chunked(body, rng.gen(), &trailers),
+                )
+            }
+            Framing::ObfuscatedEncoding => (
+                vec![OBFUSCATED_ENCODINGS
+                    .choose(&mut rng)
+                    .expect("at least one encoding")
+                    .to_string()],
+                chunked(body, false, &[]),
+            ),
+            Framing::ShortLength => (
+                vec![format!("Content-Length: {}", body.len() / 2)],
+                body.to_vec(),
+            ),
+            Framing::LongLength => (
+                vec![format!(
+                    "Content-Length: {}",
+                    body.len() + rng.gen_range(1..=1024)
+                )],
+                body.to_vec(),
+            ),
+            Framing::ZeroLength => (vec!["Content-Length: 0".to_string()], body.to_vec()),
+        }
+    }
+}
+
+/// Body in chunked transfer encoding, odd chunks get random sizes and
+/// unusually written size lines
+fn chunked(body: &[u8], odd: bool, trailers: &[&str]) -> Vec<u8> {
+    let mut rng = rand::thread_rng();
+    let mut framed = Vec::new();
+    let mut rest = body;
+    while !rest.is_empty() {
+        let size = match odd {
+            true => rng.gen_range(1..=rest.len().min(64)),
+            false => rest.len(),
+        };
+        let (chunk, remaining) = rest.split_at(size);
+        let size_line = match (odd, rng.gen_range(0..4)) {
+            (false, _) | (true, 0) => format!("{:x}", size),
+            (true, 1) => format!("{:X}", size),
+            (true, 2) => format!("{:08x}", size),
+            (true, _) => format!("{:x};fuzz=\"1\"", size),
+        };
+        framed.extend_from_slice(size_line.as_bytes());
+        framed.extend_from_slice(b"\r\n");
+        framed.extend_from_slice(chunk);
+        framed.extend_from_slice(b"\r\n");
+        rest = remaining;
+    }
+    framed.extend_from_slice(b"0\r\n");
+    for trailer in trailers {
+        framed.extend_from_slice(trailer.as_bytes());
+        framed.extend_from_slice(b"\r\n");
+    }
+    framed.extend_from_slice(b"\r\n");
+    framed
+}
+
+/// Sends the payload with its framing, or a plain Content-Length without one,
+/// over a connection of its own in its version of HTTP. This is needed because
+/// HTTP clients only frame bodies correctly, speak HTTP/1.1 and resolve dot
+/// segments. Only plain http urls are supported.
+pub(crate) fn send(payload: &Payload, method: &str) -> Result<Response> {
+    let framing = payload.framing;
+    let url = payload.full_url()?;
+    let host = url.host_str().context("url without a host")?;
+    let port = url.port_or_known_default().context("url without a port")?;
+    let mut stream = TcpStream::connect((host, port))
+        .with_context(|| format!("unable to connect to {}:{}", host, port))?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+
+    let authority = match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    };
+    let mut headers = vec![format!("{}: {}", REQUEST_ID_HEADER, payload.request_id)];
+    for (name, value) in &payload.headers {
+        headers.push(format!("{}: {}", name, value));
+    }
+    let (content_type, body) = payload.encoded_body().unwrap_or_default();
+    if !body.is_empty() {
+        headers.push(format!("Content-Type: {}", content_type));
+    }
+    let (framing_headers, body) = match framing {
+        Some(framing) => framing.frame(&body),
+        None if body.is_empty() => (Vec::new(), body),
+        None => (vec![format!("Content-Length: {}", body.len())], body),
+    };
+    headers.extend(framing_headers);
+
+    let resp = match payload.http_version {
+        HttpVersion::Http2 => {
+            // Header fields of HTTP/2 have lower case names, framing headers
+            // may hold several of them
+            let fields = headers
+                .iter()
+                .flat_map(|header| header.split("\r\n"))
+                .filter_map(|field| field.split_once(':'))
+                .map(|(name, value)| (name.to_lowercase(), value.trim_start().to_string()))
+                .collect::<Vec<_>>();
+            h2::send(
+                &stream,
+                method,
+                &authority,
+                &payload.request_target()?,
+                &fields,
+                &body,
+            )
+        }
+        version => {
+            let mut request = format!("{} {} {}\r\n", method, payload.request_target()?, version);
+            request += &format!("Host: {}\r\n", authority);
+            request += "Connection: close\r\n";
+            for header in headers {
+                request += &header;
+                request += "\r\n";
+            }
+            request += "\r\n";
+            stream.write_all(request.as_bytes())?;
+            // The server may answer before it read the whole body
+            let _ = stream.write_all(&body);
+            // Servers waiting for more of the body than was sent see its end,
+            // so only ones that hang do not answer
+            let _ = stream.shutdown(Shutdown::Write);
+            read_response(BufReader::new(stream), method)
+        }
+    };
+    resp.map_err(
+        |e| match e.downcast_ref::<io::Error>().map(io::Error::kind) {
+            Some(io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => match framing {
+                Some(framing) => Hang(framing).into(),
+                None => e.context(format!("no response within {}s", TIMEOUT.as_secs())),
+            },
+            _ => e,
+        },
+    )
+}
+
+fn read_response(mut reader: impl BufRead, method: &str) -> Result<Response> {
+    let mut line = String::new();
+    // Interim responses such as 100 Continue are skipped
+    let (status, headers) = loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            bail!("connection closed without a response");
+        }
+        let status = line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse::<u16>().ok())
+            .ok_or_else(|| anyhow!("invalid status line {:?}", line.trim_end()))?;
+        let mut headers = Vec::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                headers.push((name.trim().to_lowercase(), value.trim().to_string()));
+            }
+        }
+        if status >= 200 {
+            break (status, headers);
+        }
+    };
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    };
+
+    let mut body = Vec::new();
+    let mut reader = reader.take(MAX_BODY_SIZE);
+    let bodyless = method == "HEAD" || status == 204 || status == 304;
+    let chunked = header("transfer-encoding").is_some_and(|encoding| encoding.contains("chunked"));
+    let length = header("content-length").and_then(|length| length.parse::<usize>().ok());
+    if bodyless {
+        // Only the head is sent
+    } else if chunked {
+        loop {
+            line.clear();
+            reader.read_line(&mut line)?;
+            let size = line.trim().split(';').next().unwrap_or_default();
+            let size = usize::from_str_radix(size, 16)
+                .with_context(|| format!("invalid chunk size {:?}", line.trim_end()))?;
+            if size == 0 {
+                break;
+            }
+            (&mut reader).take(size as u64).read_to_end(&mut body)?;
+            line.clear();
+            reader.read_line(&mut line)?;
+        }
+    } else if let Some(length) = length {
+        (&mut reader)
+            .take(length as u64)
+            .read_to_end(&mut body)
+            .context("unable to read response body")?;
+    } else {
+        reader
+            .read_to_end(&mut body)
+            .context("unable to read response body")?;
+    }
+    Ok(Response {
+        status,
+        headers,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    })
+}
@@ -1,20 +1,46 @@
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, BTreeSet, HashMap},
     fs::{self, File},
-    u32,
+    io::{Read, Write},
+    path::PathBuf,
+    rc::Rc,
+    thread,
+    time::{Duration, Instant},
 };
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use openapi_utils::ReferenceOrExt;
 use openapiv3::{OpenAPI, StatusCode};
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 use ureq::OrAnyStatus;
 use url::Url;
 
-use crate::payload::Payload;
+use crate::callbacks::{self, Listener, Registry};
+use crate::checker::{Checker, Checkers, Exchange, HookCheck, PluginCheck, ScriptCheck};
+use crate::checkpoint::{Checkpoint, OperationKey, OperationState};
+use crate::checks;
+use crate::coverage::{Coverage, Goal, Observed, OperationCoverage};
+use crate::db::Database;
+use crate::drift::Behavior;
+use crate::framing::{self, HttpVersion};
+use crate::guide::{self, Guide};
+use crate::hooks::Hook;
+use crate::leaks::Detectors;
+use crate::logs::LogTail;
+use crate::oob::Canaries;
+use crate::payload::{self, Payload, PayloadOptions, REQUEST_ID_HEADER};
+use crate::plugin::Plugin;
+use crate::record::Seeds;
+use crate::schedule::{Budget, Scheduler};
+use crate::script::{self, Script};
+use crate::severity::{Severities, Severity};
+use crate::state::Store;
+use crate::status::{Policies, Policy};
+use crate::target::Target;
 use crate::tui::Tui;
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Tries {
     pub total: u32,
     pub successful: u32,
@@ -28,147 +54,1365 @@ impl Tries {
         }
     }
 }
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Stats {
-    pub ignored_status_codes: Vec<u16>,
     pub frequencies: BTreeMap<String, BTreeMap<String, Tries>>,
     pub total: u32,
 }
 
 impl Stats {
-    fn new(ignored_status_codes: Vec<u16>) -> Stats {
-        Stats {
-            ignored_status_codes,
-            total: 0,
-            ..Default::default()
+    fn update(&mut self, finding: bool, path: String, payload: &Payload) {
+        self.total += 1;
+        self.frequencies
+            .entry(path)
+            .or_default()
+            .entry(payload.method.to_string())
+            .or_default()
+            .update(finding);
+    }
+}
+
+/// Checks run on every response
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Check {
+    /// Status codes that are not documented or indicate a server error
+    Status,
+    /// Values reflected truncated at a control character
+    Truncation,
+    /// Content-Type that is not declared for the response
+    ContentType,
+    /// Declared response headers that are missing or invalid
+    Header,
+    /// Properties of JSON responses that are not in their schema
+    Undocumented,
+    /// Stack traces, database errors and debug pages in the body
+    ErrorLeak,
+    /// Values reflected unescaped in HTML or JSON
+    Reflection,
+    /// Emails, card numbers, keys and other sensitive data in the body
+    SensitiveData,
+    /// Assertions of the config file
+    Assertion,
+    /// Callback requests that do not conform to their declaration
+    Callback,
+    /// GET and HEAD requests that are answered differently when repeated
+    Idempotency,
+    /// HEAD requests that are answered differently than GET requests
+    HeadConsistency,
+    /// Methods allowed by OPTIONS responses that differ from the declared ones
+    AllowHeader,
+    /// Preflight requests of hostile origins that are allowed
+    Cors,
+    /// Findings of the `post_response` hook of the script
+    Script,
+    /// Findings of the `check` function of the plugins
+    Plugin,
+    /// Findings of the post hook command
+    Hook,
+    /// Responses delayed by the sleep primitives of the timing strategy
+    TimeBased,
+    /// Requests of the framing strategy that the server neither answers nor
+    /// closes the connection of
+    Hang,
+    /// Successful responses to requests of the invalid strategy that violate
+    /// the schema
+    AcceptedInvalid,
+    /// Successful responses without credentials to operations that declare a
+    /// security requirement
+    AuthRequired,
+    /// Canary urls in url parameters and properties that the server fetches
+    Ssrf,
+    /// Privileged properties of the mass-assignment strategy that the
+    /// resource returns once it was created or updated with them
+    MassAssignment,
+    /// Path parameters of the traversal strategy that are answered with the
+    /// content of a file, or successfully unlike plain values
+    PathTraversal,
+    /// Values of the oob strategy that make the server fetch their url of
+    /// the out-of-band interaction server
+    Oob,
+    /// Redirects to an external host put into a redirect parameter of an
+    /// operation that documents redirects
+    OpenRedirect,
+    /// Numbers of the body that the response echoes with a value a narrower
+    /// type explains, e.g. wrapped around to 32 bits
+    NumericPrecision,
+    /// Server errors for gzip bodies of the gzip strategy, which should be
+    /// rejected when they cannot be decompressed
+    Compression,
+}
+
+/// Names of the checks, the same as in the config file
+const CHECKS: &[(&str, Check)] = &[
+    ("status", Check::Status),
+    ("truncation", Check::Truncation),
+    ("content-type", Check::ContentType),
+    ("header", Check::Header),
+    ("undocumented", Check::Undocumented),
+    ("error-leak", Check::ErrorLeak),
+    ("reflection", Check::Reflection),
+    ("sensitive-data", Check::SensitiveData),
+    ("assertion", Check::Assertion),
+    ("callback", Check::Callback),
+    ("idempotency", Check::Idempotency),
+    ("head-consistency", Check::HeadConsistency),
+    ("allow-header", Check::AllowHeader),
+    ("cors", Check::Cors),
+    ("script", Check::Script),
+    ("plugin", Check::Plugin),
+    ("hook", Check::Hook),
+    ("time-based", Check::TimeBased),
+    ("hang", Check::Hang),
+    ("accepted-invalid", Check::AcceptedInvalid),
+    ("auth-required", Check::AuthRequired),
+    ("ssrf", Check::Ssrf),
+    ("mass-assignment", Check::MassAssignment),
+    ("path-traversal", Check::PathTraversal),
+    ("oob", Check::Oob),
+    ("open-redirect", Check::OpenRedirect),
+    ("numeric-precision", Check::NumericPrecision),
+    ("compression", Check::Compression),
+];
+
+/// Groups of checks that are named together on the command line
+const CHECK_GROUPS: &[(&str, &[Check])] = &[
+    (
+        "schema",
+        &[
+            Check::ContentType,
+            Check::Header,
+            Check::Undocumented,
+            Check::Callback,
+            Check::AcceptedInvalid,
+        ],
+    ),
+    ("leaks", &[Check::ErrorLeak, Check::SensitiveData]),
+    ("latency", &[Check::TimeBased, Check::Hang]),
+    (
+        "consistency",
+        &[
+            Check::Idempotency,
+            Check::HeadConsistency,
+            Check::AllowHeader,
+        ],
+    ),
+    (
+        "custom",
+        &[Check::Assertion, Check::Script, Check::Plugin, Check::Hook],
+    ),
+];
+
+impl Check {
+    /// Every check of the fuzzer
+    pub fn all() -> Vec<Check> {
+        CHECKS.iter().map(|(_, check)| *check).collect()
+    }
+
+    /// The check of the name or the checks of the group, e.g. `leaks` for
+    /// `error-leak` and `sensitive-data`
+    pub fn named(name: &str) -> Result<Vec<Check>, String> {
+        let name = name.trim();
+        if let Some((_, checks)) = CHECK_GROUPS.iter().find(|(group, _)| *group == name) {
+            return Ok(checks.to_vec());
         }
+        CHECKS
+            .iter()
+            .find(|(check, _)| *check == name)
+            .map(|(_, check)| vec![*check])
+            .ok_or_else(|| format!("unknown check `{}`", name))
     }
+}
 
-    fn update(&mut self, resp: &ureq::Response, payload: &Payload) {
-        self.total += 1;
-        let success = !(self.ignored_status_codes.contains(&resp.status())
-            || (payload
-                .responses
-                .responses
-                .contains_key(&StatusCode::Code(resp.status()))
-                && resp.status() / 100 != 5));
+// Campaigns are saved this often to be resumed when they are interrupted
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(30);
 
-        self.frequencies
-            .entry(payload.path.to_string())
-            .or_insert_with(BTreeMap::new)
-            .entry(payload.method.to_string())
-            .or_insert_with(Tries::default)
-            .update(success);
+// Responses larger than this are truncated before they are checked
+pub(crate) const MAX_BODY_SIZE: u64 = 10 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Response {
+    pub status: u16,
+    /// Headers with lower case names
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Response {
+    pub(crate) fn read(resp: ureq::Response) -> Result<Response> {
+        let status = resp.status();
+        let headers = resp
+            .headers_names()
+            .into_iter()
+            .flat_map(|name| {
+                resp.all(&name)
+                    .into_iter()
+                    .map(|value| (name.to_lowercase(), value.to_string()))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        let mut body = Vec::new();
+        resp.into_reader()
+            .take(MAX_BODY_SIZE)
+            .read_to_end(&mut body)
+            .context("unable to read response body")?;
+        Ok(Response {
+            status,
+            headers,
+            body: String::from_utf8_lossy(&body).into_owned(),
+        })
+    }
+
+    /// First value of the header, `name` is in lower case
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Finding of the fuzzer, its details are saved to `file`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Finding {
+    /// Name of the specification
+    pub api: String,
+    pub path: String,
+    pub method: String,
+    /// Status code or name of the check that reported it, e.g. `error-leak`
+    pub kind: String,
+    #[serde(default)]
+    pub severity: Severity,
+    pub reason: Option<String>,
+    pub file: PathBuf,
+}
+
+/// Specification to fuzz together with the servers its requests are sent to
+#[derive(Debug)]
+pub struct Api {
+    pub name: String,
+    pub schema: OpenAPI,
+    pub urls: Vec<Url>,
+}
+
+/// Position of the operation in a pass of stateful fuzzing. Resources are
+/// created before they are read, updated and finally deleted, parents are
+/// created before their children and deleted after them.
+fn dependency_order(path: &str, method: &str) -> (u8, isize) {
+    let depth = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .count() as isize;
+    match method {
+        "POST" => (0, depth),
+        "DELETE" => (2, -depth),
+        _ => (1, depth),
     }
 }
 
 #[derive(Debug)]
 pub struct Fuzzer {
-    schema: OpenAPI,
-    url: Url,
-    ignored_status_codes: Vec<u16>,
-    extra_headers: Vec<(String, String)>,
+    apis: Vec<Api>,
+    status_policies: Policies,
+    /// Checkers run on every response
+    checkers: Checkers,
+    payload_options: PayloadOptions,
     stats: Stats,
-    tui: Tui,
+    /// Resources created in each of the apis
+    stores: Vec<Store>,
+    /// Callbacks registered by the requests and received from the server
+    callbacks: Option<Registry>,
+    /// Canary urls put into the requests, reported when the server fetches
+    /// them
+    canaries: Option<Canaries>,
+    /// Status codes documented for each operation and the ones received
+    statuses: BTreeMap<(String, String), Observed>,
+    /// Summaries of the responses of each operation
+    behavior: Behavior,
+    /// When each rate limited operation was requested the last time
+    last_requests: HashMap<(usize, String, String), Instant>,
+    /// Operations whose CORS preflight requests were probed already
+    cors_probed: BTreeSet<(usize, String, String)>,
+    /// Requests deleting the created resources by their item path
+    cleanup: BTreeMap<String, String>,
+    findings: Vec<Finding>,
+    /// Fuzzing ends after this many requests
+    max_requests: Option<u32>,
+    /// Fuzzing ends once this coverage is reached
+    goal: Option<Goal>,
+    /// Fuzzing ends after this time
+    timeout: Option<Duration>,
+    severities: Severities,
+    /// Requests that conform to the specification sent to every operation
+    /// before fuzzing, their responses are not checked
+    warmup: u32,
+    /// Folder the findings are saved to
+    results_dir: PathBuf,
+    /// Hooks around the requests
+    script: Option<Rc<Script>>,
+    plugins: Vec<Rc<Plugin>>,
+    pre_hook: Option<Hook>,
+    /// Code coverage of the server guiding the requests
+    guide: Option<Guide>,
+    /// Recorded requests that are mutated
+    seeds: Option<Seeds>,
+    /// Api under test that is restarted when it crashes
+    target: Option<Target>,
+    /// Logs of the target stored with the findings
+    logs: Option<LogTail>,
+    /// Command collecting artifacts of 5xx findings and crashes
+    artifact_hook: Option<Hook>,
+    /// Runs and findings kept across campaigns
+    database: Option<Database>,
+    /// File every request and its response are written to in full
+    exchanges: Option<File>,
+    /// Interrupted campaign that is continued
+    resume: Option<Checkpoint>,
+    /// Terminal interface, fuzzing only ends after the budget of requests
+    /// without it
+    tui: Option<Tui>,
 }
 
 impl Fuzzer {
     pub fn new(
-        schema: OpenAPI,
-        url: Url,
+        apis: Vec<Api>,
         ignored_status_codes: Vec<u16>,
-        extra_headers: Vec<(String, String)>,
+        status_policies: Policies,
+        detectors: Detectors,
+        listener: Option<Listener>,
+        payload_options: PayloadOptions,
+        cleanup: BTreeMap<String, String>,
     ) -> Fuzzer {
+        let stores = apis
+            .iter()
+            .map(|api| match payload_options.stateful {
+                true => Store::new(&api.schema),
+                false => Store::default(),
+            })
+            .collect();
         Fuzzer {
-            schema,
-            url,
-            extra_headers,
-            ignored_status_codes: ignored_status_codes.clone(),
-            stats: Stats::new(ignored_status_codes),
-            tui: Tui::new().expect("unable to setup tui"),
+            stores,
+            apis,
+            checkers: Checkers::builtin(ignored_status_codes, status_policies.clone(), detectors),
+            status_policies,
+            callbacks: listener.map(Registry::new),
+            canaries: None,
+            payload_options,
+            stats: Stats::default(),
+            statuses: BTreeMap::new(),
+            behavior: Behavior::default(),
+            last_requests: HashMap::new(),
+            cors_probed: BTreeSet::new(),
+            cleanup,
+            findings: Vec::new(),
+            max_requests: None,
+            goal: None,
+            timeout: None,
+            severities: Severities::default(),
+            warmup: 0,
+            results_dir: PathBuf::from("results"),
+            script: None,
+            plugins: Vec::new(),
+            pre_hook: None,
+            guide: None,
+            seeds: None,
+            target: None,
+            logs: None,
+            artifact_hook: None,
+            database: None,
+            exchanges: None,
+            resume: None,
+            tui: None,
+        }
+    }
+
+    /// Shows the progress in the terminal until `q` is pressed
+    pub fn with_tui(mut self) -> Result<Fuzzer> {
+        self.tui = Some(Tui::new().context("unable to setup tui")?);
+        Ok(self)
+    }
+
+    /// Ends fuzzing after the number of requests
+    pub fn max_requests(mut self, max_requests: u32) -> Fuzzer {
+        self.max_requests = Some(max_requests);
+        self
+    }
+
+    /// Ends fuzzing once the coverage is reached
+    pub fn until_coverage(mut self, goal: Goal) -> Fuzzer {
+        self.goal = Some(goal);
+        self
+    }
+
+    /// Sends the requests with this version of HTTP instead of 1.1
+    pub fn http_version(mut self, version: HttpVersion) -> Fuzzer {
+        self.payload_options.http_version = version;
+        self
+    }
+
+    /// Classifies the findings with the severities instead of the default ones
+    pub fn severities(mut self, severities: Severities) -> Fuzzer {
+        self.severities = severities;
+        self
+    }
+
+    /// Ends fuzzing after the time
+    pub fn timeout(mut self, timeout: Duration) -> Fuzzer {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sends the number of requests that conform to the specification to
+    /// every operation before fuzzing, e.g. so that caches and the JIT of the
+    /// target are warm
+    pub fn warmup(mut self, requests: u32) -> Fuzzer {
+        self.warmup = requests;
+        self
+    }
+
+    /// Whether the coverage goal was reached, true without a goal
+    pub fn coverage_reached(&self) -> bool {
+        match self.goal {
+            Some(goal) => self
+                .statuses
+                .values()
+                .all(|observed| goal.is_reached(observed)),
+            None => true,
         }
     }
 
+    /// Runs the hooks of the script around every request
+    pub fn script(mut self, script: Script) -> Fuzzer {
+        let script = Rc::new(script);
+        self.checkers.register(ScriptCheck(Rc::clone(&script)));
+        self.script = Some(script);
+        self
+    }
+
+    /// Lets the plugin generate requests or check responses, plugins run in
+    /// the order they were added
+    pub fn plugin(mut self, plugin: Plugin) -> Fuzzer {
+        let plugin = Rc::new(plugin);
+        self.checkers.register(PluginCheck(Rc::clone(&plugin)));
+        self.plugins.push(plugin);
+        self
+    }
+
+    /// Puts canary urls of the out-of-band interaction server into the url
+    /// parameters and properties of the requests and the values of the oob
+    /// strategy, the server fetching them is a potential SSRF or blind
+    /// injection
+    pub fn oob(mut self, listener: Listener) -> Fuzzer {
+        self.canaries = Some(Canaries::new(listener));
+        self
+    }
+
+    /// Runs the command before every request, it may change or veto them
+    pub fn pre_hook(mut self, hook: Hook) -> Fuzzer {
+        self.pre_hook = Some(hook);
+        self
+    }
+
+    /// Runs the command after every response, it may report findings
+    pub fn post_hook(mut self, hook: Hook) -> Fuzzer {
+        self.checkers.register(HookCheck(hook));
+        self
+    }
+
+    /// Runs the checker on every response after the ones added before it,
+    /// e.g. to detect findings specific to the api under test
+    pub fn checker(mut self, checker: impl Checker + 'static) -> Fuzzer {
+        self.checkers.register(checker);
+        self
+    }
+
+    /// Keeps the requests that increase the code coverage of the server and
+    /// mutates them in later requests
+    pub fn guide(mut self, guide: Guide) -> Fuzzer {
+        self.guide = Some(guide);
+        self
+    }
+
+    /// Mutates the recorded requests of the operations in half of their
+    /// requests
+    pub fn seeds(mut self, seeds: Seeds) -> Fuzzer {
+        self.seeds = Some(seeds);
+        self
+    }
+
+    /// Reports the exits of the target, which is restarted after them
+    pub fn supervise(mut self, target: Target) -> Fuzzer {
+        self.target = Some(target);
+        self
+    }
+
+    /// Stores the lines the target logged around each finding with it
+    pub fn logs(mut self, logs: LogTail) -> Fuzzer {
+        self.logs = Some(logs);
+        self
+    }
+
+    /// Runs the command for every finding with a 5xx status code and every
+    /// crash of the target, the files it collects are linked to the finding
+    pub fn artifact_hook(mut self, hook: Hook) -> Fuzzer {
+        self.artifact_hook = Some(hook);
+        self
+    }
+
+    /// Stores the run, its deduplicated findings and the requests answered
+    /// with a status code for the first time in the database
+    pub fn database(mut self, database: Database) -> Fuzzer {
+        self.database = Some(database);
+        self
+    }
+
+    /// Writes every request and its response in full to the file
+    pub fn log_exchanges(mut self, file: File) -> Fuzzer {
+        self.exchanges = Some(file);
+        self
+    }
+
+    /// Ends the run in the database, returns the number of its findings and
+    /// how many of them were not found in earlier runs
+    pub fn finish_run(&self) -> Result<Option<(u32, u32)>> {
+        self.database
+            .as_ref()
+            .map(|database| database.finish(self.stats.total))
+            .transpose()
+    }
+
+    /// Continues the interrupted campaign of the checkpoint, its requests and
+    /// time count towards the limits of this one
+    pub fn resume(mut self, checkpoint: Checkpoint) -> Fuzzer {
+        self.resume = Some(checkpoint);
+        self
+    }
+
+    /// Code coverage the server reported last, if fuzzing is guided by it,
+    /// and the number of requests that increased it
+    pub fn server_coverage(&self) -> Option<(u64, usize)> {
+        self.guide
+            .as_ref()
+            .map(|guide| (guide.covered(), guide.corpus_size()))
+    }
+
+    /// Saves the findings to the folder instead of `results`
+    pub fn results_dir(mut self, results_dir: impl Into<PathBuf>) -> Fuzzer {
+        self.results_dir = results_dir.into();
+        self
+    }
+
     pub fn run(&mut self) -> Result<()> {
+        if self.warmup > 0 {
+            self.payload_options.conforming = true;
+            let warmed_up = self.warm_up();
+            self.payload_options.conforming = false;
+            warmed_up?;
+        }
+        let start = Instant::now();
         let mut message = None;
+        let mut requests = 0;
+        // Paths of all specifications take turns
+        let mut paths = self
+            .apis
+            .iter()
+            .map(|api| api.schema.paths.iter())
+            .collect::<Vec<_>>();
+        let mut interleaved = Vec::new();
         loop {
-            for (path, ref_or_item) in self.schema.paths.iter() {
-                let item = ref_or_item.to_item_ref();
-                for payload in Payload::for_all_methods(&self.url, path, item, &self.extra_headers)?
-                {
-                    match self.send_request(&payload) {
+            let before = interleaved.len();
+            for (index, paths) in paths.iter_mut().enumerate() {
+                if let Some((path, ref_or_item)) = paths.next() {
+                    let item = ref_or_item.to_item_ref();
+                    for (method, operation) in payload::operations(item) {
+                        interleaved.push((index, path.as_str(), item, method, operation));
+                    }
+                }
+            }
+            if interleaved.len() == before {
+                break;
+            }
+        }
+        if self.payload_options.stateful {
+            interleaved.sort_by_key(|&(_, path, _, method, _)| dependency_order(path, method));
+        }
+        // Every operation takes turns over the servers of its specification on its own,
+        // otherwise an operation could always be picked when the same server is next
+        let apis = &self.apis;
+        let mut servers = interleaved
+            .iter()
+            .map(|&(index, ..)| apis[index].urls.iter().cycle())
+            .collect::<Vec<_>>();
+        // Operations that are never answered show up in the coverage as well
+        for &(index, path, _, method, operation) in &interleaved {
+            if self.payload_options.filter.matches(path, method, operation) {
+                let path = match self.apis.len() {
+                    1 => path.to_string(),
+                    _ => format!("{}: {}", self.apis[index].name, path),
+                };
+                self.statuses
+                    .entry((path, method.to_string()))
+                    .or_insert_with(|| Observed::new(&operation.responses));
+            }
+        }
+        let budgets = interleaved
+            .iter()
+            .map(|&(_, path, _, method, operation)| {
+                let config = self
+                    .payload_options
+                    .operations
+                    .for_operation(path, method, operation);
+                Budget::new(&config)
+            })
+            .collect();
+        let mut scheduler = Scheduler::new(budgets);
+        let keys = interleaved
+            .iter()
+            .map(|&(index, path, _, method, _)| (index, path.to_string(), method.to_string()))
+            .collect::<Vec<_>>();
+        let mut resumed = Duration::ZERO;
+        if let Some(checkpoint) = self.resume.take() {
+            requests = checkpoint.requests;
+            resumed = checkpoint.elapsed;
+            for state in checkpoint.operations {
+                if let Some(position) = keys.iter().position(|key| *key == state.operation) {
+                    scheduler.restore(position, state.progress);
+                }
+                let (index, path, method) = state.operation;
+                let path = match self.apis.len() {
+                    1 => path,
+                    _ => format!("{}: {}", self.apis[index].name, path),
+                };
+                if let Some(observed) = self.statuses.get_mut(&(path, method)) {
+                    observed.restore(state.received, state.responses);
+                }
+            }
+            self.stats = checkpoint.stats;
+            self.findings = checkpoint.findings;
+            if let Some(guide) = &mut self.guide {
+                guide.restore(checkpoint.corpus);
+            }
+        }
+        let mut last_checkpoint = Instant::now();
+
+        loop {
+            let mut sent = false;
+            for (position, &(index, path, item, method, operation)) in
+                interleaved.iter().enumerate()
+            {
+                if !scheduler.pick(position) {
+                    continue;
+                }
+                let api = &self.apis[index];
+                let url = servers[position].next().expect("at least one url to fuzz");
+                let payload = Payload::for_operation(
+                    url,
+                    path,
+                    item,
+                    method,
+                    operation,
+                    &self.payload_options,
+                    &self.stores[index],
+                )?;
+                if let Some(mut payload) = payload {
+                    // Rate limited operations are skipped until it is their turn again
+                    if let Some(rate_limit) = payload.config.rate_limit {
+                        let key = (index, path.to_string(), payload.method.to_string());
+                        let interval = Duration::from_secs_f64(1.0 / rate_limit);
+                        match self.last_requests.get(&key) {
+                            Some(last) if last.elapsed() < interval => continue,
+                            _ => self.last_requests.insert(key, Instant::now()),
+                        };
+                    }
+                    sent = true;
+                    let key = (index, path.to_string(), payload.method.to_string());
+                    let seeds = self
+                        .seeds
+                        .as_ref()
+                        .and_then(|seeds| seeds.get(path, payload.method));
+                    if let Some(seeds) = seeds {
+                        if rand::random::<bool>() {
+                            guide::mutate(seeds, &mut payload)?;
+                        }
+                    }
+                    if let Some(guide) = &self.guide {
+                        guide.mutate(&key, &mut payload)?;
+                    }
+                    for plugin in &self.plugins {
+                        plugin.generate(&mut payload)?;
+                    }
+                    if let Some(script) = &self.script {
+                        script.pre_request(&mut payload)?;
+                    }
+                    let vetoed = match &self.pre_hook {
+                        Some(hook) => !hook.pre_request(&mut payload)?,
+                        None => false,
+                    };
+                    if let Some(registry) = self
+                        .callbacks
+                        .as_mut()
+                        .filter(|_| payload.config.check_enabled(Check::Callback))
+                    {
+                        registry.register(index, operation, &mut payload)?;
+                    }
+                    if let Some(canaries) = self.canaries.as_mut() {
+                        canaries.inject(index, item, operation, &mut payload)?;
+                    }
+                    let started = Instant::now();
+                    let resp = match vetoed {
+                        true => Err(anyhow!("request vetoed by the pre hook")),
+                        false => self.send_request(&payload),
+                    };
+                    let failed = !vetoed && resp.is_err();
+                    match resp {
                         Ok(resp) => {
-                            self.check_response(&resp, &payload)?;
-                            self.stats.update(&resp, &payload);
+                            let elapsed = started.elapsed();
+                            let mut findings = Vec::new();
+                            findings.extend(self.check_response(api, &resp, &payload, elapsed)?);
+                            if payload.config.check_enabled(Check::Cors)
+                                && self.cors_probed.insert((
+                                    index,
+                                    path.to_string(),
+                                    payload.method.to_string(),
+                                ))
+                            {
+                                findings.extend(self.probe_cors(api, &payload)?);
+                            }
+                            if let Some(database) = &self.database {
+                                for finding in &findings {
+                                    database
+                                        .record_response(finding, &script::response_value(&resp))?;
+                                }
+                            }
+                            self.stores[index].observe(&payload, &resp);
+                            let path = match self.apis.len() {
+                                1 => path.to_string(),
+                                _ => format!("{}: {}", api.name, path),
+                            };
+                            let observed = self
+                                .statuses
+                                .entry((path.clone(), payload.method.to_string()))
+                                .or_insert_with(|| Observed::new(payload.responses));
+                            let new_status = !observed.has_received(&StatusCode::Code(resp.status));
+                            observed.receive(resp.status);
+                            self.behavior
+                                .observe(format!("{} {}", payload.method, path), &resp);
+                            let increased = match &mut self.guide {
+                                Some(guide) => guide.observe(key, &payload)?,
+                                None => false,
+                            };
+                            let interest = match (new_status, increased) {
+                                (true, _) => Some("new status"),
+                                (false, true) => Some("coverage"),
+                                (false, false) => None,
+                            };
+                            if let (Some(database), Some(interest)) = (&self.database, interest) {
+                                database.record_request(
+                                    (&api.name, payload.method, payload.path),
+                                    resp.status,
+                                    interest,
+                                    &json!(payload),
+                                )?;
+                            }
+                            scheduler.observe(
+                                position,
+                                Some(resp.status),
+                                !findings.is_empty() || increased,
+                                elapsed,
+                            );
+                            self.stats.update(!findings.is_empty(), path, &payload);
+                            self.findings.extend(findings);
                             message = None;
                         }
-                        Err(e) => message = Some(e.to_string()),
+                        Err(e) => {
+                            if let Some(hang) = e
+                                .downcast_ref::<framing::Hang>()
+                                .filter(|_| payload.config.check_enabled(Check::Hang))
+                            {
+                                let finding = self.save_finding(
+                                    api,
+                                    &payload,
+                                    "hang",
+                                    Some(hang.to_string()),
+                                )?;
+                                self.findings.push(finding);
+                            }
+                            if !vetoed {
+                                scheduler.observe(position, None, false, started.elapsed());
+                            }
+                            message = Some(e.to_string());
+                        }
+                    };
+                    let crash = match &mut self.target {
+                        Some(target) => target.crashed(failed)?,
+                        None => None,
                     };
+                    if let Some(crash) = crash {
+                        let finding = json!({
+                            "request_id": payload.request_id,
+                            "payload": payload,
+                            "curl": payload.to_curl()?,
+                            "reason": crash.reason,
+                            "output": crash.output,
+                        });
+                        let finding = self.record(
+                            api,
+                            &payload.url,
+                            payload.path,
+                            payload.method,
+                            "crash",
+                            finding,
+                        )?;
+                        self.findings.push(finding);
+                    }
+                    // Failed requests count as well, the server may be down
+                    requests += 1;
+                    if self
+                        .max_requests
+                        .is_some_and(|max_requests| requests >= max_requests)
+                        || self
+                            .timeout
+                            .is_some_and(|timeout| resumed + start.elapsed() >= timeout)
+                        || (self.goal.is_some() && self.coverage_reached())
+                    {
+                        let findings = self.check_callbacks()?;
+                        self.findings.extend(findings);
+                        return self.save_checkpoint(
+                            &scheduler,
+                            &keys,
+                            requests,
+                            resumed + start.elapsed(),
+                        );
+                    }
                 }
             }
-            if self
-                .tui
-                .display(&self.stats, &message)
-                .context("unable to draw tui")?
-            {
-                return Ok(());
+            if last_checkpoint.elapsed() >= CHECKPOINT_INTERVAL {
+                self.save_checkpoint(&scheduler, &keys, requests, resumed + start.elapsed())?;
+                last_checkpoint = Instant::now();
+            }
+            let findings = self.check_callbacks()?;
+            self.findings.extend(findings);
+            // Fuzzing is done once every operation used up its budget
+            if scheduler.exhausted() {
+                return self.save_checkpoint(
+                    &scheduler,
+                    &keys,
+                    requests,
+                    resumed + start.elapsed(),
+                );
+            }
+            if !sent {
+                thread::sleep(Duration::from_millis(10));
+            }
+            if let Some(tui) = &mut self.tui {
+                if tui
+                    .display(&self.stats, &message)
+                    .context("unable to draw tui")?
+                {
+                    return self.save_checkpoint(
+                        &scheduler,
+                        &keys,
+                        requests,
+                        resumed + start.elapsed(),
+                    );
+                }
             }
         }
     }
 
-    fn send_request(&self, payload: &Payload) -> Result<ureq::Response> {
-        let mut path_with_params = payload.path.to_owned();
-        for (name, value) in payload.path_params.iter() {
-            path_with_params = path_with_params.replace(&format!("{{{}}}", name), value);
+    /// Saves the state of the campaign to the results directory
+    fn save_checkpoint(
+        &self,
+        scheduler: &Scheduler,
+        keys: &[OperationKey],
+        requests: u32,
+        elapsed: Duration,
+    ) -> Result<()> {
+        let operations = keys
+            .iter()
+            .enumerate()
+            .map(|(position, key)| {
+                let (index, path, method) = key;
+                let path = match self.apis.len() {
+                    1 => path.clone(),
+                    _ => format!("{}: {}", self.apis[*index].name, path),
+                };
+                let (received, responses) = self
+                    .statuses
+                    .get(&(path, method.clone()))
+                    .map(|observed| {
+                        let (received, responses) = observed.received();
+                        (received.clone(), responses)
+                    })
+                    .unwrap_or_default();
+                OperationState {
+                    operation: key.clone(),
+                    progress: scheduler.progress(position),
+                    received,
+                    responses,
+                }
+            })
+            .collect();
+        Checkpoint {
+            requests,
+            elapsed,
+            operations,
+            stats: self.stats.clone(),
+            findings: self.findings.clone(),
+            corpus: self.guide.as_ref().map(Guide::corpus).unwrap_or_default(),
         }
-        let mut request = ureq::request_url(
-            payload.method,
-            &payload.url.join(path_with_params.trim_start_matches('/'))?,
-        );
+        .save(&self.results_dir)
+    }
 
-        for (param, value) in payload.query_params.iter() {
-            request = request.query(param, value)
+    /// Checks the callbacks and the canary urls received since the last time,
+    /// interactions are reported by the check that put the url in
+    fn check_callbacks(&self) -> Result<Vec<Finding>> {
+        let mut findings = Vec::new();
+        for (received, pending) in self.canaries.iter().flat_map(Canaries::received) {
+            let api = &self.apis[pending.api];
+            let mut finding = pending.finding.clone();
+            finding["reason"] = json!(format!(
+                "the server sent a {} request to the canary url of {}",
+                received.method, pending.location
+            ));
+            finding["canary"] = json!({"method": received.method, "body": received.body});
+            findings.push(self.record(
+                api,
+                &pending.url,
+                &pending.path,
+                &pending.method,
+                pending.check,
+                finding,
+            )?);
         }
+        let registry = match &self.callbacks {
+            Some(registry) => registry,
+            None => return Ok(findings),
+        };
+        for (received, pending) in registry.received() {
+            let api = &self.apis[pending.api];
+            if let Some(reason) = callbacks::check(&pending.callback, &received, &api.schema) {
+                let mut finding = pending.finding.clone();
+                finding["reason"] = json!(reason);
+                finding["callback"] = json!({"method": received.method, "body": received.body});
+                findings.push(self.record(
+                    api,
+                    &pending.url,
+                    &pending.path,
+                    &pending.method,
+                    "callback",
+                    finding,
+                )?);
+            }
+        }
+        Ok(findings)
+    }
+
+    /// Findings of the fuzzer so far
+    pub fn findings(&self) -> &[Finding] {
+        &self.findings
+    }
 
-        for (header, value) in payload.headers.iter() {
-            request = request.set(header, value)
+    /// Status codes that are documented for the fuzzed operations, but were
+    /// never received, when their class has the `missing` policy
+    pub fn missing_statuses(&self) -> Vec<String> {
+        let mut missing = Vec::new();
+        for ((path, method), observed) in &self.statuses {
+            for status in observed.documented() {
+                let class = match *status {
+                    StatusCode::Code(code) => code,
+                    StatusCode::Range(class) => class * 100,
+                };
+                if !observed.has_received(status)
+                    && self.status_policies.for_status(class) == Policy::Missing
+                {
+                    missing.push(format!(
+                        "{} {}: documented status {} was never received",
+                        method, path, status
+                    ));
+                }
+            }
         }
+        missing
+    }
 
-        if !payload.body.is_empty() {
-            Ok(request.send_json(payload.body[0].clone()).or_any_status()?)
-        } else {
-            request.call().or_any_status().map_err(|e| e.into())
+    /// Summaries of the responses of every operation so far
+    pub fn behavior(&self) -> &Behavior {
+        &self.behavior
+    }
+
+    /// Operations that were selected for fuzzing together with the status
+    /// codes they were answered with
+    pub fn coverage(&self) -> Coverage {
+        Coverage {
+            operations: self
+                .statuses
+                .iter()
+                .map(|((path, method), observed)| OperationCoverage::new(path, method, observed))
+                .collect(),
         }
     }
 
-    fn check_response(&self, resp: &ureq::Response, payload: &Payload) -> Result<()> {
-        let responses = &payload.responses.responses;
+    /// Deletes the resources created during stateful fuzzing, with the request
+    /// given for their item path in the config file or the DELETE of their
+    /// item path. Returns the cleanups that failed and a summary.
+    pub fn cleanup(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        let (mut deleted, mut total) = (0, 0);
+        for (api, store) in self.apis.iter().zip(&self.stores) {
+            for created in store.created() {
+                total += 1;
+                let (method, path) = match self.cleanup.get(&created.item) {
+                    Some(template) if template.trim().is_empty() => continue,
+                    Some(template) => match template.trim().split_once(' ') {
+                        Some((method, path)) => (method.to_uppercase(), path.trim().to_string()),
+                        None => ("DELETE".to_string(), template.trim().to_string()),
+                    },
+                    None => match api.schema.paths.get(&created.item) {
+                        Some(item) if item.to_item_ref().delete.is_some() => {
+                            ("DELETE".to_string(), created.item.clone())
+                        }
+                        _ => continue,
+                    },
+                };
+                let mut path = path;
+                for (name, value) in &created.path_params {
+                    path = path.replace(&format!("{{{}}}", name), value);
+                }
+                let result = created
+                    .url
+                    .join(path.trim_start_matches('/'))
+                    .map_err(anyhow::Error::from)
+                    .and_then(|url| {
+                        let mut request = ureq::request_url(&method, &url);
+                        for (header, value) in &self.payload_options.extra_headers {
+                            request = request.set(header, value);
+                        }
+                        Ok(request.call().or_any_status()?.status())
+                    });
+                match result {
+                    // The resource may have been deleted by a fuzzed request
+                    Ok(200..=299 | 404 | 410) => deleted += 1,
+                    Ok(status) => lines.push(format!(
+                        "cleanup {} {} failed with {}",
+                        method, path, status
+                    )),
+                    Err(e) => lines.push(format!("cleanup {} {} failed: {}", method, path, e)),
+                }
+            }
+        }
+        if total > 0 {
+            lines.push(format!(
+                "deleted {} of {} resources created during fuzzing",
+                deleted, total
+            ));
+        }
+        lines
+    }
 
-        // known non 500 and ingored status codes are OK
-        if self.ignored_status_codes.contains(&resp.status())
-            || (responses.contains_key(&StatusCode::Code(resp.status()))
-                && resp.status() / 100 != 5)
-        {
-            return Ok(());
+    /// Sends the warmup requests of every operation, failures and findings of
+    /// the target while it warms up are not reported
+    fn warm_up(&self) -> Result<()> {
+        for (index, api) in self.apis.iter().enumerate() {
+            for (path, item) in &api.schema.paths {
+                let item = item.to_item_ref();
+                for (method, operation) in payload::operations(item) {
+                    for n in 0..self.warmup {
+                        let url = &api.urls[n as usize % api.urls.len()];
+                        let payload = Payload::for_operation(
+                            url,
+                            path,
+                            item,
+                            method,
+                            operation,
+                            &self.payload_options,
+                            &self.stores[index],
+                        )?;
+                        match payload {
+                            Some(payload) => {
+                                let _ = self.send_request(&payload);
+                            }
+                            None => break,
+                        }
+                    }
+                }
+            }
         }
+        Ok(())
+    }
+
+    /// Generates a request for the first operation the filter selects, runs
+    /// the hooks on it like fuzzing does and sends it. The request is returned
+    /// together with its response, which is not checked.
+    pub fn send_one(&self) -> Result<Option<(Payload<'_>, Response)>> {
+        for (index, api) in self.apis.iter().enumerate() {
+            for (path, item) in &api.schema.paths {
+                let item = item.to_item_ref();
+                for (method, operation) in payload::operations(item) {
+                    let payload = Payload::for_operation(
+                        &api.urls[0],
+                        path,
+                        item,
+                        method,
+                        operation,
+                        &self.payload_options,
+                        &self.stores[index],
+                    )?;
+                    let mut payload = match payload {
+                        Some(payload) => payload,
+                        None => continue,
+                    };
+                    for plugin in &self.plugins {
+                        plugin.generate(&mut payload)?;
+                    }
+                    if let Some(script) = &self.script {
+                        script.pre_request(&mut payload)?;
+                    }
+                    if let Some(hook) = &self.pre_hook {
+                        if !hook.pre_request(&mut payload)? {
+                            bail!("request vetoed by the pre hook");
+                        }
+                    }
+                    let resp = self.send_request(&payload)?;
+                    return Ok(Some((payload, resp)));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn send_request(&self, payload: &Payload) -> Result<Response> {
+        self.send_request_as(payload, payload.method)
+    }
+
+    /// Sends the payload with another method
+    fn send_request_as(&self, payload: &Payload, method: &str) -> Result<Response> {
+        let resp = payload.send(method);
+        self.log_exchange(payload, method, &resp)?;
+        resp
+    }
 
-        let results_dir = format!(
-            "results/{}/{}/{}",
-            payload.path.trim_matches('/').replace("/", "-"),
+    /// Writes the request and its response, or why it failed, to the exchange
+    /// log like `curl -v` does
+    fn log_exchange(&self, payload: &Payload, method: &str, resp: &Result<Response>) -> Result<()> {
+        let mut log = match &self.exchanges {
+            Some(log) => log,
+            None => return Ok(()),
+        };
+        let mut exchange = format!("> {} {}\n", method, payload.sent_url()?);
+        exchange += &format!("> {}: {}\n", REQUEST_ID_HEADER, payload.request_id);
+        for (name, value) in &payload.headers {
+            exchange += &format!("> {}: {}\n", name, value);
+        }
+        if let Some((_, body)) = payload.encoded_body() {
+            exchange += ">\n";
+            for line in String::from_utf8_lossy(&body).lines() {
+                exchange += &format!("> {}\n", line);
+            }
+        }
+        match resp {
+            Ok(resp) => {
+                exchange += &format!("< {}\n", resp.status);
+                for (name, value) in &resp.headers {
+                    exchange += &format!("< {}: {}\n", name, value);
+                }
+                if !resp.body.is_empty() {
+                    exchange += "<\n";
+                    for line in resp.body.lines() {
+                        exchange += &format!("< {}\n", line);
+                    }
+                }
+            }
+            Err(e) => exchange += &format!("< request failed: {:#}\n", e),
+        }
+        exchange += "\n";
+        // One write per exchange keeps them apart when the file is followed
+        log.write_all(exchange.as_bytes())?;
+        Ok(())
+    }
+
+    /// Checks the response and saves the payload when it is a finding
+    fn check_response(
+        &self,
+        api: &Api,
+        resp: &Response,
+        payload: &Payload,
+        elapsed: Duration,
+    ) -> Result<Option<Finding>> {
+        let send = |payload: &Payload, method: &str| self.send_request_as(payload, method);
+        let exchange = Exchange::new(api, payload, resp, elapsed, &send);
+        match self.checkers.check(&exchange)? {
+            Some(issue) => self
+                .save_finding(api, payload, &issue.kind, issue.reason)
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Sends preflight requests from hostile origins for the operation of the
+    /// payload and saves the first one that is allowed in a dangerous way
+    fn probe_cors(&self, api: &Api, payload: &Payload) -> Result<Option<Finding>> {
+        let url = payload.request_url()?;
+        let origins = [
+            "https://evil.example".to_string(),
+            "null".to_string(),
+            // Prefix and suffix matching of the allowed origins
+            format!(
+                "{}://{}.evil.example",
+                url.scheme(),
+                url.host_str().unwrap_or_default()
+            ),
+        ];
+        for origin in &origins {
+            let resp = ureq::request_url("OPTIONS", &url)
+                .set(REQUEST_ID_HEADER, &payload.request_id)
+                .set("origin", origin)
+                .set("access-control-request-method", payload.method)
+                .set("access-control-request-headers", "content-type")
+                .call()
+                .or_any_status()
+                .map_err(anyhow::Error::from)
+                .and_then(Response::read);
+            let resp = match resp {
+                Ok(resp) => resp,
+                Err(_) => continue,
+            };
+            if let Some(reason) = checks::cors_misconfiguration(origin, &resp) {
+                let curl = format!(
+                    "curl -X OPTIONS -H '{}:{}' -H 'Origin:{}' \
+                     -H 'Access-Control-Request-Method:{}' {}",
+                    REQUEST_ID_HEADER, payload.request_id, origin, payload.method, url
+                );
+                let finding = json!({
+                    "request_id": payload.request_id,
+                    "origin": origin,
+                    "reason": reason,
+                    "curl": curl,
+                });
+                return self
+                    .record(
+                        api,
+                        &payload.url,
+                        payload.path,
+                        payload.method,
+                        "cors",
+                        finding,
+                    )
+                    .map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    fn save_finding(
+        &self,
+        api: &Api,
+        payload: &Payload,
+        kind: &str,
+        reason: Option<String>,
+    ) -> Result<Finding> {
+        let mut finding = json!({
+            "request_id": payload.request_id,
+            "payload": payload,
+            "curl": payload.to_curl()?,
+        });
+        if let Some(reason) = reason {
+            finding["reason"] = json!(reason);
+        }
+        self.record(
+            api,
+            &payload.url,
+            payload.path,
             payload.method,
-            resp.status()
-        );
-        let results_file = format!(
-            "{}/{}.json",
-            results_dir,
-            format!("{:x}", rand::random::<u32>())
+            kind,
+            finding,
+        )
+    }
+
+    /// Saves the details of the finding, the reason is taken from them
+    fn record(
+        &self,
+        api: &Api,
+        url: &Url,
+        path: &str,
+        method: &str,
+        kind: &str,
+        mut details: serde_json::Value,
+    ) -> Result<Finding> {
+        let dir = self.finding_dir(api, url, path, method, kind);
+        let reason = details["reason"].as_str().map(str::to_string);
+        let severity = self.severities.of(kind);
+        details["severity"] = json!(severity);
+        let file = self.write_finding(api, &dir, kind, &mut details)?;
+        let finding = Finding {
+            api: api.name.clone(),
+            path: path.to_string(),
+            method: method.to_string(),
+            kind: kind.to_string(),
+            severity,
+            reason,
+            file,
+        };
+        if let Some(database) = &self.database {
+            database.record_finding(&finding, &details)?;
+        }
+        Ok(finding)
+    }
+
+    fn finding_dir(&self, api: &Api, url: &Url, path: &str, method: &str, kind: &str) -> String {
+        let mut results_dir = self.results_dir.to_string_lossy().into_owned();
+        // Findings of each specification and server are kept apart when
+        // fuzzing more of them
+        if self.apis.len() > 1 {
+            results_dir += &format!("/{}", api.name);
+        }
+        if api.urls.len() > 1 {
+            results_dir += &format!(
+                "/{}",
+                url[url::Position::BeforeHost..url::Position::AfterPath]
+                    .trim_matches('/')
+                    .replace(|c: char| !c.is_ascii_alphanumeric() && c != '.', "-")
+            );
+        }
+        results_dir += &format!(
+            "/{}/{}/{}",
+            path.trim_matches('/').replace('/', "-"),
+            method,
+            kind
         );
-        fs::create_dir_all(&results_dir)?;
+        results_dir
+    }
+
+    fn write_finding(
+        &self,
+        api: &Api,
+        results_dir: &str,
+        kind: &str,
+        finding: &mut serde_json::Value,
+    ) -> Result<PathBuf> {
+        let name = format!("{:x}", rand::random::<u32>());
+        let results_file = format!("{}/{}.json", results_dir, name);
+        fs::create_dir_all(results_dir)?;
 
+        if self.apis.len() > 1 {
+            finding["spec"] = json!(api.name);
+        }
+        if let Some(logs) = &self.logs {
+            finding["logs"] = json!(logs.around_now());
+        }
+        let server_error = kind.parse::<u16>().is_ok_and(|status| status >= 500);
+        if let Some(hook) = self
+            .artifact_hook
+            .as_ref()
+            .filter(|_| server_error || kind == "crash")
+        {
+            let dir = PathBuf::from(format!("{}/{}-artifacts", results_dir, name));
+            finding["artifacts"] = json!(hook.collect_artifacts(finding, &dir)?);
+        }
         serde_json::to_writer_pretty(
             &File::create(&results_file).context(format!("unable to create {}", &results_file))?,
-            &json!({"payload": payload, "curl": payload.to_curl()?}),
-        )
-        .map_err(|e| e.into())
+            finding,
+        )?;
+        Ok(PathBuf::from(results_file))
     }
 }
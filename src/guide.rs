@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Context, Result};
+use arbitrary::Unstructured;
+use rand::{seq::IteratorRandom, seq::SliceRandom, Rng};
+use regex::Regex;
+use serde_json::Value;
+use url::Url;
+
+use crate::checkpoint::OperationKey;
+use crate::payload::Payload;
+use crate::script::{apply_request, request_value};
+use crate::webhooks;
+
+// Requests of an operation that increased the coverage, the oldest ones are
+// dropped first
+const MAX_CORPUS: usize = 64;
+
+/// Number of covered lines in a plain number, a JSON document with the
+/// `totals.covered_lines` of coverage.py or a `covered` field, or the line
+/// counter of a JaCoCo XML report
+fn covered(body: &str) -> Option<u64> {
+    let body = body.trim();
+    if let Ok(covered) = body.parse::<f64>() {
+        return Some(covered as u64);
+    }
+    if let Ok(document) = serde_json::from_str::<Value>(body) {
+        return ["/totals/covered_lines", "/covered", "/coverage"]
+            .iter()
+            .find_map(|pointer| document.pointer(pointer)?.as_f64())
+            .map(|covered| covered as u64);
+    }
+    // The counters of the whole report come after the ones of the packages
+    let counter = Regex::new(r#"<counter type="LINE" missed="\d+" covered="(\d+)"\s*/>"#)
+        .expect("valid counter pattern");
+    counter
+        .captures_iter(body)
+        .last()
+        .and_then(|captures| captures[1].parse().ok())
+}
+
+/// Replaces the payload by a mutation of one of the requests of the corpus,
+/// either with one freshly generated parameter or with a mutated body
+pub(crate) fn mutate(corpus: &[Value], payload: &mut Payload) -> Result<()> {
+    let mut rng = rand::thread_rng();
+    let mut request = match corpus.choose(&mut rng) {
+        Some(request) => request.clone(),
+        None => return Ok(()),
+    };
+    // One value of the request is the freshly generated one
+    let fresh = request_value(payload);
+    let params = ["query", "path_params"]
+        .iter()
+        .flat_map(|field| {
+            fresh[*field]
+                .as_object()
+                .into_iter()
+                .flat_map(move |params| params.iter().map(move |param| (*field, param)))
+        })
+        .choose(&mut rng);
+    match (params, request.get_mut("body")) {
+        (Some((field, (name, value))), _) if rng.gen_bool(0.5) => {
+            request[field][name] = value.clone();
+        }
+        (_, Some(body)) if !body.is_null() => {
+            let input: String = rng
+                .sample_iter::<char, _>(rand::distributions::Standard)
+                .take(1024)
+                .collect();
+            webhooks::mutate(
+                body,
+                &mut Unstructured::new(input.as_bytes()),
+                payload.strategy,
+            )?;
+        }
+        (Some((field, (name, value))), _) => request[field][name] = value.clone(),
+        _ => {}
+    }
+    if let Value::Object(request) = request {
+        apply_request(payload, &request);
+    }
+    Ok(())
+}
+
+/// Guides fuzzing by the code coverage of the server, which an endpoint of
+/// the instrumented target reports. Requests that increase it are kept in a
+/// corpus and later requests of the operation mutate them.
+#[derive(Debug)]
+pub struct Guide {
+    url: Url,
+    covered: u64,
+    corpus: BTreeMap<OperationKey, Vec<Value>>,
+}
+
+impl Guide {
+    /// Polls the coverage endpoint for the coverage before fuzzing
+    pub fn new(url: Url) -> Result<Guide> {
+        let mut guide = Guide {
+            url,
+            covered: 0,
+            corpus: BTreeMap::new(),
+        };
+        guide.covered = guide.poll()?;
+        Ok(guide)
+    }
+
+    fn poll(&self) -> Result<u64> {
+        let error = || format!("unable to poll coverage from {}", self.url);
+        let body = ureq::request_url("GET", &self.url)
+            .call()
+            .with_context(error)?
+            .into_string()
+            .with_context(error)?;
+        covered(&body).ok_or_else(|| anyhow!("{} does not report the coverage", self.url))
+    }
+
+    /// Replaces most requests of an operation with a corpus by a mutation of
+    /// one of its requests
+    pub fn mutate(&self, key: &OperationKey, payload: &mut Payload) -> Result<()> {
+        match self.corpus.get(key) {
+            Some(corpus) if rand::thread_rng().gen_bool(0.8) => mutate(corpus, payload),
+            _ => Ok(()),
+        }
+    }
+
+    /// Polls the coverage after the request and keeps the request when it
+    /// increased, which is returned
+    pub fn observe(&mut self, key: OperationKey, payload: &Payload) -> Result<bool> {
+        let covered = self.poll()?;
+        if covered <= self.covered {
+            // The target restarted and starts counting anew
+            self.covered = covered;
+            return Ok(false);
+        }
+        self.covered = covered;
+        let corpus = self.corpus.entry(key).or_default();
+        corpus.push(request_value(payload));
+        if corpus.len() > MAX_CORPUS {
+            corpus.remove(0);
+        }
+        Ok(true)
+    }
+
+    /// Coverage reported by the target so far
+    pub fn covered(&self) -> u64 {
+        self.covered
+    }
+
+    pub(crate) fn corpus(&self) -> Vec<(OperationKey, Vec<Value>)> {
+        self.corpus
+            .iter()
+            .map(|(key, requests)| (key.clone(), requests.clone()))
+            .collect()
+    }
+
+    /// Continues with the corpus of an earlier campaign
+    pub(crate) fn restore(&mut self, corpus: Vec<(OperationKey, Vec<Value>)>) {
+        self.corpus.extend(corpus);
+    }
+
+    /// Number of requests that increased the coverage
+    pub fn corpus_size(&self) -> usize {
+        self.corpus.values().map(Vec::len).sum()
+    }
+}
@@ -0,0 +1,372 @@
+use std::io::{Read, Write};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::fuzzer::{Response, MAX_BODY_SIZE};
+
+// Written first by clients that know the server speaks HTTP/2 without asking
+const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+// Frame types
+const DATA: u8 = 0x0;
+const HEADERS: u8 = 0x1;
+const RST_STREAM: u8 = 0x3;
+const SETTINGS: u8 = 0x4;
+const PING: u8 = 0x6;
+const GOAWAY: u8 = 0x7;
+const WINDOW_UPDATE: u8 = 0x8;
+const CONTINUATION: u8 = 0x9;
+
+// Frame flags
+const END_STREAM: u8 = 0x1;
+const ACK: u8 = 0x1;
+const END_HEADERS: u8 = 0x4;
+const PADDED: u8 = 0x8;
+const PRIORITY: u8 = 0x20;
+
+// Settings
+const ENABLE_PUSH: u16 = 0x2;
+const INITIAL_WINDOW_SIZE: u16 = 0x4;
+const MAX_FRAME_SIZE: u16 = 0x5;
+
+// The request is sent on the first stream of the connection
+const STREAM: u32 = 1;
+const DEFAULT_WINDOW_SIZE: i64 = 65_535;
+const DEFAULT_FRAME_SIZE: usize = 16_384;
+// The server may send a response body of any size without waiting for us
+const MAX_WINDOW_SIZE: u32 = 0x7fff_ffff;
+// Frames are never larger than the largest MAX_FRAME_SIZE a server may set
+const MAX_FRAME_LENGTH: usize = 0xff_ffff;
+
+const ERROR_CODES: &[&str] = &[
+    "NO_ERROR",
+    "PROTOCOL_ERROR",
+    "INTERNAL_ERROR",
+    "FLOW_CONTROL_ERROR",
+    "SETTINGS_TIMEOUT",
+    "STREAM_CLOSED",
+    "FRAME_SIZE_ERROR",
+    "REFUSED_STREAM",
+    "CANCEL",
+    "COMPRESSION_ERROR",
+    "CONNECT_ERROR",
+    "ENHANCE_YOUR_CALM",
+    "INADEQUATE_SECURITY",
+    "HTTP_1_1_REQUIRED",
+];
+
+fn error_code(code: u32) -> String {
+    match ERROR_CODES.get(code as usize) {
+        Some(name) => name.to_string(),
+        None => format!("error code {:#x}", code),
+    }
+}
+
+struct Frame {
+    kind: u8,
+    flags: u8,
+    stream: u32,
+    payload: Vec<u8>,
+}
+
+/// Connection speaking HTTP/2 in clear text with prior knowledge, that is
+/// without asking the server to upgrade from HTTP/1.1, which carries a single
+/// request
+struct Connection<S> {
+    stream: S,
+    decoder: hpack::Decoder<'static>,
+    /// What the server lets us send on the connection and on the stream
+    connection_window: i64,
+    stream_window: i64,
+    initial_window: i64,
+    max_frame_size: usize,
+    /// Header block split over HEADERS and CONTINUATION frames
+    header_block: Vec<u8>,
+    /// Whether the stream ends once the header block is complete
+    ends_with_headers: bool,
+    status: Option<u16>,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+    /// Whether the server ended the response
+    done: bool,
+}
+
+impl<S: Read + Write> Connection<S> {
+    fn new(stream: S) -> Connection<S> {
+        Connection {
+            stream,
+            decoder: hpack::Decoder::new(),
+            connection_window: DEFAULT_WINDOW_SIZE,
+            stream_window: DEFAULT_WINDOW_SIZE,
+            initial_window: DEFAULT_WINDOW_SIZE,
+            max_frame_size: DEFAULT_FRAME_SIZE,
+            header_block: Vec::new(),
+            ends_with_headers: false,
+            status: None,
+            headers: Vec::new(),
+            body: Vec::new(),
+            done: false,
+        }
+    }
+
+    fn write_frame(&mut self, kind: u8, flags: u8, stream: u32, payload: &[u8]) -> Result<()> {
+        let mut frame = Vec::with_capacity(9 + payload.len());
+        frame.extend_from_slice(&(payload.len() as u32).to_be_bytes()[1..]);
+        frame.push(kind);
+        frame.push(flags);
+        frame.extend_from_slice(&stream.to_be_bytes());
+        frame.extend_from_slice(payload);
+        self.stream.write_all(&frame)?;
+        Ok(())
+    }
+
+    fn read_frame(&mut self) -> Result<Frame> {
+        let mut header = [0; 9];
+        self.stream
+            .read_exact(&mut header)
+            .context("connection closed before the response ended")?;
+        let length = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+        if length > MAX_FRAME_LENGTH {
+            bail!("frame of {} bytes", length);
+        }
+        let mut payload = vec![0; length];
+        self.stream
+            .read_exact(&mut payload)
+            .context("connection closed before the response ended")?;
+        Ok(Frame {
+            kind: header[3],
+            flags: header[4],
+            stream: u32::from_be_bytes([header[5], header[6], header[7], header[8]]) & 0x7fff_ffff,
+            payload,
+        })
+    }
+
+    /// Writes the preface and our settings: no server push and windows
+    /// large enough for any response
+    fn start(&mut self) -> Result<()> {
+        self.stream.write_all(PREFACE)?;
+        let mut settings = Vec::new();
+        for (id, value) in [(ENABLE_PUSH, 0), (INITIAL_WINDOW_SIZE, MAX_WINDOW_SIZE)] {
+            settings.extend_from_slice(&id.to_be_bytes());
+            settings.extend_from_slice(&value.to_be_bytes());
+        }
+        self.write_frame(SETTINGS, 0, 0, &settings)?;
+        let increment = MAX_WINDOW_SIZE - DEFAULT_WINDOW_SIZE as u32;
+        self.write_frame(WINDOW_UPDATE, 0, 0, &increment.to_be_bytes())
+    }
+
+    fn write_headers(&mut self, block: &[u8], end_stream: bool) -> Result<()> {
+        let mut fragments = block.chunks(self.max_frame_size).peekable();
+        let mut kind = HEADERS;
+        let mut flags = if end_stream { END_STREAM } else { 0 };
+        // An empty block is still sent in a HEADERS frame
+        let first = fragments.next().unwrap_or_default();
+        let mut fragment = first;
+        loop {
+            let last = fragments.peek().is_none();
+            if last {
+                flags |= END_HEADERS;
+            }
+            self.write_frame(kind, flags, STREAM, fragment)?;
+            match fragments.next() {
+                Some(next) => fragment = next,
+                None => return Ok(()),
+            }
+            kind = CONTINUATION;
+            flags = 0;
+        }
+    }
+
+    /// Writes the body in DATA frames as far as the server lets us, it is not
+    /// sent any further once the server answered
+    fn write_body(&mut self, body: &[u8]) -> Result<()> {
+        let mut rest = body;
+        while !rest.is_empty() && !self.done {
+            let window = self.connection_window.min(self.stream_window);
+            if window <= 0 {
+                let frame = self.read_frame()?;
+                self.receive(frame)?;
+                continue;
+            }
+            let size = rest.len().min(self.max_frame_size).min(window as usize);
+            let (data, remaining) = rest.split_at(size);
+            let flags = if remaining.is_empty() { END_STREAM } else { 0 };
+            self.write_frame(DATA, flags, STREAM, data)?;
+            self.connection_window -= size as i64;
+            self.stream_window -= size as i64;
+            rest = remaining;
+        }
+        Ok(())
+    }
+
+    fn receive(&mut self, frame: Frame) -> Result<()> {
+        match frame.kind {
+            DATA if frame.stream == STREAM => {
+                let data = unpadded(&frame)?;
+                let room = (MAX_BODY_SIZE as usize).saturating_sub(self.body.len());
+                self.body.extend_from_slice(&data[..data.len().min(room)]);
+                self.done = frame.flags & END_STREAM != 0;
+            }
+            HEADERS if frame.stream == STREAM => {
+                let mut fragment = unpadded(&frame)?;
+                if frame.flags & PRIORITY != 0 {
+                    fragment = fragment.get(5..).context("truncated HEADERS frame")?;
+                }
+                self.header_block = fragment.to_vec();
+                self.ends_with_headers = frame.flags & END_STREAM != 0;
+                if frame.flags & END_HEADERS != 0 {
+                    self.end_headers()?;
+                }
+            }
+            CONTINUATION if frame.stream == STREAM => {
+                self.header_block.extend_from_slice(&frame.payload);
+                if frame.flags & END_HEADERS != 0 {
+                    self.end_headers()?;
+                }
+            }
+            SETTINGS if frame.flags & ACK == 0 => {
+                for setting in frame.payload.chunks_exact(6) {
+                    let id = u16::from_be_bytes([setting[0], setting[1]]);
+                    let value =
+                        u32::from_be_bytes([setting[2], setting[3], setting[4], setting[5]]);
+                    match id {
+                        INITIAL_WINDOW_SIZE => {
+                            self.stream_window += value as i64 - self.initial_window;
+                            self.initial_window = value as i64;
+                        }
+                        MAX_FRAME_SIZE => self.max_frame_size = value as usize,
+                        _ => {}
+                    }
+                }
+                self.write_frame(SETTINGS, ACK, 0, &[])?;
+            }
+            PING if frame.flags & ACK == 0 => self.write_frame(PING, ACK, 0, &frame.payload)?,
+            WINDOW_UPDATE => {
+                let increment = frame
+                    .payload
+                    .get(..4)
+                    .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                    .context("truncated WINDOW_UPDATE frame")?
+                    & 0x7fff_ffff;
+                match frame.stream {
+                    0 => self.connection_window += increment as i64,
+                    STREAM => self.stream_window += increment as i64,
+                    _ => {}
+                }
+            }
+            RST_STREAM if frame.stream == STREAM => {
+                let code = frame
+                    .payload
+                    .get(..4)
+                    .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                    .unwrap_or_default();
+                bail!("the server reset the stream with {}", error_code(code));
+            }
+            GOAWAY => {
+                let field = |range: std::ops::Range<usize>| {
+                    frame
+                        .payload
+                        .get(range)
+                        .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                        .unwrap_or_default()
+                };
+                let (last_stream, code) = (field(0..4) & 0x7fff_ffff, field(4..8));
+                // Streams up to the last one are still answered
+                if code != 0 || last_stream < STREAM {
+                    bail!("the server closed the connection with {}", error_code(code));
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Takes the status and the headers of the response from the complete
+    /// header block, later blocks are trailers
+    fn end_headers(&mut self) -> Result<()> {
+        let fields = self
+            .decoder
+            .decode(&self.header_block)
+            .map_err(|e| anyhow!("invalid header block: {:?}", e))?;
+        let fields = fields
+            .into_iter()
+            .map(|(name, value)| {
+                (
+                    String::from_utf8_lossy(&name).to_lowercase(),
+                    String::from_utf8_lossy(&value).into_owned(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let status = fields
+            .iter()
+            .find(|(name, _)| name == ":status")
+            .and_then(|(_, status)| status.parse::<u16>().ok());
+        match (self.status, status) {
+            // Interim responses such as 100 Continue are skipped
+            (None, Some(100..=199)) => return Ok(()),
+            (None, Some(status)) => self.status = Some(status),
+            (None, None) => bail!("response without a status"),
+            (Some(_), _) => {}
+        }
+        self.headers.extend(
+            fields
+                .into_iter()
+                .filter(|(name, _)| !name.starts_with(':')),
+        );
+        self.done |= self.ends_with_headers;
+        Ok(())
+    }
+}
+
+/// Payload of a DATA or HEADERS frame without its padding
+fn unpadded(frame: &Frame) -> Result<&[u8]> {
+    if frame.flags & PADDED == 0 {
+        return Ok(&frame.payload);
+    }
+    let padding = *frame.payload.first().context("truncated padded frame")? as usize;
+    frame
+        .payload
+        .get(1..frame.payload.len().saturating_sub(padding))
+        .filter(|_| padding < frame.payload.len())
+        .context("padding longer than the frame")
+}
+
+/// Sends the request over HTTP/2 and reads its response. `headers` have lower
+/// case names and go after the pseudo headers of the request.
+pub(crate) fn send<S: Read + Write>(
+    stream: S,
+    method: &str,
+    authority: &str,
+    target: &str,
+    headers: &[(String, String)],
+    body: &[u8],
+) -> Result<Response> {
+    let mut connection = Connection::new(stream);
+    connection.start()?;
+    let pseudo = [
+        (":method", method),
+        (":scheme", "http"),
+        (":authority", authority),
+        (":path", target),
+    ];
+    let fields = pseudo
+        .iter()
+        .map(|(name, value)| (name.as_bytes(), value.as_bytes()))
+        .chain(
+            headers
+                .iter()
+                .map(|(name, value)| (name.as_bytes(), value.as_bytes())),
+        );
+    let block = hpack::Encoder::new().encode(fields);
+    connection.write_headers(&block, body.is_empty())?;
+    connection.write_body(body)?;
+    while !connection.done {
+        let frame = connection.read_frame()?;
+        connection.receive(frame)?;
+    }
+    Ok(Response {
+        status: connection.status.context("response without a status")?,
+        headers: connection.headers,
+        body: String::from_utf8_lossy(&connection.body).into_owned(),
+    })
+}
@@ -0,0 +1,247 @@
+use std::collections::BTreeMap;
+
+use rand::{seq::SliceRandom, Rng};
+use serde_json::{Map, Value};
+
+// Extension the hints of the request body schemas are moved to
+const BODY_HINTS: &str = "x-fuzz-body";
+// Schemas nested deeper than this are not looked at, references may be cyclic
+const MAX_DEPTH: usize = 16;
+
+/// Hints of the specification's author given by `x-fuzz-*` extensions
+#[derive(Debug, Default, Clone)]
+pub struct Hints {
+    /// `x-fuzz-skip: true` leaves out the operation, parameter or property
+    pub skip: bool,
+    /// `x-fuzz-values` lists values that are sampled by most requests
+    pub values: Vec<Value>,
+    /// `x-fuzz-max-length` caps the length of generated strings
+    pub max_length: Option<usize>,
+}
+
+/// Hints of the request body by the JSON pointer of the properties, array
+/// items are addressed by `*`
+pub type BodyHints = BTreeMap<String, Hints>;
+
+impl Hints {
+    /// Hints of the extensions, looked up by their name
+    pub fn new<'a>(extension: impl Fn(&str) -> Option<&'a Value>) -> Hints {
+        Hints {
+            skip: extension("x-fuzz-skip") == Some(&Value::Bool(true)),
+            values: extension("x-fuzz-values")
+                .and_then(Value::as_array)
+                .cloned()
+                .unwrap_or_default(),
+            max_length: extension("x-fuzz-max-length")
+                .and_then(Value::as_u64)
+                .map(|length| length as usize),
+        }
+    }
+
+    /// Hints of the request body for the operation's extensions
+    pub fn body<'a>(extension: impl Fn(&str) -> Option<&'a Value>) -> BodyHints {
+        let hints = match extension(BODY_HINTS).and_then(Value::as_object) {
+            Some(hints) => hints,
+            None => return BodyHints::new(),
+        };
+        hints
+            .iter()
+            .filter_map(|(pointer, hints)| {
+                let hints = hints.as_object()?;
+                Some((pointer.clone(), Hints::new(|name| hints.get(name))))
+            })
+            .collect()
+    }
+
+    /// One of the values, some requests still generate their own
+    pub fn sample(&self) -> Option<Value> {
+        let mut rng = rand::thread_rng();
+        if rng.gen_bool(0.1) {
+            return None;
+        }
+        self.values.choose(&mut rng).cloned()
+    }
+
+    /// Cuts generated strings to the maximum length
+    pub fn truncate(&self, value: String) -> String {
+        match self.max_length {
+            Some(length) => value.chars().take(length).collect(),
+            None => value,
+        }
+    }
+}
+
+/// Escapes the name for a JSON pointer
+pub fn escape(name: &str) -> String {
+    name.replace('~', "~0").replace('/', "~1")
+}
+
+/// `x-fuzz-*` extensions of the object
+fn extensions(value: &Value) -> Map<String, Value> {
+    value
+        .as_object()
+        .map(|object| {
+            object
+                .iter()
+                .filter(|(name, _)| name.starts_with("x-fuzz-"))
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Follows local references, external ones are resolved before
+fn resolve<'a>(value: &'a Value, document: &'a Value) -> &'a Value {
+    let mut value = value;
+    for _ in 0..MAX_DEPTH {
+        match value
+            .get("$ref")
+            .and_then(Value::as_str)
+            .and_then(|reference| reference.strip_prefix('#'))
+            .and_then(|pointer| document.pointer(pointer))
+        {
+            Some(target) => value = target,
+            None => break,
+        }
+    }
+    value
+}
+
+/// Collects the hints of the schema and the ones nested in it
+fn collect(
+    schema: &Value,
+    pointer: &str,
+    document: &Value,
+    hints: &mut Map<String, Value>,
+    depth: usize,
+) {
+    if depth > MAX_DEPTH {
+        return;
+    }
+    let schema = resolve(schema, document);
+    let found = extensions(schema);
+    if !found.is_empty() {
+        // Properties win over the hints of the schemas they are composed of
+        if let Value::Object(existing) = hints
+            .entry(pointer.to_string())
+            .or_insert_with(|| Value::Object(Map::new()))
+        {
+            for (name, value) in found {
+                existing.entry(name).or_insert(value);
+            }
+        }
+    }
+    if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+        for (name, property) in properties {
+            let pointer = format!("{}/{}", pointer, escape(name));
+            collect(property, &pointer, document, hints, depth + 1);
+        }
+    }
+    if let Some(items) = schema.get("items") {
+        collect(items, &format!("{}/*", pointer), document, hints, depth + 1);
+    }
+    for keyword in &["allOf", "oneOf", "anyOf"] {
+        for schema in schema
+            .get(*keyword)
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            collect(schema, pointer, document, hints, depth + 1);
+        }
+    }
+}
+
+/// Moves the hints of schemas, which openapiv3 does not keep, to the
+/// parameters and operations they belong to. Hints of a parameter's schema
+/// are added to the parameter, the ones of the request body schemas end up
+/// in `x-fuzz-body` of the operation. Returns whether there were any.
+pub fn lift(document: &mut Value) -> bool {
+    const METHODS: &[&str] = &[
+        "get", "put", "post", "delete", "options", "head", "patch", "trace",
+    ];
+    let mut lifted = false;
+
+    // Pointers of the parameter objects, which are changed afterwards
+    let mut parameters = Vec::new();
+    let mut bodies = Vec::new();
+    if let Some(paths) = document.get("paths").and_then(Value::as_object) {
+        for (path, item) in paths {
+            let item_pointer = format!("/paths/{}", escape(path));
+            for (index, _) in item
+                .get("parameters")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .enumerate()
+            {
+                parameters.push(format!("{}/parameters/{}", item_pointer, index));
+            }
+            for method in METHODS {
+                let operation = match item.get(*method) {
+                    Some(operation) => operation,
+                    None => continue,
+                };
+                let pointer = format!("{}/{}", item_pointer, method);
+                for (index, _) in operation
+                    .get("parameters")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                    .enumerate()
+                {
+                    parameters.push(format!("{}/parameters/{}", pointer, index));
+                }
+                let body = operation
+                    .get("requestBody")
+                    .map(|body| resolve(body, document));
+                let mut hints = Map::new();
+                for media in body
+                    .and_then(|body| body.get("content"))
+                    .and_then(Value::as_object)
+                    .into_iter()
+                    .flat_map(|content| content.values())
+                {
+                    if let Some(schema) = media.get("schema") {
+                        collect(schema, "", document, &mut hints, 0);
+                    }
+                }
+                if !hints.is_empty() {
+                    bodies.push((pointer, hints));
+                }
+            }
+        }
+    }
+    if let Some(components) = document
+        .pointer("/components/parameters")
+        .and_then(Value::as_object)
+    {
+        for name in components.keys() {
+            parameters.push(format!("/components/parameters/{}", escape(name)));
+        }
+    }
+
+    for pointer in parameters {
+        let found = match document.pointer(&pointer) {
+            // Referenced parameters are changed where they are declared
+            Some(parameter) if parameter.get("$ref").is_none() => parameter
+                .get("schema")
+                .map(|schema| extensions(resolve(schema, document)))
+                .unwrap_or_default(),
+            _ => continue,
+        };
+        if let Some(Value::Object(parameter)) = document.pointer_mut(&pointer) {
+            for (name, value) in found {
+                lifted = true;
+                parameter.entry(name).or_insert(value);
+            }
+        }
+    }
+    for (pointer, hints) in bodies {
+        if let Some(Value::Object(operation)) = document.pointer_mut(&pointer) {
+            lifted = true;
+            operation.insert(BODY_HINTS.to_string(), Value::Object(hints));
+        }
+    }
+    lifted
+}
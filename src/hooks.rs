@@ -0,0 +1,119 @@
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+};
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Value};
+
+use crate::fuzzer::Response;
+use crate::payload::Payload;
+use crate::script::{apply_request, request_value, response_value};
+
+/// Shell command run around the requests of the fuzzer, which receives the
+/// request or the request and the response as JSON on its stdin. Requests and
+/// responses have the same fields as the ones of the script hooks.
+#[derive(Debug, Clone)]
+pub struct Hook {
+    command: String,
+}
+
+impl Hook {
+    pub fn new(command: impl Into<String>) -> Hook {
+        Hook {
+            command: command.into(),
+        }
+    }
+
+    /// Exit status and stdout of the command
+    fn run(&self, input: &Value) -> Result<(Option<i32>, String)> {
+        self.run_with(input, &[])
+    }
+
+    fn run_with(&self, input: &Value, envs: &[(&str, &Path)]) -> Result<(Option<i32>, String)> {
+        let error = || format!("unable to run hook `{}`", self.command);
+        let (shell, flag) = match cfg!(windows) {
+            true => ("cmd", "/C"),
+            false => ("sh", "-c"),
+        };
+        let mut child = Command::new(shell)
+            .args([flag, &self.command])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .envs(envs.iter().copied())
+            .spawn()
+            .with_context(error)?;
+        // Written from another thread, the command may print before it has
+        // read all of its input
+        let input = serde_json::to_vec(input)?;
+        let mut stdin = child.stdin.take().expect("stdin of the hook is piped");
+        let writer = thread::spawn(move || stdin.write_all(&input));
+        let output = child.wait_with_output().with_context(error)?;
+        // Commands are free to ignore their input
+        let _ = writer.join();
+        Ok((
+            output.status.code(),
+            String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        ))
+    }
+
+    /// Lets the command change the payload by printing the request to send,
+    /// false when it vetoed the request by exiting with a non-zero status
+    pub fn pre_request(&self, payload: &mut Payload) -> Result<bool> {
+        let (status, output) = self.run(&request_value(payload))?;
+        if status != Some(0) {
+            return Ok(false);
+        }
+        if !output.is_empty() {
+            match serde_json::from_str::<Value>(&output) {
+                Ok(Value::Object(request)) => apply_request(payload, &request),
+                _ => {
+                    return Err(anyhow!(
+                        "pre hook `{}` did not print a JSON request",
+                        self.command
+                    ))
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    /// Reason of a finding printed by the command, a non-zero exit status is
+    /// a finding as well
+    pub fn post_response(&self, payload: &Payload, resp: &Response) -> Result<Option<String>> {
+        let input = json!({"request": request_value(payload), "response": response_value(resp)});
+        let (status, output) = self.run(&input)?;
+        Ok(match (status, output.is_empty()) {
+            (_, false) => Some(output),
+            (Some(0), true) => None,
+            (Some(status), true) => Some(format!("post hook exited with status {}", status)),
+            (None, true) => Some("post hook was killed".to_string()),
+        })
+    }
+
+    /// Lets the command collect artifacts of the finding, such as core dumps
+    /// or `kubectl describe` output, which receives the finding on its stdin.
+    /// Returns the files it put into `$FUZZ_ARTIFACTS` and the existing files
+    /// it printed one per line.
+    pub fn collect_artifacts(&self, finding: &Value, dir: &Path) -> Result<Vec<PathBuf>> {
+        fs::create_dir_all(dir).with_context(|| format!("unable to create {}", dir.display()))?;
+        let (_, output) = self.run_with(finding, &[("FUZZ_ARTIFACTS", dir)])?;
+        let mut artifacts = fs::read_dir(dir)?
+            .map(|entry| Ok(entry?.path()))
+            .collect::<Result<Vec<_>>>()?;
+        artifacts.sort();
+        if artifacts.is_empty() {
+            let _ = fs::remove_dir(dir);
+        }
+        artifacts.extend(
+            output
+                .lines()
+                .map(|line| PathBuf::from(line.trim()))
+                .filter(|path| path.exists()),
+        );
+        Ok(artifacts)
+    }
+}
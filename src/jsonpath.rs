@@ -0,0 +1,302 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+
+/// Key of an object or index of an array on the way to a selected value
+#[derive(Debug, Clone, PartialEq)]
+enum Key {
+    Name(String),
+    Index(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Comparison {
+    Equal,
+    NotEqual,
+}
+
+/// Filter selecting the children for which the value at `path` exists or
+/// compares to the given value
+#[derive(Debug, Clone, PartialEq)]
+struct Filter {
+    path: Vec<String>,
+    comparison: Option<(Comparison, Value)>,
+}
+
+impl Filter {
+    fn matches(&self, value: &Value) -> bool {
+        let selected = self
+            .path
+            .iter()
+            .try_fold(value, |value, name| value.get(name));
+        match (&self.comparison, selected) {
+            (None, selected) => selected.is_some(),
+            (Some((Comparison::Equal, expected)), selected) => selected == Some(expected),
+            (Some((Comparison::NotEqual, expected)), selected) => selected != Some(expected),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Selector {
+    Name(String),
+    Index(usize),
+    Wildcard,
+    Filter(Filter),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Segment {
+    /// Whether `..` applies the selector to all descendants
+    descendant: bool,
+    selector: Selector,
+}
+
+/// The subset of JSONPath commonly used to point into API documents: child
+/// names (`.name`, `['name']`), array indices (`[0]`), wildcards (`.*`,
+/// `[*]`), recursive descent (`..name`) and simple filters (`[?(@.name)]`,
+/// `[?(@.name == 'value')]`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPath(Vec<Segment>);
+
+fn parse_literal(literal: &str) -> Result<Value, String> {
+    let literal = literal.trim();
+    if literal.len() >= 2 && literal.starts_with('\'') && literal.ends_with('\'') {
+        return Ok(Value::String(literal[1..literal.len() - 1].to_string()));
+    }
+    serde_json::from_str(literal).map_err(|_| format!("invalid value `{}` in filter", literal))
+}
+
+fn parse_filter(filter: &str) -> Result<Filter, String> {
+    let filter = filter.trim();
+    let filter = match filter.starts_with('(') && filter.ends_with(')') {
+        true => &filter[1..filter.len() - 1],
+        false => filter,
+    };
+    let (operand, comparison) = match (filter.find("=="), filter.find("!=")) {
+        (Some(index), _) => (
+            &filter[..index],
+            Some((Comparison::Equal, parse_literal(&filter[index + 2..])?)),
+        ),
+        (None, Some(index)) => (
+            &filter[..index],
+            Some((Comparison::NotEqual, parse_literal(&filter[index + 2..])?)),
+        ),
+        (None, None) => (filter, None),
+    };
+    let operand = operand.trim();
+    let path = operand
+        .strip_prefix('@')
+        .ok_or_else(|| format!("filter `{}` must start with `@`", filter))?;
+    let path = match path.is_empty() {
+        true => Vec::new(),
+        false => JsonPath::parse_segments(path)?
+            .into_iter()
+            .map(|segment| match segment {
+                Segment {
+                    descendant: false,
+                    selector: Selector::Name(name),
+                } => Ok(name),
+                _ => Err(format!("unsupported filter `{}`", filter)),
+            })
+            .collect::<Result<_, _>>()?,
+    };
+    Ok(Filter { path, comparison })
+}
+
+/// Parses the selector in brackets at the start of `rest`, returning it with
+/// the remaining input
+fn parse_bracket(rest: &str) -> Result<(Selector, &str), String> {
+    let inner = &rest[1..];
+    if let Some(quote) = inner.chars().next().filter(|c| *c == '\'' || *c == '"') {
+        let end = inner[1..]
+            .find(quote)
+            .ok_or_else(|| format!("unterminated name in `{}`", rest))?;
+        let name = inner[1..=end].to_string();
+        let rest = inner[end + 2..]
+            .strip_prefix(']')
+            .ok_or_else(|| format!("expected `]` in `{}`", rest))?;
+        return Ok((Selector::Name(name), rest));
+    }
+
+    // Brackets and quotes may be nested in filters
+    let mut depth = 0;
+    let mut quote = None;
+    let mut end = None;
+    for (index, c) in inner.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'') | (None, '"') => quote = Some(c),
+            (None, '[') | (None, '(') => depth += 1,
+            (None, ')') => depth -= 1,
+            (None, ']') if depth == 0 => {
+                end = Some(index);
+                break;
+            }
+            (None, ']') => depth -= 1,
+            _ => {}
+        }
+    }
+    let end = end.ok_or_else(|| format!("expected `]` in `{}`", rest))?;
+    let content = inner[..end].trim();
+    let selector = match content {
+        "*" => Selector::Wildcard,
+        _ if content.starts_with('?') => Selector::Filter(parse_filter(&content[1..])?),
+        _ => Selector::Index(
+            content
+                .parse()
+                .map_err(|_| format!("invalid index `{}`", content))?,
+        ),
+    };
+    Ok((selector, &inner[end + 1..]))
+}
+
+impl JsonPath {
+    fn parse_segments(mut rest: &str) -> Result<Vec<Segment>, String> {
+        let mut segments = Vec::new();
+        while !rest.is_empty() {
+            let descendant = rest.starts_with("..");
+            if descendant {
+                rest = &rest[2..];
+            } else if rest.starts_with('.') {
+                rest = &rest[1..];
+            } else if !rest.starts_with('[') {
+                return Err(format!("expected `.` or `[` at `{}`", rest));
+            }
+
+            let selector = if rest.starts_with('[') {
+                let (selector, remaining) = parse_bracket(rest)?;
+                rest = remaining;
+                selector
+            } else {
+                let end = rest.find(['.', '[']).unwrap_or(rest.len());
+                let name = &rest[..end];
+                rest = &rest[end..];
+                match name {
+                    "" => return Err("empty name".to_string()),
+                    "*" => Selector::Wildcard,
+                    _ => Selector::Name(name.to_string()),
+                }
+            };
+            segments.push(Segment {
+                descendant,
+                selector,
+            });
+        }
+        Ok(segments)
+    }
+
+    /// Selected values together with the keys leading to them
+    fn select_with_keys<'a>(&self, root: &'a Value) -> Vec<(Vec<Key>, &'a Value)> {
+        let mut current = vec![(Vec::new(), root)];
+        for segment in &self.0 {
+            let mut candidates = Vec::new();
+            for (keys, value) in current {
+                match segment.descendant {
+                    true => descendants(keys, value, &mut candidates),
+                    false => candidates.push((keys, value)),
+                }
+            }
+            current = candidates
+                .into_iter()
+                .flat_map(|(keys, value)| children(&segment.selector, keys, value))
+                .collect();
+        }
+        current
+    }
+
+    /// Values selected by the path in document order
+    pub fn select<'a>(&self, root: &'a Value) -> Vec<&'a Value> {
+        self.select_with_keys(root)
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect()
+    }
+
+    /// JSON pointers to the selected values, usable with `Value::pointer_mut`
+    pub fn pointers(&self, root: &Value) -> Vec<String> {
+        self.select_with_keys(root)
+            .into_iter()
+            .map(|(keys, _)| {
+                keys.iter()
+                    .map(|key| match key {
+                        Key::Name(name) => {
+                            format!("/{}", name.replace('~', "~0").replace('/', "~1"))
+                        }
+                        Key::Index(index) => format!("/{}", index),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+fn descendants<'a>(keys: Vec<Key>, value: &'a Value, found: &mut Vec<(Vec<Key>, &'a Value)>) {
+    found.push((keys.clone(), value));
+    for (key, child) in entries(value) {
+        let mut keys = keys.clone();
+        keys.push(key);
+        descendants(keys, child, found);
+    }
+}
+
+fn entries(value: &Value) -> Vec<(Key, &Value)> {
+    match value {
+        Value::Object(object) => object
+            .iter()
+            .map(|(name, value)| (Key::Name(name.clone()), value))
+            .collect(),
+        Value::Array(array) => array
+            .iter()
+            .enumerate()
+            .map(|(index, value)| (Key::Index(index), value))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn children<'a>(
+    selector: &Selector,
+    keys: Vec<Key>,
+    value: &'a Value,
+) -> Vec<(Vec<Key>, &'a Value)> {
+    entries(value)
+        .into_iter()
+        .filter(|(key, child)| match (selector, key) {
+            (Selector::Name(name), Key::Name(key)) => name == key,
+            (Selector::Index(index), Key::Index(key)) => index == key,
+            (Selector::Wildcard, _) => true,
+            (Selector::Filter(filter), _) => filter.matches(child),
+            _ => false,
+        })
+        .map(|(key, child)| {
+            let mut keys = keys.clone();
+            keys.push(key);
+            (keys, child)
+        })
+        .collect()
+}
+
+impl FromStr for JsonPath {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rest = s
+            .trim()
+            .strip_prefix('$')
+            .ok_or_else(|| format!("JSONPath `{}` must start with `$`", s))?;
+        JsonPath::parse_segments(rest)
+            .map(JsonPath)
+            .map_err(|e| format!("invalid JSONPath `{}`: {}", s, e))
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonPath {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
@@ -0,0 +1,163 @@
+use std::{collections::BTreeMap, sync::OnceLock};
+
+use anyhow::{Context, Result};
+use regex::Regex;
+
+/// Patterns of stack traces, database errors and debug pages of common
+/// languages and frameworks, which reveal internals of the server
+const ERROR_PATTERNS: &[(&str, &str)] = &[
+    ("Java stack trace", r"\n\s*at [\w$.]+\([\w$]+\.java:\d+\)"),
+    (
+        "Java exception",
+        r"\b(?:java|javax|jakarta)\.[\w.]+(?:Exception|Error)\b",
+    ),
+    (
+        "Python stack trace",
+        r"Traceback \(most recent call last\):",
+    ),
+    ("Python stack trace", r#"File "[^"]+\.py", line \d+"#),
+    ("Go stack trace", r"goroutine \d+ \[[a-z ]+\]:"),
+    ("Go panic", r"panic: runtime error"),
+    (
+        ".NET stack trace",
+        r"\n\s*at [\w.`<>]+\(.*\) in .+:line \d+",
+    ),
+    (".NET exception", r"\bSystem\.[\w.]+Exception\b"),
+    (
+        "Node.js stack trace",
+        r"\n\s*at .+ \(/.+\.[cm]?js:\d+:\d+\)",
+    ),
+    ("Ruby stack trace", r"\.rb:\d+:in `"),
+    (
+        "PHP error",
+        r"(?:Fatal error|Parse error|Warning): .+ in .+\.php on line \d+",
+    ),
+    ("PHP stack trace", r"Stack trace:\s*#0 "),
+    ("SQL error", r"(?i)you have an error in your sql syntax"),
+    (
+        "SQL error",
+        r"(?i)unclosed quotation mark after the character string",
+    ),
+    ("SQL error", r"(?i)syntax error at or near"),
+    ("SQL error", r"(?i)unterminated quoted string"),
+    ("SQL error", r"\bORA-\d{5}\b"),
+    ("SQL error", r"SQLSTATE\[\w+\]"),
+    (
+        "SQL error",
+        r"\b(?:PG::\w+Error|psycopg2\.errors|sqlite3\.OperationalError|SQLite3::)",
+    ),
+    ("debug page", r"Whitelabel Error Page"),
+    ("debug page", r"Werkzeug Debugger"),
+    (
+        "debug page",
+        r"You're seeing this error because you have <code>DEBUG = True</code>",
+    ),
+    ("debug page", r"Server Error in '[^']*' Application"),
+    ("debug page", r"Whoops! There was an error"),
+];
+
+fn error_patterns() -> &'static [(&'static str, Regex)] {
+    static PATTERNS: OnceLock<Vec<(&str, Regex)>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        ERROR_PATTERNS
+            .iter()
+            .map(|(name, pattern)| (*name, Regex::new(pattern).expect("valid error pattern")))
+            .collect()
+    })
+}
+
+/// Shortens the matched text for the reason of a finding
+fn excerpt(text: &str) -> String {
+    const MAX_LENGTH: usize = 100;
+    let text = text.trim();
+    match text.char_indices().nth(MAX_LENGTH) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// Looks for a stack trace, database error or debug page in the body
+pub fn find_error_leak(body: &str) -> Option<String> {
+    error_patterns().iter().find_map(|(name, pattern)| {
+        pattern
+            .find(body)
+            .map(|found| format!("{} {:?}", name, excerpt(found.as_str())))
+    })
+}
+
+/// Built-in detectors of data that should not be exposed by an API
+const SENSITIVE_PATTERNS: &[(&str, &str)] = &[
+    ("email", r"\b[\w.+-]+@[\w-]+(?:\.[\w-]+)*\.[A-Za-z]{2,}\b"),
+    ("card-number", r"\b\d(?:[ -]?\d){12,18}\b"),
+    ("private-key", r"-----BEGIN (?:[A-Z]+ )?PRIVATE KEY-----"),
+    (
+        "internal-ip",
+        r"\b(?:10\.\d{1,3}|192\.168|172\.(?:1[6-9]|2\d|3[01]))\.\d{1,3}\.\d{1,3}\b",
+    ),
+    ("aws-access-key", r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b"),
+    ("google-api-key", r"\bAIza[0-9A-Za-z_-]{35}\b"),
+    ("github-token", r"\bgh[pousr]_[0-9A-Za-z]{36}\b"),
+    ("slack-token", r"\bxox[abprs]-[0-9A-Za-z-]{10,}"),
+];
+
+/// Whether the digits pass the Luhn checksum of payment card numbers
+fn luhn(number: &str) -> bool {
+    let digits = number
+        .chars()
+        .filter_map(|c| c.to_digit(10))
+        .collect::<Vec<_>>();
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &digit)| match i % 2 {
+            1 if digit * 2 > 9 => digit * 2 - 9,
+            1 => digit * 2,
+            _ => digit,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+/// Regular expressions looking for sensitive data in response bodies
+#[derive(Debug)]
+pub struct Detectors(Vec<(String, Regex)>);
+
+impl Detectors {
+    /// Built-in detectors together with the custom ones by their name. A
+    /// custom detector replaces the built-in one of the same name and an empty
+    /// pattern disables it.
+    pub fn new(custom: &BTreeMap<String, String>) -> Result<Detectors> {
+        let builtin = SENSITIVE_PATTERNS
+            .iter()
+            .filter(|(name, _)| !custom.contains_key(*name))
+            .map(|(name, pattern)| (name.to_string(), pattern.to_string()));
+        let custom = custom
+            .iter()
+            .filter(|(_, pattern)| !pattern.is_empty())
+            .map(|(name, pattern)| (name.clone(), pattern.clone()));
+        builtin
+            .chain(custom)
+            .map(|(name, pattern)| {
+                let regex = Regex::new(&pattern)
+                    .with_context(|| format!("invalid pattern of sensitive data {}", name))?;
+                Ok((name, regex))
+            })
+            .collect::<Result<_>>()
+            .map(Detectors)
+    }
+
+    /// Looks for sensitive data in the body, values sent in the request are
+    /// not reported when they are echoed back
+    pub fn find<'a>(&self, body: &str, sent: impl IntoIterator<Item = &'a str>) -> Option<String> {
+        let sent = sent.into_iter().collect::<Vec<_>>();
+        self.0.iter().find_map(|(name, regex)| {
+            regex
+                .find_iter(body)
+                .map(|found| found.as_str())
+                .filter(|found| name != "card-number" || luhn(found))
+                .find(|found| !sent.iter().any(|value| value.contains(found)))
+                .map(|found| format!("{} {:?}", name, excerpt(found)))
+        })
+    }
+}
@@ -0,0 +1,60 @@
+//! Black-box fuzzer of APIs described by OpenAPI specifications. The
+//! `openapi-fuzzer` binary is built on top of this library, other programs can
+//! load a specification, build a [`Fuzzer`] for it and [`Fuzzer::run`] it, or
+//! use a [`FuzzerBuilder`] e.g. in integration tests.
+
+pub mod budget;
+mod builder;
+pub mod callbacks;
+pub mod checker;
+pub mod checkpoint;
+mod checks;
+pub mod color;
+pub mod compression;
+pub mod config;
+mod conform;
+pub mod coverage;
+pub mod db;
+pub mod diff;
+pub mod drift;
+pub mod dry_run;
+mod faker;
+pub mod filter;
+pub mod form;
+pub mod framing;
+pub mod fuzzer;
+pub mod guide;
+mod h2;
+mod hints;
+pub mod hooks;
+mod jsonpath;
+pub mod leaks;
+pub mod lint;
+pub mod load;
+pub mod logs;
+pub mod oob;
+mod overlay;
+pub mod payload;
+pub mod plugin;
+pub mod record;
+mod refs;
+mod schedule;
+pub mod script;
+pub mod servers;
+pub mod severity;
+pub mod snapshot;
+pub mod spec;
+mod state;
+pub mod status;
+pub mod strategy;
+mod swagger;
+pub mod target;
+pub mod triage;
+mod tui;
+mod validate;
+pub mod webhooks;
+
+pub use builder::FuzzerBuilder;
+pub use checker::{Checker, Exchange, Issue};
+pub use fuzzer::{Api, Check, Finding, Fuzzer, Response};
+pub use payload::{Payload, PayloadOptions};
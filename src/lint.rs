@@ -0,0 +1,240 @@
+use std::fmt;
+
+use openapi_utils::ReferenceOrExt;
+use openapiv3::{AnySchema, OpenAPI, ReferenceOr, Schema, SchemaKind, Type};
+use serde_json::Value;
+
+use crate::form;
+use crate::payload;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    /// The specification cannot be fuzzed
+    Error,
+    /// Part of the specification is skipped or fuzzed poorly
+    Warning,
+}
+
+#[derive(Debug)]
+pub struct Issue {
+    pub level: Level,
+    pub location: String,
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = match self.level {
+            Level::Error => "error",
+            Level::Warning => "warning",
+        };
+        write!(f, "{}: {}: {}", level, self.location, self.message)
+    }
+}
+
+fn issue(level: Level, location: impl Into<String>, message: impl Into<String>) -> Issue {
+    Issue {
+        level,
+        location: location.into(),
+        message: message.into(),
+    }
+}
+
+/// Follows a reference within the document, the value itself otherwise
+fn resolve<'a>(document: &'a Value, value: &'a Value) -> Option<&'a Value> {
+    match value["$ref"].as_str() {
+        Some(reference) => document.pointer(reference.strip_prefix('#')?),
+        None => Some(value),
+    }
+}
+
+fn find_references(document: &Value, value: &Value, pointer: &str, issues: &mut Vec<Issue>) {
+    match value {
+        Value::Object(object) => {
+            if let Some(Value::String(reference)) = object.get("$ref") {
+                // Only whole components can be dereferenced
+                let target = reference
+                    .strip_prefix("#/components/")
+                    .filter(|path| path.split('/').count() == 2)
+                    .and_then(|_| document.pointer(&reference[1..]));
+                match target {
+                    None => issues.push(issue(
+                        Level::Error,
+                        pointer,
+                        format!("reference {} cannot be resolved", reference),
+                    )),
+                    Some(target) if target.get("$ref").is_some() => issues.push(issue(
+                        Level::Error,
+                        pointer,
+                        format!("reference {} points to another reference", reference),
+                    )),
+                    _ => {}
+                }
+            }
+            for (key, value) in object {
+                let key = key.replace('~', "~0").replace('/', "~1");
+                find_references(document, value, &format!("{}/{}", pointer, key), issues);
+            }
+        }
+        Value::Array(array) => {
+            for (index, value) in array.iter().enumerate() {
+                find_references(document, value, &format!("{}/{}", pointer, index), issues);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn check_media_types(content: Option<&Value>, location: &str, of: &str, issues: &mut Vec<Issue>) {
+    for (media_type, media) in content.and_then(Value::as_object).into_iter().flatten() {
+        if media.get("schema").is_none() {
+            issues.push(issue(
+                Level::Error,
+                location,
+                format!("media type {} of {} has no schema", media_type, of),
+            ));
+        }
+    }
+}
+
+/// Looks for constructs that make dereferencing the specification fail, it
+/// has to be run before `deref_all`
+pub fn check_references(schema: &OpenAPI) -> Vec<Issue> {
+    let document = serde_json::to_value(schema).unwrap_or_default();
+    let mut issues = Vec::new();
+    find_references(&document, &document, "", &mut issues);
+
+    for (path, item) in document["paths"].as_object().into_iter().flatten() {
+        if item.get("$ref").is_some() {
+            issues.push(issue(
+                Level::Error,
+                path,
+                "references to path items are not supported",
+            ));
+            continue;
+        }
+        for (method, operation) in item.as_object().into_iter().flatten() {
+            let location = format!("{} {}", method.to_uppercase(), path);
+            if let Some(body) = operation
+                .get("requestBody")
+                .and_then(|body| resolve(&document, body))
+            {
+                check_media_types(
+                    body.get("content"),
+                    &location,
+                    "the request body",
+                    &mut issues,
+                );
+            }
+            for (status, response) in operation["responses"].as_object().into_iter().flatten() {
+                if let Some(response) = resolve(&document, response) {
+                    check_media_types(
+                        response.get("content"),
+                        &location,
+                        &format!("response {}", status),
+                        &mut issues,
+                    );
+                }
+            }
+        }
+    }
+    issues
+}
+
+fn reference_in_box(schema: &ReferenceOr<Box<Schema>>) -> Option<&str> {
+    match schema {
+        ReferenceOr::Reference { reference } => Some(reference),
+        ReferenceOr::Item(schema) => unresolved_reference(schema),
+    }
+}
+
+fn reference_in(schema: &ReferenceOr<Schema>) -> Option<&str> {
+    match schema {
+        ReferenceOr::Reference { reference } => Some(reference),
+        ReferenceOr::Item(schema) => unresolved_reference(schema),
+    }
+}
+
+/// Reference left in the schema after dereferencing, which happens when the
+/// schema is recursive or nested too deeply
+fn unresolved_reference(schema: &Schema) -> Option<&str> {
+    match &schema.schema_kind {
+        SchemaKind::Type(Type::Object(object)) => {
+            object.properties.values().find_map(reference_in_box)
+        }
+        SchemaKind::Type(Type::Array(array)) => reference_in_box(&array.items),
+        SchemaKind::OneOf { one_of: schemas }
+        | SchemaKind::AnyOf { any_of: schemas }
+        | SchemaKind::AllOf { all_of: schemas } => schemas.iter().find_map(reference_in),
+        _ => None,
+    }
+}
+
+/// Reports the parts of a dereferenced specification that the fuzzer skips or
+/// cannot fuzz well
+pub fn check_operations(schema: &OpenAPI) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    if schema.paths.is_empty() {
+        issues.push(issue(Level::Warning, "paths", "there are no paths to fuzz"));
+    }
+
+    for (path, item) in schema.paths.iter() {
+        let operations = payload::operations(item.to_item_ref());
+        if operations.is_empty() {
+            issues.push(issue(Level::Warning, path, "path has no operations"));
+        }
+
+        for (method, operation) in operations {
+            let location = format!("{} {}", method, path);
+            if let Some(body) = &operation.request_body {
+                let content = &body.to_item_ref().content;
+                let sent = |media: &str| media.contains("json") || form::is_form(media);
+                if !content.is_empty() && !content.keys().any(|media| sent(media)) {
+                    issues.push(issue(
+                        Level::Warning,
+                        &location,
+                        format!(
+                            "request body is not sent, none of its media types ({}) is JSON or a form",
+                            content.keys().cloned().collect::<Vec<_>>().join(", ")
+                        ),
+                    ));
+                }
+                for (media_type, media) in content.iter().filter(|(media, _)| sent(media)) {
+                    let schema = match &media.schema {
+                        Some(ReferenceOr::Item(schema)) => schema,
+                        _ => continue,
+                    };
+                    if let Some(reference) = unresolved_reference(schema) {
+                        issues.push(issue(
+                            Level::Error,
+                            &location,
+                            format!(
+                                "schema of the {} request body is recursive or nested too deeply at {}",
+                                media_type, reference
+                            ),
+                        ));
+                    } else if schema.schema_kind == SchemaKind::Any(AnySchema::default()) {
+                        issues.push(issue(
+                            Level::Warning,
+                            &location,
+                            format!(
+                                "schema of the {} request body is empty, random strings are sent",
+                                media_type
+                            ),
+                        ));
+                    }
+                }
+            }
+
+            let responses = &operation.responses;
+            if responses.responses.is_empty() && responses.default.is_none() {
+                issues.push(issue(
+                    Level::Warning,
+                    &location,
+                    "no responses are documented, every status code is reported",
+                ));
+            }
+        }
+    }
+    issues
+}
@@ -0,0 +1,273 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io,
+    path::Path,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context, Result};
+use openapi_utils::ReferenceOrExt;
+use serde::Serialize;
+use serde_json::Value;
+use ureq::OrAnyStatus;
+use url::Url;
+
+use crate::form::{self, Form};
+use crate::fuzzer::Api;
+use crate::payload::{self, Payload, PayloadOptions, REQUEST_ID_HEADER};
+use crate::state::Store;
+
+/// Settings of a load run
+#[derive(Debug, Clone)]
+pub struct LoadOptions {
+    /// Requests generated for every operation, which are sent in turns
+    pub requests: usize,
+    /// Requests in flight at once
+    pub concurrency: usize,
+    /// Requests per second of all workers together, they are sent as fast as
+    /// possible without it
+    pub rate: Option<f64>,
+    pub duration: Duration,
+}
+
+/// Request generated before the load starts
+#[derive(Debug)]
+struct Prepared {
+    operation: String,
+    method: String,
+    url: Url,
+    headers: Vec<(String, String)>,
+    body: Option<Value>,
+    form: Option<Form>,
+}
+
+/// Response time of a request and its status code, which is missing when the
+/// request failed
+type Sample = (usize, Option<u16>, Duration);
+
+/// Latency percentiles of some of the requests in milliseconds together with
+/// their status codes by class, e.g. `2xx`
+#[derive(Debug, Default, Serialize)]
+pub struct Latencies {
+    pub requests: usize,
+    pub errors: usize,
+    pub statuses: BTreeMap<String, usize>,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl Latencies {
+    fn new<'a>(samples: impl Iterator<Item = &'a Sample>) -> Latencies {
+        let mut latencies = Latencies::default();
+        let mut durations = Vec::new();
+        for (_, status, duration) in samples {
+            latencies.requests += 1;
+            match status {
+                Some(status) => {
+                    *latencies
+                        .statuses
+                        .entry(format!("{}xx", status / 100))
+                        .or_default() += 1
+                }
+                None => latencies.errors += 1,
+            }
+            durations.push(duration.as_secs_f64() * 1000.0);
+        }
+        durations.sort_by(f64::total_cmp);
+        let percentile = |p: f64| match durations.len() {
+            0 => 0.0,
+            n => durations[((n - 1) as f64 * p).round() as usize],
+        };
+        latencies.p50 = percentile(0.5);
+        latencies.p90 = percentile(0.9);
+        latencies.p99 = percentile(0.99);
+        latencies.max = percentile(1.0);
+        latencies
+    }
+
+    fn summary(&self) -> String {
+        let mut line = format!(
+            "{} requests, p50 {:.1}ms, p90 {:.1}ms, p99 {:.1}ms, max {:.1}ms",
+            self.requests, self.p50, self.p90, self.p99, self.max
+        );
+        for (class, count) in &self.statuses {
+            line += &format!(", {} {}", count, class);
+        }
+        if self.errors > 0 {
+            line += &format!(", {} failed", self.errors);
+        }
+        line
+    }
+}
+
+/// Throughput and latencies of a load run, overall and by operation
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub seconds: f64,
+    pub throughput: f64,
+    pub total: Latencies,
+    pub operations: BTreeMap<String, Latencies>,
+}
+
+impl Report {
+    /// Lines printed once the load run is done
+    pub fn summary(&self) -> Vec<String> {
+        let mut lines = vec![format!(
+            "{:.1} requests per second in {:.1}s: {}",
+            self.throughput,
+            self.seconds,
+            self.total.summary()
+        )];
+        lines.extend(
+            self.operations
+                .iter()
+                .map(|(operation, latencies)| format!("  {}: {}", operation, latencies.summary())),
+        );
+        lines
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        serde_json::to_writer_pretty(
+            File::create(path).with_context(|| format!("unable to create {}", path.display()))?,
+            self,
+        )?;
+        Ok(())
+    }
+}
+
+/// Requests of all operations the filter of the options selects, every one of
+/// them gets the same number of requests
+fn prepare(apis: &[Api], options: &PayloadOptions, requests: usize) -> Result<Vec<Prepared>> {
+    let store = Store::default();
+    let mut prepared = Vec::new();
+    for api in apis {
+        for (path, item) in &api.schema.paths {
+            let item = item.to_item_ref();
+            for (method, operation) in payload::operations(item) {
+                for n in 0..requests {
+                    let url = &api.urls[n % api.urls.len()];
+                    let payload = Payload::for_operation(
+                        url, path, item, method, operation, options, &store,
+                    )?;
+                    let payload = match payload {
+                        Some(payload) => payload,
+                        None => break,
+                    };
+                    prepared.push(Prepared {
+                        operation: match apis.len() {
+                            1 => format!("{} {}", method, path),
+                            _ => format!("{}: {} {}", api.name, method, path),
+                        },
+                        method: method.to_string(),
+                        url: payload.full_url()?,
+                        headers: payload.headers.clone(),
+                        body: payload.body.first().cloned(),
+                        form: payload.form.clone(),
+                    });
+                }
+            }
+        }
+    }
+    Ok(prepared)
+}
+
+/// Sends the request and reads its response, returns its status code
+fn send(agent: &ureq::Agent, request: &Prepared) -> Option<u16> {
+    let mut req = agent.request_url(&request.method, &request.url).set(
+        REQUEST_ID_HEADER,
+        &format!("{:032x}", rand::random::<u128>()),
+    );
+    for (name, value) in &request.headers {
+        req = req.set(name, value);
+    }
+    let resp = match &request.body {
+        Some(body) => form::send(req, body, request.form.as_ref()).ok()?,
+        None => req.call().or_any_status().ok()?,
+    };
+    let status = resp.status();
+    // The connection is only reused once the body was read
+    io::copy(&mut resp.into_reader(), &mut io::sink()).ok()?;
+    Some(status)
+}
+
+/// Sends requests that conform to the specifications in turns for the
+/// duration of the load run and measures their response times
+pub fn run(apis: &[Api], options: &PayloadOptions, load: &LoadOptions) -> Result<Report> {
+    if load.concurrency == 0 || load.requests == 0 {
+        bail!("the load needs at least one request and one worker");
+    }
+    if load.rate.is_some_and(|rate| rate <= 0.0) {
+        bail!("the rate has to be positive");
+    }
+    let prepared = prepare(apis, options, load.requests)?;
+    if prepared.is_empty() {
+        bail!("no operations left to send");
+    }
+    let agent = ureq::AgentBuilder::new()
+        .max_idle_connections_per_host(load.concurrency)
+        .build();
+
+    let next = AtomicUsize::new(0);
+    let start = Instant::now();
+    let samples = thread::scope(|scope| {
+        let workers = (0..load.concurrency)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut samples: Vec<Sample> = Vec::new();
+                    loop {
+                        let n = next.fetch_add(1, Ordering::Relaxed);
+                        // Requests are due at a fixed interval, workers that
+                        // are ahead wait for their turn
+                        if let Some(rate) = load.rate {
+                            let due = Duration::from_secs_f64(n as f64 / rate);
+                            if due >= load.duration {
+                                break;
+                            }
+                            if let Some(wait) = due.checked_sub(start.elapsed()) {
+                                thread::sleep(wait);
+                            }
+                        }
+                        if start.elapsed() >= load.duration {
+                            break;
+                        }
+                        let index = n % prepared.len();
+                        let sent = Instant::now();
+                        let status = send(&agent, &prepared[index]);
+                        samples.push((index, status, sent.elapsed()));
+                    }
+                    samples
+                })
+            })
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap_or_default())
+            .collect::<Vec<_>>()
+    });
+    let seconds = start.elapsed().as_secs_f64();
+
+    let mut by_operation: BTreeMap<&str, Vec<Sample>> = BTreeMap::new();
+    for sample in &samples {
+        by_operation
+            .entry(&prepared[sample.0].operation)
+            .or_default()
+            .push(*sample);
+    }
+    Ok(Report {
+        seconds,
+        throughput: samples.len() as f64 / seconds,
+        total: Latencies::new(samples.iter()),
+        operations: by_operation
+            .into_iter()
+            .map(|(operation, samples)| (operation.to_string(), Latencies::new(samples.iter())))
+            .collect(),
+    })
+}
@@ -0,0 +1,143 @@
+use std::{
+    collections::VecDeque,
+    fs::{self, File},
+    io::{BufRead, BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+
+// Lines older than this are dropped, findings only look at the recent ones
+const MAX_AGE: Duration = Duration::from_secs(30);
+// Lines of the target logged before a finding that are stored with it
+const WINDOW: Duration = Duration::from_secs(2);
+// Time the target gets to write the lines of the request that caused a finding
+const DELAY: Duration = Duration::from_millis(200);
+
+type Lines = Arc<Mutex<VecDeque<(Instant, String)>>>;
+
+/// Logs of the target followed in the background, either a file or the output
+/// of a command such as `docker logs -f api`. The lines logged around each
+/// finding are stored with it.
+#[derive(Debug)]
+pub struct LogTail {
+    lines: Lines,
+    child: Option<Child>,
+}
+
+fn push(lines: &Lines, line: String) {
+    let mut lines = lines.lock().expect("lines are not poisoned");
+    let now = Instant::now();
+    while lines
+        .front()
+        .is_some_and(|(logged, _)| now.duration_since(*logged) > MAX_AGE)
+    {
+        lines.pop_front();
+    }
+    lines.push_back((now, line));
+}
+
+fn read_lines(stream: impl Read + Send + 'static, lines: Lines) {
+    thread::spawn(move || {
+        for line in BufReader::new(stream).lines() {
+            match line {
+                Ok(line) => push(&lines, line),
+                Err(_) => break,
+            }
+        }
+    });
+}
+
+/// Reads the lines appended to the file, starting over when it is truncated
+fn follow(path: PathBuf, mut file: File, lines: Lines) {
+    thread::spawn(move || {
+        let mut position = file.seek(SeekFrom::End(0)).unwrap_or(0);
+        let mut reader = BufReader::new(file);
+        let mut line = String::new();
+        loop {
+            match reader.read_line(&mut line) {
+                Ok(0) | Err(_) => {
+                    thread::sleep(Duration::from_millis(100));
+                    let truncated = fs::metadata(&path).is_ok_and(|m| m.len() < position);
+                    if truncated {
+                        if let Ok(file) = File::open(&path) {
+                            reader = BufReader::new(file);
+                            position = 0;
+                        }
+                    }
+                }
+                Ok(read) => {
+                    position += read as u64;
+                    // Lines are only complete once their newline is written
+                    if line.ends_with('\n') {
+                        push(&lines, line.trim_end().to_string());
+                        line.clear();
+                    }
+                }
+            }
+        }
+    });
+}
+
+impl LogTail {
+    /// Follows the lines appended to the file from now on
+    pub fn file(path: &Path) -> Result<LogTail> {
+        let file =
+            File::open(path).with_context(|| format!("unable to open log {}", path.display()))?;
+        let lines = Lines::default();
+        follow(path.to_path_buf(), file, lines.clone());
+        Ok(LogTail { lines, child: None })
+    }
+
+    /// Follows the stdout and stderr of the command
+    pub fn command(command: &str) -> Result<LogTail> {
+        let (shell, flag) = match cfg!(windows) {
+            true => ("cmd", "/C"),
+            false => ("sh", "-c"),
+        };
+        let mut child = Command::new(shell)
+            .args([flag, command])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("unable to run log command `{}`", command))?;
+        let lines = Lines::default();
+        if let Some(stdout) = child.stdout.take() {
+            read_lines(stdout, lines.clone());
+        }
+        if let Some(stderr) = child.stderr.take() {
+            read_lines(stderr, lines.clone());
+        }
+        Ok(LogTail {
+            lines,
+            child: Some(child),
+        })
+    }
+
+    /// Lines logged shortly before and after now
+    pub fn around_now(&self) -> Vec<String> {
+        let from = Instant::now().checked_sub(WINDOW);
+        thread::sleep(DELAY);
+        self.lines
+            .lock()
+            .expect("lines are not poisoned")
+            .iter()
+            .filter(|(logged, _)| from.is_none_or(|from| *logged >= from))
+            .map(|(_, line)| line.clone())
+            .collect()
+    }
+}
+
+impl Drop for LogTail {
+    fn drop(&mut self) {
+        if let Some(child) = &mut self.child {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
@@ -1,36 +1,567 @@
-mod fuzzer;
-mod payload;
-mod tui;
-
-use anyhow::{Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use argh::FromArgs;
-use fuzzer::Fuzzer;
-use openapi_utils::SpecExt;
+use openapi_fuzzer::budget::BodyBudget;
+use openapi_fuzzer::callbacks::Listener;
+use openapi_fuzzer::checkpoint::Checkpoint;
+use openapi_fuzzer::color::{self, Color};
+use openapi_fuzzer::config::{self, Config, Values};
+use openapi_fuzzer::coverage::Goal;
+use openapi_fuzzer::db::{Database, Fingerprint};
+use openapi_fuzzer::drift::Behavior;
+use openapi_fuzzer::filter::{Deprecated, Filter};
+use openapi_fuzzer::framing::HttpVersion;
+use openapi_fuzzer::guide::Guide;
+use openapi_fuzzer::hooks::Hook;
+use openapi_fuzzer::leaks::Detectors;
+use openapi_fuzzer::lint::{self, Level};
+use openapi_fuzzer::load::{self, LoadOptions};
+use openapi_fuzzer::logs::LogTail;
+use openapi_fuzzer::payload::{self, PayloadOptions};
+use openapi_fuzzer::plugin::Plugin;
+use openapi_fuzzer::record::{self, Seeds};
+use openapi_fuzzer::script::Script;
+use openapi_fuzzer::severity::{Severities, Severity};
+use openapi_fuzzer::status::Policies;
+use openapi_fuzzer::strategy::Strategy;
+use openapi_fuzzer::target::Target;
+use openapi_fuzzer::{
+    diff, dry_run, servers, snapshot, spec, triage, webhooks, Api, Check, Finding, Fuzzer, Response,
+};
+use openapi_utils::{ReferenceOrExt, SpecExt};
 use openapiv3::OpenAPI;
-use std::path::PathBuf;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::time::Duration;
 use url::{ParseError, Url};
 
+// Requests generated for every operation by --dry-run
+const DRY_RUN_REQUESTS: usize = 3;
+
 #[derive(FromArgs, Debug)]
 /// OpenAPI fuzzer
 struct Args {
-    /// path to OpenAPI specification file
+    /// path to OpenAPI specification file, `-` reads it from stdin, may be
+    /// repeated to fuzz multiple specifications at once
     #[argh(option, short = 's')]
-    spec: PathBuf,
+    spec: Vec<PathBuf>,
+
+    /// older version of the specification, only the operations that were
+    /// added or changed since it are fuzzed
+    #[argh(option)]
+    spec_old: Option<PathBuf>,
+
+    /// JSON Merge Patch or OpenAPI Overlay file applied to the specification
+    /// before fuzzing, may be repeated
+    #[argh(option)]
+    overlay: Vec<PathBuf>,
+
+    /// only report the parts of the specification that cannot be fuzzed
+    /// and exit
+    #[argh(switch)]
+    lint: bool,
+
+    /// print the requests that would be sent to every operation as curl
+    /// commands and save them to results/dry-run instead of sending them
+    #[argh(switch)]
+    dry_run: bool,
+
+    /// do not fuzz operations marked as deprecated
+    #[argh(switch)]
+    skip_deprecated: bool,
+
+    /// only fuzz operations marked as deprecated
+    #[argh(switch)]
+    only_deprecated: bool,
+
+    /// only fuzz operations with this tag, may be repeated
+    #[argh(option)]
+    tag: Vec<String>,
 
-    /// url of api to fuzz
+    /// do not fuzz operations with this tag, may be repeated
+    #[argh(option)]
+    exclude_tag: Vec<String>,
+
+    /// only fuzz paths matching this glob pattern, e.g. `/pets/**`, may be
+    /// repeated
+    #[argh(option)]
+    include_path: Vec<String>,
+
+    /// do not fuzz paths matching this glob pattern, may be repeated
+    #[argh(option)]
+    exclude_path: Vec<String>,
+
+    /// comma separated methods to fuzz, e.g. `GET,HEAD` for read-only
+    /// fuzzing
+    #[argh(option)]
+    methods: Option<Methods>,
+
+    /// only fuzz the operation with this operationId, globs like `get*` are
+    /// allowed, may be repeated
+    #[argh(option)]
+    operation: Vec<String>,
+
+    /// TOML, YAML or JSON file with settings, flags given on the command
+    /// line take precedence
+    #[argh(option)]
+    config: Option<PathBuf>,
+
+    /// profile of the config file to use
+    #[argh(option)]
+    profile: Option<String>,
+
+    /// url of api to fuzz, may be repeated to spread the requests across
+    /// multiple servers, defaults to a server from the specification
     #[argh(option, short = 'u')]
-    url: UrlWithTrailingSlash,
+    url: Vec<UrlWithTrailingSlash>,
+
+    /// index of the server from the specification to fuzz when url is not
+    /// given, defaults to the first one
+    #[argh(option)]
+    server: Option<usize>,
+
+    /// fuzz all servers from the specification when url is not given
+    #[argh(switch)]
+    all_servers: bool,
+
+    /// value of a server variable in form name=value
+    #[argh(option)]
+    server_var: Vec<Variable>,
+
+    /// reuse the ids of resources created by POST requests in the path
+    /// parameters of later requests
+    #[argh(switch)]
+    stateful: bool,
+
+    /// address to listen on for callbacks of the server, e.g.
+    /// `127.0.0.1:8900`, callbacks are not checked without it
+    #[argh(option)]
+    callback_listen: Option<String>,
+
+    /// url the server sends callbacks to, defaults to the listen address
+    #[argh(option)]
+    callback_url: Option<UrlWithTrailingSlash>,
+
+    /// address of the out-of-band interaction server, e.g. `0.0.0.0:8901`,
+    /// whose urls are put into url parameters and properties and the values
+    /// of the oob strategy, SSRF is not probed without it
+    #[argh(option)]
+    oob_listen: Option<String>,
+
+    /// url the server reaches the interaction server at, e.g. a public
+    /// tunnel to it, defaults to the listen address
+    #[argh(option)]
+    oob_url: Option<UrlWithTrailingSlash>,
+
+    /// url of a webhook consumer to send deliveries of the webhooks of the
+    /// specification to instead of fuzzing the api, `{{webhook}}` in it is
+    /// replaced by the name of the webhook
+    #[argh(option)]
+    webhook_receiver: Option<String>,
+
+    /// number of deliveries sent for each webhook, defaults to 100
+    #[argh(option)]
+    webhook_deliveries: Option<usize>,
 
     /// status codes that will not be considered as finding
     #[argh(option, short = 'i')]
     ignore_status_code: Vec<u16>,
 
-    /// additional header to send
+    /// comma separated status codes that will not be considered as finding,
+    /// e.g. `404,429`
+    #[argh(option)]
+    ignore_status: Option<StatusCodes>,
+
+    /// comma separated policies of status classes, e.g.
+    /// `5xx=undocumented,4xx=ignore`. `undocumented` reports status codes
+    /// that are not documented, `always` every status code, `ignore` none and
+    /// `missing` reports documented status codes that were never received as
+    /// well. Defaults to `always` for 5xx and `undocumented` otherwise
+    #[argh(option)]
+    status_policy: Option<Policies>,
+
+    /// comma separated checks run on the responses, e.g.
+    /// `status,schema,leaks`, all of them by default. Besides the names of the
+    /// checks of the config file the groups schema, leaks, latency,
+    /// consistency and custom are accepted
+    #[argh(option)]
+    checks: Option<Checks>,
+
+    /// comma separated checks or groups of them that are not run, e.g.
+    /// `latency`, may be repeated
+    #[argh(option)]
+    disable_check: Vec<Checks>,
+
+    /// comma separated severities of finding classes or kinds, e.g.
+    /// `undocumented-status=low,error-leak=critical`. Classes are
+    /// server-error, schema-violation, data-leak, auth-bypass, timeout,
+    /// injection, undocumented-status, inconsistency and custom
+    #[argh(option)]
+    severity: Option<Severities>,
+
+    /// exit with an error when there are findings of this severity or a
+    /// higher one: info, low, medium, high or critical
+    #[argh(option)]
+    fail_on: Option<Severity>,
+
+    /// additional header to send, `${{VAR}}` in its value is replaced by the
+    /// environment variable
+    #[argh(option, short = 'H')]
+    header: Vec<Header>,
+
+    /// fixed value of a parameter in all operations in form name=value, may
+    /// be repeated
+    #[argh(option)]
+    param: Vec<Variable>,
+
+    /// end fuzzing once every operation received this many responses, or
+    /// `statuses` once every documented status code was received
+    #[argh(option)]
+    until_coverage: Option<Goal>,
+
+    /// end fuzzing after this many seconds
+    #[argh(option)]
+    timeout: Option<u64>,
+
+    /// version of HTTP the fuzzed requests are sent with, `1.0`, `1.1`
+    /// (default) or `2`. HTTP/1.0 and HTTP/2 are only supported for plain
+    /// http urls
+    #[argh(option)]
+    http_version: Option<HttpVersion>,
+
+    /// requests that conform to the specification sent to every operation
+    /// before fuzzing without checking their responses, e.g. to warm up caches
+    #[argh(option)]
+    warmup: Option<u32>,
+
+    /// write every request and its response in full to
+    /// results/exchanges.log, e.g. to follow it with `tail -f`
+    #[argh(switch, short = 'v')]
+    verbose: bool,
+
+    /// command starting the api under test, which is restarted whenever it
+    /// exits and whose output is attached to the crash findings
+    #[argh(option)]
+    target_cmd: Option<String>,
+
+    /// log file of the target, the lines logged around each finding are
+    /// stored with it
+    #[argh(option)]
+    target_log: Option<PathBuf>,
+
+    /// command printing the logs of the target, e.g. `docker logs -f api`,
+    /// the lines logged around each finding are stored with it
+    #[argh(option)]
+    target_log_cmd: Option<String>,
+
+    /// command collecting artifacts such as core dumps for every 5xx finding
+    /// and crash, it finds the directory to put them into in `$FUZZ_ARTIFACTS`
+    #[argh(option)]
+    artifact_cmd: Option<String>,
+
+    /// results directory of an interrupted campaign to continue from its
+    /// last checkpoint, its findings are kept
+    #[argh(option)]
+    resume: Option<PathBuf>,
+
+    /// sqlite database the runs and their deduplicated findings are stored
+    /// in, created when it does not exist
+    #[argh(option)]
+    db: Option<PathBuf>,
+
+    /// comma separated attributes that tell findings in the database apart:
+    /// `reason`, `status` and `path` or `concrete-path`, or `none`. Defaults
+    /// to `reason,status,path`
+    #[argh(option)]
+    fingerprint: Option<Fingerprint>,
+
+    /// file the summaries of the responses of every operation are kept in
+    /// between runs, changes since the previous run are reported
+    #[argh(option)]
+    drift: Option<PathBuf>,
+
+    /// endpoint of the instrumented server reporting its covered lines, as a
+    /// number, coverage.py JSON or JaCoCo XML, which guides the requests
+    #[argh(option)]
+    coverage_url: Option<Url>,
+
+    /// file with requests recorded by the `record` subcommand, which are
+    /// mutated in half of the requests of their operations
+    #[argh(option)]
+    seeds: Option<PathBuf>,
+
+    /// rhai script with `pre_request` and `post_response` hooks run around
+    /// every request
+    #[argh(option)]
+    script: Option<PathBuf>,
+
+    /// webassembly plugin exporting `generate` or `check` functions, may be
+    /// repeated
+    #[argh(option)]
+    plugin: Vec<PathBuf>,
+
+    /// shell command run before every request, which receives it as JSON on
+    /// stdin, prints the request to send and vetoes it with a non-zero exit
+    /// status
+    #[argh(option)]
+    pre_hook: Option<String>,
+
+    /// shell command run after every response, which receives the request
+    /// and the response as JSON on stdin and prints the reason of a finding
+    #[argh(option)]
+    post_hook: Option<String>,
+
+    /// file mapping parameter names or `operationId.name` to lists of
+    /// known-good values that requests sample from
+    #[argh(option)]
+    values: Option<PathBuf>,
+
+    /// string generation strategy to use (random, unicode, control,
+    /// reflection, realistic, framing, nesting, timing, invalid,
+    /// mass-assignment, traversal, oob, duplicate-keys, gzip), may be
+    /// repeated
+    #[argh(option)]
+    strategy: Vec<Strategy>,
+
+    /// bytes of the strings and property names of a generated body together,
+    /// 1048576 by default
+    #[argh(option)]
+    max_body_bytes: Option<usize>,
+
+    /// properties and array items of a generated body together, 10000 by
+    /// default
+    #[argh(option)]
+    max_body_elements: Option<usize>,
+
+    /// levels of the value the nesting strategy puts into bodies, 1000 by
+    /// default and at most 5000
+    #[argh(option)]
+    nesting_depth: Option<usize>,
+
+    #[argh(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(FromArgs, Debug)]
+#[argh(subcommand)]
+enum Command {
+    Findings(FindingsCommand),
+    Record(RecordCommand),
+    Snapshot(SnapshotCommand),
+    Load(LoadCommand),
+    One(OneCommand),
+}
+
+#[derive(FromArgs, Debug)]
+/// record the requests sent to the api through a reverse proxy in front of
+/// the first server, as seeds for --seeds
+#[argh(subcommand, name = "record")]
+struct RecordCommand {
+    /// address the proxy listens on, e.g. `127.0.0.1:8080`
+    #[argh(option)]
+    listen: String,
+
+    /// file the requests are appended to, `seeds.jsonl` by default
+    #[argh(option, default = "PathBuf::from(\"seeds.jsonl\")")]
+    output: PathBuf,
+}
+
+#[derive(FromArgs, Debug)]
+/// send the requests given with --seeds to the first server and compare the
+/// responses with their snapshots, which the first run takes
+#[argh(subcommand, name = "snapshot")]
+struct SnapshotCommand {
+    /// file the snapshots are kept in, `snapshots.json` by default
+    #[argh(option, default = "PathBuf::from(\"snapshots.json\")")]
+    file: PathBuf,
+
+    /// take the snapshots again instead of comparing the responses with them
+    #[argh(switch)]
+    update: bool,
+}
+
+#[derive(FromArgs, Debug)]
+/// send a fixed set of requests that conform to the specification and report
+/// the throughput and the latency percentiles
+#[argh(subcommand, name = "load")]
+struct LoadCommand {
+    /// requests generated for every operation, which are sent in turns, 10 by
+    /// default
+    #[argh(option, default = "10")]
+    requests: usize,
+
+    /// number of requests in flight at once, 4 by default
+    #[argh(option, default = "4")]
+    concurrency: usize,
+
+    /// requests per second of all of them together, they are sent as fast as
+    /// possible without it
+    #[argh(option)]
+    rate: Option<f64>,
+
+    /// seconds the load lasts, 30 by default
+    #[argh(option, default = "30")]
+    duration: u64,
+}
+
+#[derive(FromArgs, Debug)]
+/// generate, print and send a single request to an operation and show the
+/// full response
+#[argh(subcommand, name = "one")]
+struct OneCommand {
+    /// operationId of the operation, the first one is picked when a glob
+    /// matches several of them
+    #[argh(option)]
+    operation: String,
+}
+
+#[derive(FromArgs, Debug)]
+/// triage the findings stored in the database given with --db
+#[argh(subcommand, name = "findings")]
+struct FindingsCommand {
+    #[argh(subcommand)]
+    action: FindingsAction,
+}
+
+#[derive(FromArgs, Debug)]
+#[argh(subcommand)]
+enum FindingsAction {
+    List(ListFindings),
+    Show(ShowFinding),
+    Rerun(RerunFinding),
+}
+
+#[derive(FromArgs, Debug)]
+/// list the deduplicated findings, the most recent ones first
+#[argh(subcommand, name = "list")]
+struct ListFindings {
+    /// only list findings of the kind, e.g. `500` or `error-leak`
+    #[argh(option)]
+    kind: Option<String>,
+}
+
+#[derive(FromArgs, Debug)]
+/// show the request, the response and the details of a finding
+#[argh(subcommand, name = "show")]
+struct ShowFinding {
+    /// id of the finding
+    #[argh(positional)]
+    id: i64,
+}
+
+#[derive(FromArgs, Debug)]
+/// send the request of a finding again and show the response
+#[argh(subcommand, name = "rerun")]
+struct RerunFinding {
+    /// id of the finding
+    #[argh(positional)]
+    id: i64,
+
+    /// extra header replacing the one of the same name, e.g. a fresh token
     #[argh(option, short = 'H')]
     header: Vec<Header>,
 }
 
+/// Runs the findings subcommand on the database
+fn triage(db: &Path, action: FindingsAction) -> Result<()> {
+    match action {
+        FindingsAction::List(list) => {
+            let findings = triage::list(db, list.kind.as_deref())?;
+            for finding in &findings {
+                println!("{}", finding.summary());
+            }
+            eprintln!("{} findings", findings.len());
+        }
+        FindingsAction::Show(show) => {
+            let finding = triage::get(db, show.id)?;
+            println!("{}", finding.summary());
+            println!("file: {}", finding.file);
+            if let Some(curl) = finding.details["curl"].as_str() {
+                println!("curl: {}", curl);
+            }
+            println!(
+                "request: {}",
+                serde_json::to_string_pretty(&finding.details["payload"])?
+            );
+            match &finding.response {
+                Some(response) => {
+                    println!("response: {}", serde_json::to_string_pretty(response)?)
+                }
+                None => println!("response: none"),
+            }
+            for field in ["output", "logs", "artifacts"] {
+                if let Some(lines) = finding.details[field].as_array() {
+                    println!("{}:", field);
+                    for line in lines {
+                        println!("  {}", line.as_str().unwrap_or_default());
+                    }
+                }
+            }
+        }
+        FindingsAction::Rerun(rerun) => {
+            let finding = triage::get(db, rerun.id)?;
+            println!("{}", finding.summary());
+            let headers = rerun
+                .header
+                .into_iter()
+                .map(|h| h.into())
+                .collect::<Vec<_>>();
+            let resp = triage::rerun(&finding, &headers)?;
+            let before = finding
+                .response
+                .as_ref()
+                .and_then(|response| response["status"].as_u64());
+            match before {
+                Some(before) => println!("status: {} (was {})", resp.status, before),
+                None => println!("status: {}", resp.status),
+            }
+            print_response(&resp)?;
+        }
+    }
+    Ok(())
+}
+
+/// Lines counting the findings of each operation by their kind, the most
+/// serious ones first
+fn finding_summary(findings: &[Finding]) -> Vec<String> {
+    let several_apis = findings
+        .iter()
+        .any(|finding| finding.api != findings[0].api);
+    let mut counts = BTreeMap::new();
+    for finding in findings {
+        let operation = match several_apis {
+            true => format!("{}: {} {}", finding.api, finding.method, finding.path),
+            false => format!("{} {}", finding.method, finding.path),
+        };
+        *counts
+            .entry((Reverse(finding.severity), finding.kind.as_str(), operation))
+            .or_insert(0) += 1;
+    }
+    counts
+        .into_iter()
+        .map(|((Reverse(severity), kind, operation), count)| {
+            color::paint(
+                Color::for_severity(severity),
+                format!("{}: {} {} findings ({})", operation, count, kind, severity),
+            )
+        })
+        .collect()
+}
+
+/// Prints the headers and the body of the response, JSON bodies pretty
+fn print_response(resp: &Response) -> Result<()> {
+    for (name, value) in &resp.headers {
+        println!("{}: {}", name, value);
+    }
+    match serde_json::from_str::<serde_json::Value>(&resp.body) {
+        Ok(body) => println!("\n{}", serde_json::to_string_pretty(&body)?),
+        Err(_) => println!("\n{}", resp.body),
+    }
+    Ok(())
+}
+
 #[derive(Debug)]
 struct Header(String, String);
 
@@ -42,10 +573,10 @@ impl FromStr for Header {
         if parts.len() != 2 {
             return Err("invalid header format".to_string());
         }
-        Ok(Header {
-            0: parts[0].to_string().to_lowercase(),
-            1: parts[1].to_string(),
-        })
+        Ok(Header(
+            parts[0].to_string().to_lowercase(),
+            config::substitute_env(parts[1])?,
+        ))
     }
 }
 
@@ -55,6 +586,82 @@ impl From<Header> for (String, String) {
     }
 }
 
+#[derive(Debug)]
+struct Variable(String, String);
+
+impl FromStr for Variable {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s.splitn(2, '=').collect::<Vec<_>>();
+        if parts.len() != 2 {
+            return Err("invalid variable format".to_string());
+        }
+        Ok(Variable(parts[0].to_string(), parts[1].to_string()))
+    }
+}
+
+impl From<Variable> for (String, String) {
+    fn from(val: Variable) -> Self {
+        (val.0, val.1)
+    }
+}
+
+#[derive(Debug)]
+struct Checks(Vec<Check>);
+
+impl FromStr for Checks {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut checks = Vec::new();
+        for name in s.split(',') {
+            checks.extend(Check::named(name)?);
+        }
+        Ok(Checks(checks))
+    }
+}
+
+#[derive(Debug)]
+struct Methods(Vec<String>);
+
+impl FromStr for Methods {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const METHODS: &[&str] = &[
+            "GET", "PUT", "POST", "DELETE", "OPTIONS", "HEAD", "PATCH", "TRACE",
+        ];
+        s.split(',')
+            .map(|method| {
+                let method = method.trim().to_uppercase();
+                match METHODS.contains(&method.as_str()) {
+                    true => Ok(method),
+                    false => Err(format!("unknown method `{}`", method)),
+                }
+            })
+            .collect::<Result<_, _>>()
+            .map(Methods)
+    }
+}
+
+#[derive(Debug)]
+struct StatusCodes(Vec<u16>);
+
+impl FromStr for StatusCodes {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(|code| match code.trim().parse::<u16>() {
+                Ok(code) if (100..1000).contains(&code) => Ok(code),
+                _ => Err(format!("invalid status code `{}`", code.trim())),
+            })
+            .collect::<Result<_, _>>()
+            .map(StatusCodes)
+    }
+}
+
 #[derive(Debug)]
 struct UrlWithTrailingSlash(Url);
 
@@ -62,10 +669,9 @@ impl FromStr for UrlWithTrailingSlash {
     type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.ends_with('/') {
-            true => Ok(UrlWithTrailingSlash(Url::from_str(s)?)),
-            false => Ok(UrlWithTrailingSlash(Url::from_str(&(s.to_owned() + "/"))?)),
-        }
+        Ok(UrlWithTrailingSlash(servers::with_trailing_slash(
+            Url::from_str(s)?,
+        )))
     }
 }
 
@@ -75,18 +681,751 @@ impl From<UrlWithTrailingSlash> for Url {
     }
 }
 
+/// Name of the specification used for its findings, unique among all of them
+fn spec_name(path: &Path, names: &[String]) -> String {
+    let name = match path.to_str() {
+        Some("-") => "stdin".to_string(),
+        _ => path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "spec".to_string()),
+    };
+    let mut unique = name.clone();
+    let mut suffix = 1;
+    while names.contains(&unique) {
+        unique = format!("{}-{}", name, suffix);
+        suffix += 1;
+    }
+    unique
+}
+
+fn server_urls(
+    args: &Args,
+    schema: &OpenAPI,
+    server_variables: &[(String, String)],
+) -> Result<Vec<Url>> {
+    if !args.url.is_empty() {
+        return Ok(args.url.iter().map(|url| url.0.clone()).collect());
+    }
+    if args.all_servers {
+        if schema.servers.is_empty() {
+            bail!("no url given and the specification has no servers");
+        }
+        return schema
+            .servers
+            .iter()
+            .map(|server| servers::resolve(server, None, server_variables))
+            .collect();
+    }
+    let index = args.server.unwrap_or(0);
+    let server = schema.servers.get(index).ok_or_else(|| {
+        anyhow!(
+            "no url given and the specification has no server with index {}",
+            index
+        )
+    })?;
+    Ok(vec![servers::resolve(server, None, server_variables)?])
+}
+
+/// Fills in the settings of the config file that are not given on the command
+/// line. Headers and server variables are merged by their name.
+fn apply_config(args: &mut Args, config: &Config) -> Result<()> {
+    fn or_config<T: Clone>(flag: &mut Vec<T>, config: &[T]) {
+        if flag.is_empty() {
+            *flag = config.to_vec();
+        }
+    }
+
+    or_config(&mut args.spec, &config.spec);
+    or_config(&mut args.overlay, &config.overlay);
+    if !args.skip_deprecated && !args.only_deprecated {
+        args.skip_deprecated = config.skip_deprecated;
+        args.only_deprecated = config.only_deprecated;
+    }
+    or_config(&mut args.tag, &config.tag);
+    or_config(&mut args.exclude_tag, &config.exclude_tag);
+    or_config(&mut args.include_path, &config.include_path);
+    or_config(&mut args.exclude_path, &config.exclude_path);
+    if args.fingerprint.is_none() && !config.fingerprint.is_empty() {
+        args.fingerprint = Some(
+            config
+                .fingerprint
+                .join(",")
+                .parse()
+                .map_err(|e: String| anyhow!(e))?,
+        );
+    }
+    if args.methods.is_none() && !config.methods.is_empty() {
+        args.methods = Some(
+            config
+                .methods
+                .join(",")
+                .parse()
+                .map_err(|e: String| anyhow!(e))?,
+        );
+    }
+    or_config(&mut args.operation, &config.operation);
+    if args.url.is_empty() {
+        args.url = config
+            .url
+            .iter()
+            .map(|url| UrlWithTrailingSlash(servers::with_trailing_slash(url.clone())))
+            .collect();
+    }
+    args.server = args.server.or(config.server);
+    args.all_servers |= config.all_servers;
+    args.stateful |= config.stateful;
+    if args.callback_listen.is_none() {
+        args.callback_listen = config.callback_listen.clone();
+    }
+    if args.callback_url.is_none() {
+        args.callback_url = config
+            .callback_url
+            .clone()
+            .map(|url| UrlWithTrailingSlash(servers::with_trailing_slash(url)));
+    }
+    if args.oob_listen.is_none() {
+        args.oob_listen = config.oob_listen.clone();
+    }
+    if args.oob_url.is_none() {
+        args.oob_url = config
+            .oob_url
+            .clone()
+            .map(|url| UrlWithTrailingSlash(servers::with_trailing_slash(url)));
+    }
+    for (name, value) in &config.server_var {
+        if !args.server_var.iter().any(|variable| &variable.0 == name) {
+            args.server_var.push(Variable(name.clone(), value.clone()));
+        }
+    }
+    for (name, value) in &config.param {
+        if !args.param.iter().any(|param| &param.0 == name) {
+            args.param.push(Variable(name.clone(), value.clone()));
+        }
+    }
+    or_config(&mut args.ignore_status_code, &config.ignore_status_code);
+    for (name, value) in &config.header {
+        let name = name.to_lowercase();
+        if !args.header.iter().any(|header| header.0 == name) {
+            args.header.push(Header(name, value.clone()));
+        }
+    }
+    if args.status_policy.is_none() && !config.status_policy.is_empty() {
+        let policies = config
+            .status_policy
+            .iter()
+            .map(|(class, policy)| format!("{}={}", class, policy))
+            .collect::<Vec<_>>();
+        args.status_policy = Some(policies.join(",").parse().map_err(|e: String| anyhow!(e))?);
+    }
+    if args.checks.is_none() && !config.checks.is_empty() {
+        args.checks = Some(
+            config
+                .checks
+                .join(",")
+                .parse()
+                .map_err(|e: String| anyhow!(e))?,
+        );
+    }
+    if args.disable_check.is_empty() {
+        args.disable_check = config
+            .disable_check
+            .iter()
+            .map(|checks| checks.parse())
+            .collect::<Result<_, String>>()
+            .map_err(|e| anyhow!(e))?;
+    }
+    if args.severity.is_none() && !config.severity.is_empty() {
+        let severities = config
+            .severity
+            .iter()
+            .map(|(key, severity)| format!("{}={}", key, severity))
+            .collect::<Vec<_>>();
+        args.severity = Some(
+            severities
+                .join(",")
+                .parse()
+                .map_err(|e: String| anyhow!(e))?,
+        );
+    }
+    if args.fail_on.is_none() {
+        args.fail_on = config
+            .fail_on
+            .as_deref()
+            .map(str::parse)
+            .transpose()
+            .map_err(|e: String| anyhow!(e))?;
+    }
+    or_config(&mut args.plugin, &config.plugin);
+    if args.script.is_none() {
+        args.script = config.script.clone();
+    }
+    if args.values.is_none() {
+        args.values = config.values.clone();
+    }
+    if args.pre_hook.is_none() {
+        args.pre_hook = config.pre_hook.clone();
+    }
+    if args.post_hook.is_none() {
+        args.post_hook = config.post_hook.clone();
+    }
+    if args.strategy.is_empty() {
+        args.strategy = config
+            .strategy
+            .iter()
+            .map(|strategy| strategy.parse())
+            .collect::<Result<_, String>>()
+            .map_err(|e| anyhow!(e))?;
+    }
+    if args.until_coverage.is_none() {
+        args.until_coverage = config
+            .until_coverage
+            .as_deref()
+            .map(str::parse)
+            .transpose()
+            .map_err(|e: String| anyhow!(e))?;
+    }
+    args.timeout = args.timeout.or(config.timeout);
+    if args.http_version.is_none() {
+        args.http_version = config
+            .http_version
+            .as_ref()
+            .map(|version| match version {
+                serde_json::Value::String(version) => version.parse(),
+                version => version.to_string().parse(),
+            })
+            .transpose()
+            .map_err(|e: String| anyhow!(e))?;
+    }
+    args.warmup = args.warmup.or(config.warmup);
+    args.max_body_bytes = args.max_body_bytes.or(config.max_body_bytes);
+    args.max_body_elements = args.max_body_elements.or(config.max_body_elements);
+    args.nesting_depth = args.nesting_depth.or(config.nesting_depth);
+    args.verbose |= config.verbose;
+    if args.target_cmd.is_none() {
+        args.target_cmd = config.target_cmd.clone();
+    }
+    if args.target_log.is_none() {
+        args.target_log = config.target_log.clone();
+    }
+    if args.target_log_cmd.is_none() {
+        args.target_log_cmd = config.target_log_cmd.clone();
+    }
+    if args.artifact_cmd.is_none() {
+        args.artifact_cmd = config.artifact_cmd.clone();
+    }
+    if args.db.is_none() {
+        args.db = config.db.clone();
+    }
+    if args.drift.is_none() {
+        args.drift = config.drift.clone();
+    }
+    if args.spec_old.is_none() {
+        args.spec_old = config.spec_old.clone();
+    }
+    if args.coverage_url.is_none() {
+        args.coverage_url = config.coverage_url.clone();
+    }
+    if args.seeds.is_none() {
+        args.seeds = config.seeds.clone();
+    }
+    Ok(())
+}
+
 fn main() -> Result<()> {
-    let args: Args = argh::from_env();
-    let specfile = std::fs::read_to_string(&args.spec)?;
-    let openapi_schema: OpenAPI =
-        serde_yaml::from_str(&specfile).context("Failed to parse schema")?;
-    let openapi_schema = openapi_schema.deref_all();
-
-    Fuzzer::new(
-        openapi_schema,
-        args.url.into(),
-        args.ignore_status_code,
-        args.header.into_iter().map(|h| h.into()).collect(),
-    )
-    .run()
+    let mut args: Args = argh::from_env();
+    let config = match (&args.config, &args.profile) {
+        (Some(path), profile) => Config::load(path, profile.as_deref())?,
+        (None, Some(_)) => bail!("--profile requires a config file given with --config"),
+        (None, None) => Config::default(),
+    };
+    apply_config(&mut args, &config)?;
+    let command = match args.command.take() {
+        Some(Command::Findings(findings)) => {
+            let db = args
+                .db
+                .context("no database given, pass one with --db or in the config file")?;
+            return triage(&db, findings.action);
+        }
+        command => command,
+    };
+    if args.spec.is_empty() {
+        bail!("no specification given, pass one with -s or in the config file");
+    }
+    // Findings of a resumed run are saved to the folder of its checkpoint
+    let results_dir = args
+        .resume
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("results"));
+    let server_variables = args
+        .server_var
+        .iter()
+        .map(|v| (v.0.clone(), v.1.clone()))
+        .collect::<Vec<_>>();
+
+    let mut apis = Vec::new();
+    let mut names = Vec::new();
+    let mut report = Vec::new();
+    for path in &args.spec {
+        let schema = spec::load(path, &args.overlay)
+            .with_context(|| format!("unable to load {}", path.display()))?;
+        let mut issues = lint::check_references(&schema);
+        let schema = match issues.iter().any(|issue| issue.level == Level::Error) {
+            true => None,
+            false => Some(schema.deref_all()),
+        };
+        if let Some(schema) = &schema {
+            issues.extend(lint::check_operations(schema));
+        }
+        for issue in &issues {
+            let color = match issue.level {
+                Level::Error => Color::Red,
+                Level::Warning => Color::Yellow,
+            };
+            let line = color::paint(color, format!("{}: {}", path.display(), issue));
+            eprintln!("{}", line);
+            report.push(line);
+        }
+        let schema = match schema {
+            Some(schema) if !issues.iter().any(|issue| issue.level == Level::Error) => schema,
+            _ => bail!("{} cannot be fuzzed", path.display()),
+        };
+        if args.lint {
+            continue;
+        }
+        let name = spec_name(path, &names);
+        names.push(name.clone());
+        if let Some(receiver) = &args.webhook_receiver {
+            let results_dir = match args.spec.len() {
+                1 => results_dir.join("webhooks"),
+                _ => results_dir.join(&name).join("webhooks"),
+            };
+            webhooks::deliver(
+                &schema,
+                receiver,
+                args.webhook_deliveries.unwrap_or(100),
+                &args.strategy,
+                &results_dir.to_string_lossy(),
+            )?;
+            continue;
+        }
+        let urls = server_urls(&args, &schema, &server_variables)
+            .with_context(|| format!("no server to fuzz for {}", path.display()))?;
+        apis.push(Api { name, schema, urls });
+    }
+    let strategies = match args.strategy.is_empty() {
+        true => vec![Strategy::Random],
+        false => args.strategy,
+    };
+    if strategies.contains(&Strategy::Oob) && args.oob_listen.is_none() {
+        bail!("--strategy oob requires an interaction server given with --oob-listen");
+    }
+    // Requests only a connection of their own can send would go out as
+    // ordinary ones otherwise
+    let non_http = apis
+        .iter()
+        .flat_map(|api| &api.urls)
+        .find(|url| url.scheme() != "http");
+    let raw = strategies.iter().find_map(|strategy| match strategy {
+        Strategy::Framing => Some("framing"),
+        Strategy::Traversal => Some("traversal"),
+        _ => None,
+    });
+    if let (Some(url), Some(strategy)) = (non_http, raw) {
+        bail!(
+            "--strategy {} is only supported for plain http urls, not {}",
+            strategy,
+            url
+        );
+    }
+
+    if args.lint || args.webhook_receiver.is_some() {
+        return Ok(());
+    }
+    let command = match command {
+        Some(Command::Record(command)) => {
+            if apis.len() != 1 {
+                bail!("record proxies a single specification");
+            }
+            return record::record(
+                &apis[0].schema,
+                &command.listen,
+                &apis[0].urls[0],
+                &command.output,
+            );
+        }
+        Some(Command::Snapshot(command)) => {
+            let seeds = args
+                .seeds
+                .context("no seeds given, pass them with --seeds or in the config file")?;
+            let seeds = Seeds::load(&seeds)?;
+            let url = &apis[0].urls[0];
+            let headers = args
+                .header
+                .iter()
+                .map(|header| (header.0.clone(), header.1.clone()))
+                .collect::<Vec<_>>();
+            if command.update || !command.file.exists() {
+                let taken = snapshot::take(&seeds, url, &headers, &command.file)?;
+                eprintln!("{} snapshots saved to {}", taken, command.file.display());
+                return Ok(());
+            }
+            let changed_dir = results_dir.join("snapshot");
+            let changed = snapshot::compare(
+                &seeds,
+                url,
+                &headers,
+                &command.file,
+                &changed_dir.to_string_lossy(),
+            )?;
+            if changed > 0 {
+                bail!(
+                    "{} responses changed since the snapshots, see {}",
+                    changed,
+                    changed_dir.display()
+                );
+            }
+            return Ok(());
+        }
+        command => command,
+    };
+
+    let mut filter = Filter {
+        deprecated: match (args.skip_deprecated, args.only_deprecated) {
+            (true, true) => bail!("--skip-deprecated and --only-deprecated exclude each other"),
+            (true, false) => Deprecated::Skip,
+            (false, true) => Deprecated::Only,
+            (false, false) => Deprecated::Include,
+        },
+        tags: args.tag,
+        excluded_tags: args.exclude_tag,
+        included_paths: args.include_path,
+        excluded_paths: args.exclude_path,
+        methods: args.methods.map(|methods| methods.0).unwrap_or_default(),
+        operations: match &command {
+            Some(Command::One(one)) => vec![one.operation.clone()],
+            _ => args.operation,
+        },
+        changed: None,
+    };
+    if let Some(old) = &args.spec_old {
+        if apis.len() != 1 {
+            bail!("--spec-old compares a single specification");
+        }
+        let schema = spec::load(old, &args.overlay)
+            .with_context(|| format!("unable to load {}", old.display()))?
+            .deref_all();
+        let changed = diff::changed_operations(&schema, &apis[0].schema);
+        if changed.is_empty() {
+            eprintln!("no operation changed since {}", old.display());
+            return Ok(());
+        }
+        eprintln!(
+            "{} operations were added or changed since {}",
+            changed.len(),
+            old.display()
+        );
+        filter.changed = Some(changed);
+    }
+    let selected = apis
+        .iter()
+        .flat_map(|api| api.schema.paths.iter())
+        .flat_map(|(path, item)| {
+            payload::operations(item.to_item_ref())
+                .into_iter()
+                .map(move |(method, operation)| (path, method, operation))
+        })
+        .filter(|(path, method, operation)| filter.matches(path, method, operation))
+        .count();
+    if selected == 0 {
+        bail!("no operations left to fuzz");
+    }
+
+    let values = match &args.values {
+        Some(path) => Values::load(path)?,
+        None => Values::default(),
+    };
+    let mut operations = config.operations;
+    let disabled = args
+        .disable_check
+        .into_iter()
+        .flat_map(|checks| checks.0)
+        .collect::<Vec<_>>();
+    operations.select_checks(args.checks.map(|checks| checks.0), &disabled);
+    let payload_options = PayloadOptions {
+        extra_headers: args.header.into_iter().map(|h| h.into()).collect(),
+        params: args.param.into_iter().map(|p| p.into()).collect(),
+        strategies,
+        server_variables,
+        filter,
+        operations,
+        stateful: args.stateful,
+        values,
+        conforming: false,
+        body_budget: BodyBudget {
+            bytes: args.max_body_bytes.unwrap_or(BodyBudget::default().bytes),
+            elements: args
+                .max_body_elements
+                .unwrap_or(BodyBudget::default().elements),
+        },
+        nesting_depth: args.nesting_depth,
+        http_version: args.http_version.unwrap_or_default(),
+    };
+    if let Some(Command::Load(command)) = &command {
+        let report = load::run(
+            &apis,
+            &PayloadOptions {
+                conforming: true,
+                ..payload_options
+            },
+            &LoadOptions {
+                requests: command.requests,
+                concurrency: command.concurrency,
+                rate: command.rate,
+                duration: Duration::from_secs(command.duration),
+            },
+        )?;
+        for line in report.summary() {
+            println!("{}", line);
+        }
+        return report.save(&results_dir.join("load.json"));
+    }
+
+    let script = args.script.as_deref().map(Script::load).transpose()?;
+    let plugins = args
+        .plugin
+        .iter()
+        .map(|path| Plugin::load(path))
+        .collect::<Result<Vec<_>>>()?;
+    if args.dry_run {
+        let dry_run_dir = results_dir.join("dry-run");
+        let generated = dry_run::generate(
+            &apis,
+            &payload_options,
+            DRY_RUN_REQUESTS,
+            script.as_ref(),
+            &plugins,
+            &dry_run_dir.to_string_lossy(),
+        )?;
+        eprintln!("{} requests saved to {}", generated, dry_run_dir.display());
+        return Ok(());
+    }
+
+    let listener = match &args.callback_listen {
+        Some(address) => Some(
+            Listener::start(address, args.callback_url.map(|url| url.0))
+                .context("unable to listen for callbacks")?,
+        ),
+        None => None,
+    };
+    let canaries = match &args.oob_listen {
+        Some(address) => Some(
+            Listener::start(address, args.oob_url.map(|url| url.0))
+                .context("unable to start the out-of-band interaction server")?,
+        ),
+        None => None,
+    };
+
+    if let Some(version @ (HttpVersion::Http10 | HttpVersion::Http2)) = args.http_version {
+        if let Some(url) = apis
+            .iter()
+            .flat_map(|api| &api.urls)
+            .find(|url| url.scheme() != "http")
+        {
+            bail!(
+                "{} is only supported for plain http urls, not {}",
+                version,
+                url
+            );
+        }
+    }
+
+    // Started before anything else talks to it
+    let target = match args.target_cmd {
+        Some(command) => Some(Target::start(command, &apis[0].urls[0])?),
+        None => None,
+    };
+
+    let logs = match (&args.target_log, &args.target_log_cmd) {
+        (Some(_), Some(_)) => bail!("--target-log and --target-log-cmd exclude each other"),
+        (Some(path), None) => Some(LogTail::file(path)?),
+        (None, Some(command)) => Some(LogTail::command(command)?),
+        (None, None) => None,
+    };
+
+    let database = match &args.db {
+        Some(path) => Some(
+            Database::open(
+                path,
+                &apis.iter().map(|api| api.name.clone()).collect::<Vec<_>>(),
+            )?
+            .fingerprint(args.fingerprint.unwrap_or_default()),
+        ),
+        None => None,
+    };
+
+    let mut ignored_status_codes = args.ignore_status_code;
+    ignored_status_codes.extend(args.ignore_status.into_iter().flat_map(|codes| codes.0));
+
+    let mut fuzzer = Fuzzer::new(
+        apis,
+        ignored_status_codes,
+        args.status_policy.unwrap_or_default(),
+        Detectors::new(&config.sensitive_data)?,
+        listener,
+        payload_options,
+        config.cleanup,
+    );
+    if let Some(script) = script {
+        fuzzer = fuzzer.script(script);
+    }
+    for plugin in plugins {
+        fuzzer = fuzzer.plugin(plugin);
+    }
+    if let Some(listener) = canaries {
+        fuzzer = fuzzer.oob(listener);
+    }
+    if let Some(command) = args.pre_hook {
+        fuzzer = fuzzer.pre_hook(Hook::new(command));
+    }
+    if let Some(command) = args.post_hook {
+        fuzzer = fuzzer.post_hook(Hook::new(command));
+    }
+    if let Some(target) = target {
+        fuzzer = fuzzer.supervise(target);
+    }
+    if let Some(command) = args.artifact_cmd {
+        fuzzer = fuzzer.artifact_hook(Hook::new(command));
+    }
+    if let Some(database) = database {
+        fuzzer = fuzzer.database(database);
+    }
+    if let Some(dir) = &args.resume {
+        fuzzer = fuzzer.resume(Checkpoint::load(dir)?).results_dir(dir);
+    }
+    if let Some(logs) = logs {
+        fuzzer = fuzzer.logs(logs);
+    }
+    if let Some(url) = args.coverage_url {
+        fuzzer = fuzzer.guide(Guide::new(url)?);
+    }
+    if let Some(path) = &args.seeds {
+        fuzzer = fuzzer.seeds(Seeds::load(path)?);
+    }
+    if let Some(goal) = args.until_coverage {
+        fuzzer = fuzzer.until_coverage(goal);
+    }
+    if let Some(timeout) = args.timeout {
+        fuzzer = fuzzer.timeout(Duration::from_secs(timeout));
+    }
+    if let Some(severities) = args.severity {
+        fuzzer = fuzzer.severities(severities);
+    }
+    if let Some(requests) = args.warmup {
+        fuzzer = fuzzer.warmup(requests);
+    }
+    if args.verbose {
+        fs::create_dir_all(&results_dir)?;
+        let path = results_dir.join("exchanges.log");
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("unable to open {}", path.display()))?;
+        fuzzer = fuzzer.log_exchanges(file);
+    }
+    if let Some(Command::One(_)) = command {
+        let (payload, resp) = fuzzer.send_one()?.context("no operations left to send")?;
+        println!("# {} {}", payload.method, payload.path);
+        println!("{}", payload.to_curl()?);
+        println!("{}", serde_json::to_string_pretty(&payload)?);
+        println!("\nstatus: {}", resp.status);
+        return print_response(&resp);
+    }
+    let mut fuzzer = fuzzer.with_tui()?;
+    fuzzer.run()?;
+    let reached = fuzzer.coverage_reached();
+    let run = fuzzer.finish_run()?;
+    let server_coverage = fuzzer.server_coverage();
+    let missing = fuzzer.missing_statuses();
+    let coverage = fuzzer.coverage();
+    let cleanup = fuzzer.cleanup();
+    let findings = finding_summary(fuzzer.findings());
+    let failing = args.fail_on.map(|level| {
+        fuzzer
+            .findings()
+            .iter()
+            .filter(|finding| finding.severity >= level)
+            .count()
+    });
+    let behavior = fuzzer.behavior().clone();
+    // Restores the terminal from the tui
+    drop(fuzzer);
+
+    // The tui has replaced the report printed before fuzzing
+    for issue in report {
+        eprintln!("{}", issue);
+    }
+    for status in missing {
+        eprintln!("{}", status);
+    }
+    for line in findings {
+        eprintln!("{}", line);
+    }
+    for line in cleanup {
+        eprintln!("{}", line);
+    }
+    coverage.save(&results_dir.join("coverage.json"))?;
+    if let Some(path) = &args.drift {
+        if let Some(previous) = Behavior::load(path)? {
+            let changes = behavior.changes(&previous);
+            for change in &changes {
+                eprintln!("{}", color::paint(Color::Yellow, change));
+            }
+            eprintln!(
+                "{}",
+                color::paint(
+                    Color::Cyan,
+                    format!("drift: {} changes since the previous run", changes.len())
+                )
+            );
+            serde_json::to_writer_pretty(File::create(results_dir.join("drift.json"))?, &changes)?;
+        }
+        behavior.save(path)?;
+    }
+    if let Some((findings, new)) = run {
+        eprintln!(
+            "{}",
+            color::paint(
+                Color::Cyan,
+                format!(
+                    "database: {} distinct findings in this run, {} of them new",
+                    findings, new
+                )
+            )
+        );
+    }
+    if let Some((covered, corpus)) = server_coverage {
+        eprintln!(
+            "{}",
+            color::paint(
+                Color::Cyan,
+                format!(
+                    "server coverage: {} lines covered, {} requests increased it",
+                    covered, corpus
+                )
+            )
+        );
+    }
+    eprintln!("{}", color::paint(Color::Cyan, coverage.summary()));
+    for gap in coverage.gaps() {
+        eprintln!("{}", gap);
+    }
+    if !reached {
+        bail!("the coverage goal was not reached");
+    }
+    if let (Some(level), Some(failing @ 1..)) = (args.fail_on, failing) {
+        bail!("{} findings of severity {} or higher", failing, level);
+    }
+    Ok(())
 }
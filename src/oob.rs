@@ -0,0 +1,247 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use openapi_utils::{ParameterExt, ReferenceOrExt};
+use openapiv3::{Operation, Parameter, PathItem, Schema, SchemaKind, Type};
+use serde_json::{json, Value};
+use url::Url;
+
+use crate::callbacks::{Listener, Received};
+use crate::fuzzer::Check;
+use crate::payload::{self, Payload};
+use crate::strategy::{Strategy, OOB_PLACEHOLDER};
+
+/// Whether a string of the name and format probably holds a url that the
+/// server fetches, e.g. a `format: uri` property or a `callbackUrl` parameter
+fn holds_url(name: &str, format: &str) -> bool {
+    let name = name.to_lowercase();
+    matches!(
+        format,
+        "uri" | "url" | "iri" | "uri-reference" | "iri-reference"
+    ) || name.contains("url")
+        || name.contains("callback")
+        || name.contains("webhook")
+}
+
+/// Replaces the url strings of the body by canary urls, `location` is where
+/// the value is in the body, e.g. `body#/webhook/url`
+fn inject_body(
+    schema: &Schema,
+    value: &mut Value,
+    name: &str,
+    location: &str,
+    canary: &mut dyn FnMut(&str) -> String,
+) {
+    match (&schema.schema_kind, value) {
+        (SchemaKind::Type(Type::String(string)), Value::String(text))
+            if holds_url(name, payload::format_name(&string.format)) =>
+        {
+            *text = canary(location)
+        }
+        (SchemaKind::Type(Type::Object(object)), Value::Object(properties)) => {
+            for (property, schema) in &object.properties {
+                if let Some(value) = properties.get_mut(property) {
+                    let location = format!("{}/{}", location, property);
+                    inject_body(schema.to_item_ref(), value, property, &location, canary);
+                }
+            }
+        }
+        (SchemaKind::Type(Type::Array(array)), Value::Array(items)) => {
+            for (index, item) in items.iter_mut().enumerate() {
+                let location = format!("{}/{}", location, index);
+                inject_body(array.items.to_item_ref(), item, name, &location, canary);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Replaces the placeholders of the oob strategy in the strings of the body
+/// by canary urls, `location` is where the value is in the body
+fn inject_placeholders(
+    value: &mut Value,
+    location: &str,
+    canary: &mut dyn FnMut(&str, &str) -> String,
+) {
+    match value {
+        Value::String(text) if text.contains(OOB_PLACEHOLDER) => *text = canary(location, text),
+        Value::Object(properties) => {
+            for (property, value) in properties.iter_mut() {
+                let location = format!("{}/{}", location, property);
+                inject_placeholders(value, &location, canary);
+            }
+        }
+        Value::Array(items) => {
+            for (index, item) in items.iter_mut().enumerate() {
+                let location = format!("{}/{}", location, index);
+                inject_placeholders(item, &location, canary);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Canary url put into a request, which the server should never fetch
+#[derive(Debug)]
+pub struct Pending {
+    /// Check that reports the request, `ssrf` for url parameters and
+    /// properties and `oob` for values of the oob strategy
+    pub check: &'static str,
+    pub api: usize,
+    pub url: Url,
+    pub path: String,
+    pub method: String,
+    /// Where the canary url was put in the request, e.g. `query parameter
+    /// target` or `body#/webhook/url`, followed by the value with the
+    /// placeholder for the oob strategy
+    pub location: String,
+    /// Payload and curl command of the request
+    pub finding: Value,
+}
+
+/// Canary urls put into the url parameters and properties of the requests
+/// and into the values of the oob strategy, which are matched to the requests
+/// of the out-of-band interaction server by the token in them
+#[derive(Debug)]
+pub struct Canaries {
+    listener: Listener,
+    pending: BTreeMap<String, Pending>,
+    /// Number of canary urls that were put into requests so far
+    count: u64,
+}
+
+impl Canaries {
+    pub fn new(listener: Listener) -> Canaries {
+        Canaries {
+            listener,
+            pending: BTreeMap::new(),
+            count: 0,
+        }
+    }
+
+    /// Points the query and header parameters and the body properties of the
+    /// request that hold urls to the listener, and fills in the urls of the
+    /// values of the oob strategy
+    pub fn inject(
+        &mut self,
+        api: usize,
+        item: &PathItem,
+        operation: &Operation,
+        payload: &mut Payload,
+    ) -> Result<()> {
+        // Only the most recent canaries are kept
+        const MAX_PENDING: usize = 10_000;
+        let mut tokens = Vec::new();
+        let listener = &self.listener;
+        let count = &mut self.count;
+        let mut canary = |check: &'static str, location: String| {
+            *count += 1;
+            let token = format!("{:016x}", count);
+            let url = listener.canary_url(&token);
+            tokens.push((token, check, location));
+            url
+        };
+        let ssrf = payload.config.check_enabled(Check::Ssrf);
+        let parameters = match ssrf {
+            true => payload::merge_parameters(item, operation),
+            false => Vec::new(),
+        };
+        for parameter in parameters {
+            let name = parameter.name();
+            if !holds_url(name, payload::parameter_format(parameter)) {
+                continue;
+            }
+            match parameter {
+                Parameter::Query { .. } => {
+                    for (_, value) in payload
+                        .query_params
+                        .iter_mut()
+                        .filter(|(param, _)| *param == name)
+                    {
+                        *value = canary("ssrf", format!("query parameter {}", name));
+                    }
+                }
+                Parameter::Header { .. } => {
+                    for (_, value) in payload
+                        .headers
+                        .iter_mut()
+                        .filter(|(header, _)| header.eq_ignore_ascii_case(name))
+                    {
+                        *value = canary("ssrf", format!("header {}", name));
+                    }
+                }
+                // Urls do not fit into a path segment or a cookie
+                Parameter::Path { .. } | Parameter::Cookie { .. } => (),
+            }
+        }
+        if let (true, Some(schema), Some(body)) = (
+            ssrf,
+            payload::body_schema(operation, payload.form.as_ref()),
+            payload.body.first_mut(),
+        ) {
+            inject_body(schema, body, "", "body#", &mut |location| {
+                canary("ssrf", location.to_string())
+            });
+        }
+        if payload.strategy == Strategy::Oob && payload.config.check_enabled(Check::Oob) {
+            let mut interaction = |location: String, value: &str| {
+                let url = canary("oob", format!("{} {:?}", location, value));
+                value.replace(OOB_PLACEHOLDER, &url)
+            };
+            for (name, value) in payload.query_params.iter_mut() {
+                if value.contains(OOB_PLACEHOLDER) {
+                    *value = interaction(format!("query parameter {}", name), value);
+                }
+            }
+            for (name, value) in payload.headers.iter_mut() {
+                if value.contains(OOB_PLACEHOLDER) {
+                    *value = interaction(format!("header {}", name), value);
+                }
+            }
+            for body in payload.body.iter_mut() {
+                inject_placeholders(body, "body#", &mut |location, value| {
+                    interaction(location.to_string(), value)
+                });
+            }
+        }
+        if tokens.is_empty() {
+            return Ok(());
+        }
+        let finding = json!({
+            "request_id": payload.request_id,
+            "payload": payload,
+            "curl": payload.to_curl()?,
+        });
+        for (token, check, location) in tokens {
+            self.pending.insert(
+                token,
+                Pending {
+                    check,
+                    api,
+                    url: payload.url.clone(),
+                    path: payload.path.to_string(),
+                    method: payload.method.to_string(),
+                    location,
+                    finding: finding.clone(),
+                },
+            );
+        }
+        while self.pending.len() > MAX_PENDING {
+            self.pending.pop_first();
+        }
+        Ok(())
+    }
+
+    /// Canary urls fetched since the last time together with the requests
+    /// they were put into
+    pub fn received(&self) -> Vec<(Received, &Pending)> {
+        self.listener
+            .received()
+            .into_iter()
+            .filter_map(|received| {
+                let pending = self.pending.get(&received.token)?;
+                Some((received, pending))
+            })
+            .collect()
+    }
+}
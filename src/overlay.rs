@@ -0,0 +1,112 @@
+use std::{fs, path::Path};
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::Value;
+
+use crate::jsonpath::JsonPath;
+use crate::spec::{self, Format};
+
+/// Applies a JSON Merge Patch (RFC 7396), `null` removes the member
+pub fn merge_patch(target: &mut Value, patch: &Value) {
+    match (target, patch) {
+        (Value::Object(target), Value::Object(patch)) => {
+            for (key, value) in patch {
+                match value {
+                    Value::Null => {
+                        target.remove(key);
+                    }
+                    _ => merge_patch(target.entry(key.clone()).or_insert(Value::Null), value),
+                }
+            }
+        }
+        (target, Value::Object(patch)) => {
+            *target = Value::Object(Default::default());
+            merge_patch(target, &Value::Object(patch.clone()));
+        }
+        (target, patch) => *target = patch.clone(),
+    }
+}
+
+/// Merges the update of an overlay action into its target: objects are merged
+/// recursively, arrays get the update appended and other values are replaced
+fn merge_update(target: &mut Value, update: &Value) {
+    match (target, update) {
+        (Value::Object(target), Value::Object(update)) => {
+            for (key, value) in update {
+                match target.get_mut(key) {
+                    Some(existing) => merge_update(existing, value),
+                    None => {
+                        target.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (Value::Array(target), Value::Array(update)) => target.extend(update.iter().cloned()),
+        (Value::Array(target), update) => target.push(update.clone()),
+        (target, update) => *target = update.clone(),
+    }
+}
+
+fn remove(document: &mut Value, pointer: &str) {
+    let (parent, key) = match pointer.rfind('/') {
+        Some(index) => (&pointer[..index], &pointer[index + 1..]),
+        None => return,
+    };
+    let key = key.replace("~1", "/").replace("~0", "~");
+    match document.pointer_mut(parent) {
+        Some(Value::Object(object)) => {
+            object.remove(&key);
+        }
+        Some(Value::Array(array)) => {
+            if let Ok(index) = key.parse::<usize>() {
+                if index < array.len() {
+                    array.remove(index);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Applies the actions of an OpenAPI Overlay in order
+fn apply_actions(document: &mut Value, actions: &[Value]) -> Result<()> {
+    for (index, action) in actions.iter().enumerate() {
+        let target = action["target"]
+            .as_str()
+            .ok_or_else(|| anyhow!("action {} has no target", index))?;
+        let path = target.parse::<JsonPath>().map_err(|e| anyhow!(e))?;
+        let pointers = path.pointers(document);
+        if action["remove"] == Value::Bool(true) {
+            // Later array elements go first so the earlier indices stay valid
+            for pointer in pointers.iter().rev() {
+                remove(document, pointer);
+            }
+        } else if let Some(update) = action.get("update") {
+            for pointer in &pointers {
+                if let Some(target) = document.pointer_mut(pointer) {
+                    merge_update(target, update);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Applies the overlay at `path` to the specification document. The file is
+/// either an OpenAPI Overlay document with `actions` or a JSON Merge Patch.
+pub fn apply(document: &mut Value, path: &Path) -> Result<()> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("unable to read overlay {}", path.display()))?;
+    let overlay = spec::parse_document(&content, Format::detect(&path.to_string_lossy(), &content))
+        .with_context(|| format!("unable to parse overlay {}", path.display()))?;
+
+    match (overlay.get("overlay"), overlay.get("actions")) {
+        (Some(_), Some(Value::Array(actions))) => apply_actions(document, actions)
+            .with_context(|| format!("unable to apply overlay {}", path.display())),
+        (Some(_), _) => bail!("overlay {} has no actions", path.display()),
+        _ => {
+            merge_patch(document, &overlay);
+            Ok(())
+        }
+    }
+}
@@ -1,72 +1,272 @@
-use anyhow::{Error, Result};
+use anyhow::{bail, Error, Result};
 use arbitrary::{Arbitrary, Unstructured};
-use openapi_utils::ReferenceOrExt;
+use openapi_utils::{ParameterExt, ReferenceOrExt};
 use openapiv3::{
-    ArrayType, ObjectType, Operation, Parameter, PathItem, ReferenceOr, Responses, Schema,
-    SchemaKind, Type,
+    ArrayType, ObjectType, Operation, Parameter, ParameterSchemaOrContent, PathItem, ReferenceOr,
+    Responses, Schema, SchemaKind, StringFormat, Type, VariantOrUnknownOrEmpty,
 };
 use rand::{prelude::IteratorRandom, Rng};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
-use url::Url;
+use ureq::OrAnyStatus;
+use url::{Position, Url};
 
-#[derive(Debug, Serialize)]
+use crate::budget::{BodyBudget, Remaining};
+use crate::checks;
+use crate::compression::Compression;
+use crate::config::{OperationConfig, Operations, Values};
+use crate::conform;
+use crate::filter::Filter;
+use crate::form::{self, Form};
+use crate::framing::{self, Framing, HttpVersion};
+use crate::fuzzer::Response;
+use crate::hints::{self, BodyHints, Hints};
+use crate::servers;
+use crate::state::Store;
+use crate::strategy::{self, Strategy};
+
+/// Header identifying each request in the logs and traces of the server
+pub const REQUEST_ID_HEADER: &str = "X-Fuzz-Request-Id";
+
+// Arrays are not generated longer than this, whatever their maxItems is
+pub(crate) const MAX_ARRAY_ITEMS: usize = 64;
+
+// Values of schemas nested deeper than this are generated as null
+const MAX_DEPTH: usize = 32;
+
+/// Levels of the value the nesting strategy puts into the body
+pub const DEFAULT_NESTING_DEPTH: usize = 1000;
+
+// Deeper values would overflow the stack of the fuzzer itself, as dropping
+// and serializing them recurses into every level
+const MAX_NESTING_DEPTH: usize = 5000;
+
+#[derive(Debug, Clone, Serialize)]
 pub struct Payload<'a> {
-    pub url: &'a Url,
+    /// Unique id sent in the `X-Fuzz-Request-Id` header
+    pub request_id: String,
+    pub url: Url,
     pub method: &'a str,
     pub path: &'a str,
     pub query_params: Vec<(&'a str, String)>,
     pub path_params: Vec<(&'a str, String)>,
-    pub headers: Vec<(&'a str, String)>,
+    pub headers: Vec<(String, String)>,
     pub body: Vec<serde_json::Value>,
     #[serde(skip)]
     pub responses: &'a Responses,
+    /// Framing of the body of the framing strategy
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub framing: Option<Framing>,
+    /// Form the body is serialized as, it is sent as JSON without one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub form: Option<Form>,
+    /// Key of the body the duplicate-keys strategy sends twice
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duplicate_key: Option<DuplicateKey>,
+    /// Compression of the body of the gzip strategy
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compression: Option<Compression>,
+    /// Version of HTTP the request is sent with
+    #[serde(skip_serializing_if = "HttpVersion::is_default")]
+    pub http_version: HttpVersion,
+    /// Whether path parameters are put into the path as they are, without
+    /// encoding them or resolving their dot segments
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub raw_path: bool,
+    #[serde(skip)]
+    pub strategy: Strategy,
+    #[serde(skip)]
+    pub config: OperationConfig,
+}
+
+/// Options shared by all generated payloads
+#[derive(Debug, Default)]
+pub struct PayloadOptions {
+    pub extra_headers: Vec<(String, String)>,
+    /// Fixed values of parameters by their name in all operations, which win
+    /// over the ones of the operation's config
+    pub params: Vec<(String, String)>,
+    pub strategies: Vec<Strategy>,
+    pub server_variables: Vec<(String, String)>,
+    pub filter: Filter,
+    pub operations: Operations,
+    /// Whether ids of created resources are reused
+    pub stateful: bool,
+    /// Known-good values sampled for the parameters
+    pub values: Values,
+    /// Whether values that are not pinned conform to their schema instead of
+    /// being fuzzed
+    pub conforming: bool,
+    /// Size limits of the generated bodies
+    pub body_budget: BodyBudget,
+    /// Levels of the value nested by the nesting strategy,
+    /// `DEFAULT_NESTING_DEPTH` without it
+    pub nesting_depth: Option<usize>,
+    /// Version of HTTP the requests are sent with
+    pub http_version: HttpVersion,
+}
+
+/// Where a value of the request body is generated
+#[derive(Debug, Clone, Copy)]
+struct Location<'a> {
+    /// Name of the property the value belongs to
+    name: &'a str,
+    /// JSON pointer of the value, array items are addressed by `*`
+    pointer: &'a str,
+    hints: &'a BodyHints,
+    budget: &'a Remaining,
+    /// Objects and arrays the value is nested in
+    depth: usize,
 }
 
-fn generate_json_object(object: &ObjectType, gen: &mut Unstructured) -> Result<serde_json::Value> {
+fn generate_json_object(
+    object: &ObjectType,
+    location: Location,
+    gen: &mut Unstructured,
+    strategy: Strategy,
+) -> Result<serde_json::Value> {
     let mut json_object = serde_json::Map::with_capacity(object.properties.len());
     for (name, schema) in &object.properties {
+        let pointer = format!("{}/{}", location.pointer, hints::escape(name));
+        if location.hints.get(&pointer).is_some_and(|hints| hints.skip) {
+            continue;
+        }
+        if !location.budget.element(name) {
+            break;
+        }
         let schema_kind = &schema.to_item_ref().schema_kind;
-        json_object.insert(name.clone(), schema_kind_to_json(schema_kind, gen)?);
+        let location = Location {
+            name,
+            pointer: &pointer,
+            depth: location.depth + 1,
+            ..location
+        };
+        json_object.insert(
+            name.clone(),
+            schema_kind_to_json(schema_kind, location, gen, strategy)?,
+        );
     }
     Ok(serde_json::Value::Object(json_object))
 }
 
-fn generate_json_array(array: &ArrayType, gen: &mut Unstructured) -> Result<serde_json::Value> {
+fn generate_json_array(
+    array: &ArrayType,
+    location: Location,
+    gen: &mut Unstructured,
+    strategy: Strategy,
+) -> Result<serde_json::Value> {
     let items = array.items.to_item_ref();
-    let (min, max) = (array.min_items.unwrap_or(1), array.max_items.unwrap_or(10));
-    let json_array = (min..=max)
-        .map(|_| schema_kind_to_json(&items.schema_kind, gen))
+    let pointer = format!("{}/*", location.pointer);
+    let location = Location {
+        pointer: &pointer,
+        depth: location.depth + 1,
+        ..location
+    };
+    let json_array = (0..array_length(array, 10))
+        .take_while(|_| location.budget.element(""))
+        .map(|_| schema_kind_to_json(&items.schema_kind, location, gen, strategy))
         .collect::<Result<Vec<serde_json::Value>>>();
     Ok(serde_json::Value::Array(json_array?))
 }
 
-fn schema_type_to_json(schema_type: &Type, gen: &mut Unstructured) -> Result<serde_json::Value> {
+/// Random number of items of an array within the bounds of its schema, `max`
+/// is used when it has no maxItems. Both bounds are capped, so that a huge
+/// maxItems does not make the fuzzer build huge arrays.
+pub(crate) fn array_length(array: &ArrayType, max: usize) -> usize {
+    let max = array.max_items.unwrap_or(max);
+    let min = array.min_items.unwrap_or_else(|| max.min(1));
+    // A minItems above maxItems cannot be met, the minimum wins then
+    let max = max.max(min);
+    rand::thread_rng().gen_range(min.min(MAX_ARRAY_ITEMS)..=max.min(MAX_ARRAY_ITEMS))
+}
+
+/// Name of the string format, empty when there is none
+pub(crate) fn format_name(format: &VariantOrUnknownOrEmpty<StringFormat>) -> &str {
+    match format {
+        VariantOrUnknownOrEmpty::Item(StringFormat::Date) => "date",
+        VariantOrUnknownOrEmpty::Item(StringFormat::DateTime) => "date-time",
+        VariantOrUnknownOrEmpty::Item(StringFormat::Password) => "password",
+        VariantOrUnknownOrEmpty::Item(StringFormat::Byte) => "byte",
+        VariantOrUnknownOrEmpty::Item(StringFormat::Binary) => "binary",
+        VariantOrUnknownOrEmpty::Unknown(format) => format,
+        VariantOrUnknownOrEmpty::Empty => "",
+    }
+}
+
+/// Format of the parameter's schema when it is a string
+pub(crate) fn parameter_schema(parameter: &Parameter) -> Option<&Schema> {
+    match &parameter.parameter_data().format {
+        ParameterSchemaOrContent::Schema(schema) => Some(schema.to_item_ref()),
+        ParameterSchemaOrContent::Content(_) => None,
+    }
+}
+
+pub(crate) fn parameter_format(parameter: &Parameter) -> &str {
+    let schema = match parameter_schema(parameter) {
+        Some(schema) => schema,
+        None => return "",
+    };
+    match &schema.schema_kind {
+        SchemaKind::Type(Type::String(string)) => format_name(&string.format),
+        _ => "",
+    }
+}
+
+/// JSON value of the schema type, strings are generated for the name of the
+/// property they are the value of
+fn schema_type_to_json(
+    schema_type: &Type,
+    location: Location,
+    gen: &mut Unstructured,
+    strategy: Strategy,
+) -> Result<serde_json::Value> {
     match schema_type {
-        Type::String(_string_type) => Ok(json!(String::arbitrary(gen)?)),
+        Type::String(string_type) => Ok(json!(strategy.generate_named(
+            location.name,
+            format_name(&string_type.format),
+            gen
+        )?)),
         Type::Number(_number_type) => Ok(json!(f64::arbitrary(gen)?)),
         Type::Integer(_integer_type) => Ok(json!(i64::arbitrary(gen)?)),
-        Type::Object(object_type) => generate_json_object(object_type, gen),
-        Type::Array(array_type) => generate_json_array(array_type, gen),
+        Type::Object(object_type) => generate_json_object(object_type, location, gen, strategy),
+        Type::Array(array_type) => generate_json_array(array_type, location, gen, strategy),
         Type::Boolean {} => Ok(json!(bool::arbitrary(gen)?)),
     }
 }
 
+/// JSON value of the schema, the hints of its location in the body are
+/// applied to it
 fn schema_kind_to_json(
     schema_kind: &SchemaKind,
+    location: Location,
     gen: &mut Unstructured,
+    strategy: Strategy,
 ) -> Result<serde_json::Value> {
+    let hints = location.hints.get(location.pointer);
+    if let Some(value) = hints.and_then(Hints::sample) {
+        return Ok(value);
+    }
+    if location.depth > MAX_DEPTH {
+        return Ok(serde_json::Value::Null);
+    }
     let f = |vec: &Vec<ReferenceOr<Schema>>,
              gen: &mut Unstructured|
      -> Result<Vec<serde_json::Value>> {
         vec.iter()
-            .map(|ref_of_schema| schema_kind_to_json(&ref_of_schema.to_item_ref().schema_kind, gen))
+            .map(|ref_of_schema| {
+                schema_kind_to_json(
+                    &ref_of_schema.to_item_ref().schema_kind,
+                    location,
+                    gen,
+                    strategy,
+                )
+            })
             .collect()
     };
 
-    match schema_kind {
-        SchemaKind::Any(_any) => Ok(json!(String::arbitrary(gen)?)),
-        SchemaKind::Type(schema_type) => Ok(schema_type_to_json(schema_type, gen)?),
+    let value = match schema_kind {
+        SchemaKind::Any(_any) => Ok(json!(strategy.generate_named(location.name, "", gen)?)),
+        SchemaKind::Type(schema_type) => schema_type_to_json(schema_type, location, gen, strategy),
         SchemaKind::OneOf { one_of } => f(one_of, gen)?
             .into_iter()
             .choose(&mut rand::thread_rng())
@@ -76,20 +276,343 @@ fn schema_kind_to_json(
             .choose_multiple(&mut rand::thread_rng(), 5)
             .into()),
         SchemaKind::AllOf { all_of } => Ok(f(all_of, gen)?.into()),
+    }?;
+    Ok(match (value, hints) {
+        (serde_json::Value::String(value), Some(hints)) => {
+            json!(location.budget.truncate(hints.truncate(value)))
+        }
+        (serde_json::Value::String(value), None) => json!(location.budget.truncate(value)),
+        (value, _) => value,
+    })
+}
+
+/// Value nested `depth` levels deep in only arrays, only objects or both of them
+/// in turns
+fn deeply_nested(depth: usize) -> serde_json::Value {
+    let shape = rand::thread_rng().gen_range(0..3);
+    let mut value = json!(null);
+    for level in 0..depth {
+        value = match (shape, level % 2) {
+            (0, _) | (2, 0) => json!([value]),
+            _ => json!({ "a": value }),
+        };
+    }
+    value
+}
+
+/// Replaces a random property of the body with a deeply nested value, or the
+/// body as a whole when it has no properties
+fn nest(body: &mut serde_json::Value, depth: usize) {
+    let nested = deeply_nested(depth);
+    match body {
+        serde_json::Value::Object(object) if !object.is_empty() => {
+            if let Some(value) = object.values_mut().choose(&mut rand::thread_rng()) {
+                *value = nested;
+            }
+        }
+        body => *body = nested,
+    }
+}
+
+/// Value that conflicts with the one of a duplicate key, of another type for
+/// arrays and objects
+fn conflicting(value: &serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Bool(value) => json!(!value),
+        serde_json::Value::Number(number) => match number.as_i64() {
+            Some(number) => json!(number.wrapping_neg().wrapping_sub(1)),
+            None => json!(-number.as_f64().unwrap_or_default() - 1.0),
+        },
+        serde_json::Value::Null => json!(0),
+        _ => json!("openapi-fuzzer"),
+    }
+}
+
+/// Escapes a reference token of a JSON pointer
+fn pointer_token(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+/// Key of an object of the body that the duplicate-keys strategy sends twice,
+/// the other occurrence has a value that conflicts with the one of the body
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DuplicateKey {
+    /// JSON pointer of the object in the body
+    pub pointer: String,
+    pub key: String,
+    pub value: serde_json::Value,
+    /// Whether the repeated key is spelled with an escape, e.g. `"\u0072ole"`
+    /// for `role`, which parsers that compare raw keys do not take for the
+    /// same
+    pub escaped: bool,
+    /// Whether the repeated key comes before the original one
+    pub before: bool,
+}
+
+impl DuplicateKey {
+    /// A random key of one of the objects of the body, nothing when it has no
+    /// keys
+    fn random(body: &serde_json::Value) -> Option<DuplicateKey> {
+        fn keys<'v>(
+            value: &'v serde_json::Value,
+            pointer: String,
+            found: &mut Vec<(String, &'v str, &'v serde_json::Value)>,
+        ) {
+            match value {
+                serde_json::Value::Object(object) => {
+                    for (key, value) in object {
+                        found.push((pointer.clone(), key, value));
+                        keys(value, format!("{}/{}", pointer, pointer_token(key)), found);
+                    }
+                }
+                serde_json::Value::Array(items) => {
+                    for (index, item) in items.iter().enumerate() {
+                        keys(item, format!("{}/{}", pointer, index), found);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        let mut rng = rand::thread_rng();
+        let mut found = Vec::new();
+        keys(body, String::new(), &mut found);
+        let (pointer, key, value) = found.into_iter().choose(&mut rng)?;
+        Some(DuplicateKey {
+            pointer,
+            key: key.to_string(),
+            value: conflicting(value),
+            escaped: key
+                .chars()
+                .next()
+                .is_some_and(|first| (first as u32) < 0x10000)
+                && rng.gen(),
+            before: rng.gen(),
+        })
+    }
+
+    /// JSON of the body with the key repeated, or the plain body when the key
+    /// is no longer in it
+    pub fn encode(&self, body: &serde_json::Value) -> String {
+        let mut raw = String::new();
+        self.write(body, "", &mut raw);
+        raw
+    }
+
+    fn write(&self, value: &serde_json::Value, pointer: &str, raw: &mut String) {
+        let object = match value {
+            serde_json::Value::Object(object) => object,
+            serde_json::Value::Array(items) => {
+                raw.push('[');
+                for (index, item) in items.iter().enumerate() {
+                    if index > 0 {
+                        raw.push(',');
+                    }
+                    self.write(item, &format!("{}/{}", pointer, index), raw);
+                }
+                raw.push(']');
+                return;
+            }
+            value => {
+                *raw += &value.to_string();
+                return;
+            }
+        };
+        let json = |key: &str| serde_json::Value::from(key).to_string();
+        raw.push('{');
+        for (index, (key, value)) in object.iter().enumerate() {
+            if index > 0 {
+                raw.push(',');
+            }
+            let mut entry = json(key) + ":";
+            self.write(
+                value,
+                &format!("{}/{}", pointer, pointer_token(key)),
+                &mut entry,
+            );
+            if pointer != self.pointer || *key != self.key {
+                *raw += &entry;
+                continue;
+            }
+            let spelled = match self.escaped {
+                true => {
+                    let first = key.chars().next().unwrap_or_default();
+                    let rest = json(&key[first.len_utf8()..]);
+                    format!("\"\\u{:04x}{}", first as u32, &rest[1..])
+                }
+                false => json(key),
+            };
+            let repeated = format!("{}:{}", spelled, self.value);
+            match self.before {
+                true => *raw += &format!("{},{}", repeated, entry),
+                false => *raw += &format!("{},{}", entry, repeated),
+            }
+        }
+        raw.push('}');
+    }
+}
+
+/// Content type and bytes of the body as they are sent, serialized as the
+/// form or as JSON, with the duplicate key and compressed
+pub(crate) fn encode_body(
+    body: &serde_json::Value,
+    form: Option<&Form>,
+    duplicate_key: Option<&DuplicateKey>,
+    compression: Option<Compression>,
+) -> (String, Vec<u8>) {
+    let (content_type, encoded) = match (form, duplicate_key) {
+        (Some(form), _) => form.encode(body),
+        (None, Some(duplicate)) => (
+            "application/json".to_string(),
+            duplicate.encode(body).into_bytes(),
+        ),
+        (None, None) => (
+            "application/json".to_string(),
+            serde_json::to_vec(body).expect("unable to serialize json"),
+        ),
+    };
+    match compression {
+        Some(compression) => (content_type, compression.compress(&encoded)),
+        None => (content_type, encoded),
+    }
+}
+
+/// Value of another type than the one of the schema
+fn wrong_type(schema: &Schema) -> serde_json::Value {
+    match &schema.schema_kind {
+        SchemaKind::Type(Type::String(_)) => json!(12345),
+        SchemaKind::Type(Type::Number(_) | Type::Integer(_)) => json!("not a number"),
+        SchemaKind::Type(Type::Boolean {}) => json!("not a boolean"),
+        SchemaKind::Type(Type::Array(_)) => json!({"not": "an array"}),
+        _ => json!(["not an object"]),
+    }
+}
+
+/// Makes the body clearly violate its schema: a required property is left
+/// out, or a property or the body itself gets a value of another type
+fn invalidate(body: &mut serde_json::Value, schema: &Schema) {
+    let mut rng = rand::thread_rng();
+    if let (serde_json::Value::Object(object), SchemaKind::Type(Type::Object(object_type))) =
+        (&mut *body, &schema.schema_kind)
+    {
+        let required = object_type
+            .required
+            .iter()
+            .filter(|name| object.contains_key(*name))
+            .choose(&mut rng)
+            .cloned();
+        if let Some(required) = required.filter(|_| rng.gen()) {
+            object.remove(&required);
+            return;
+        }
+        let property = object
+            .iter_mut()
+            .filter_map(|(name, value)| Some((value, object_type.properties.get(name)?)))
+            .choose(&mut rng);
+        if let Some((value, property)) = property {
+            *value = wrong_type(property.to_item_ref());
+            return;
+        }
+    }
+    *body = wrong_type(schema);
+}
+
+/// Schema of the request body of the operation in the media type of the form,
+/// or in a JSON media type without one
+pub(crate) fn body_schema<'a>(operation: &'a Operation, form: Option<&Form>) -> Option<&'a Schema> {
+    operation
+        .request_body
+        .as_ref()?
+        .to_item_ref()
+        .content
+        .iter()
+        .find(|(content, _)| match form {
+            Some(form) => **content == form.media_type,
+            None => content.contains("json"),
+        })
+        .and_then(|(_, media)| media.schema.as_ref())
+        .map(|schema| schema.to_item_ref())
+}
+
+/// Whether the object schema declares the property
+pub(crate) fn declares(schema: &Schema, property: &str) -> bool {
+    match &schema.schema_kind {
+        SchemaKind::Type(Type::Object(object)) => object.properties.contains_key(property),
+        SchemaKind::Any(any) => any.properties.contains_key(property),
+        _ => false,
+    }
+}
+
+/// Adds the privileged properties that the schema does not declare to an
+/// object body
+fn assign_privileged(body: &mut serde_json::Value, schema: &Schema) {
+    if let serde_json::Value::Object(object) = body {
+        for (property, value) in strategy::privileged_properties() {
+            if !declares(schema, property) {
+                object.insert(property.to_string(), value);
+            }
+        }
     }
 }
 
+/// Parameters of the operation together with the path level parameters it does
+/// not override. A parameter is identified by its name and location.
+pub(crate) fn merge_parameters<'a>(
+    item: &'a PathItem,
+    operation: &'a Operation,
+) -> Vec<&'a Parameter> {
+    let mut parameters: Vec<&Parameter> = Vec::new();
+    for parameter in operation
+        .parameters
+        .iter()
+        .chain(item.parameters.iter())
+        .map(|ref_or_param| ref_or_param.to_item_ref())
+    {
+        if !parameters.iter().any(|p| {
+            p.name() == parameter.name() && p.location_string() == parameter.location_string()
+        }) {
+            parameters.push(parameter);
+        }
+    }
+    parameters
+}
+
+/// Operations of the path item together with their methods
+pub fn operations(item: &PathItem) -> Vec<(&'static str, &Operation)> {
+    vec![
+        ("GET", &item.get),
+        ("PUT", &item.put),
+        ("POST", &item.post),
+        ("DELETE", &item.delete),
+        ("OPTIONS", &item.options),
+        ("HEAD", &item.head),
+        ("PATCH", &item.patch),
+        ("TRACE", &item.trace),
+    ]
+    .into_iter()
+    .filter_map(|(method, operation)| operation.as_ref().map(|operation| (method, operation)))
+    .collect()
+}
+
 impl<'a> Payload<'a> {
     fn new(
-        url: &'a Url,
+        url: Url,
         method: &'a str,
         path: &'a str,
+        item: &'a PathItem,
         operation: &'a Operation,
-        extra_headers: &'a [(String, String)],
+        options: &'a PayloadOptions,
+        store: &Store,
     ) -> Result<Payload<'a>> {
+        let config = options.operations.for_operation(path, method, operation);
         let mut query_params: Vec<(&str, String)> = Vec::new();
         let mut path_params: Vec<(&str, String)> = Vec::new();
-        let mut headers: Vec<(&str, String)> = Vec::new();
+        let mut headers: Vec<(String, String)> = Vec::new();
+        let strategy = *options
+            .strategies
+            .iter()
+            .choose(&mut rand::thread_rng())
+            .unwrap_or(&Strategy::Random);
 
         // Set-up random data generator
         let fuzzer_input: String = rand::thread_rng()
@@ -98,114 +621,514 @@ impl<'a> Payload<'a> {
             .collect();
 
         let mut generator = Unstructured::new(fuzzer_input.as_bytes());
-        for ref_or_param in operation.parameters.iter() {
-            match ref_or_param.to_item_ref() {
-                Parameter::Query { parameter_data, .. } => {
-                    query_params.push((&parameter_data.name, String::arbitrary(&mut generator)?))
-                }
+        let budget = Remaining::new(options.body_budget);
+        for parameter in merge_parameters(item, operation) {
+            let name = parameter.name();
+            let hints = Hints::new(|name| parameter.parameter_data().extensions.get(name));
+            if hints.skip {
+                continue;
+            }
+            let format = parameter_format(parameter);
+            let pinned = options
+                .params
+                .iter()
+                .find(|(param, _)| param == name)
+                .map(|(_, value)| value)
+                .or_else(|| config.params.get(name))
+                .cloned()
+                .or_else(|| options.values.sample(operation, name))
+                .or_else(|| {
+                    hints.sample().map(|value| match value {
+                        serde_json::Value::String(value) => value,
+                        value => value.to_string(),
+                    })
+                })
+                .or_else(|| store.extracted_param(name))
+                .or_else(|| store.linked_param(path, method, name));
+            let conforming = parameter_schema(parameter)
+                .filter(|_| options.conforming)
+                .map(|schema| match conform::generate_resolved(schema, &budget) {
+                    serde_json::Value::String(value) => value,
+                    value => value.to_string(),
+                });
+            let mut generate = |strategy: Strategy| match &conforming {
+                Some(value) => Ok(value.clone()),
+                None => strategy
+                    .generate_named(name, format, &mut generator)
+                    .map(|value| hints.truncate(value)),
+            };
+            match parameter {
+                Parameter::Query { parameter_data, .. } => query_params.push((
+                    &parameter_data.name,
+                    pinned.map_or_else(|| generate(strategy), Ok)?,
+                )),
                 Parameter::Path { parameter_data, .. } => {
-                    path_params.push((&parameter_data.name, String::arbitrary(&mut generator)?))
-                }
-                Parameter::Header { parameter_data, .. } => {
-                    headers.push((&parameter_data.name, String::arbitrary(&mut generator)?))
+                    let value = match pinned {
+                        Some(value) => value,
+                        None if strategy == Strategy::Traversal => {
+                            strategy::traversal_string(&mut generator)?
+                        }
+                        None => store
+                            .path_param(path, name)
+                            .map_or_else(|| generate(strategy.for_path()), Ok)?,
+                    };
+                    path_params.push((&parameter_data.name, value))
                 }
+                Parameter::Header { parameter_data, .. } => headers.push((
+                    parameter_data.name.clone(),
+                    pinned.map_or_else(|| generate(strategy.for_header()), Ok)?,
+                )),
                 Parameter::Cookie { parameter_data, .. } => headers.push((
-                    "Cookie",
+                    "Cookie".to_string(),
                     format!(
                         "{}={}",
                         parameter_data.name,
-                        String::arbitrary(&mut generator)?
+                        pinned.map_or_else(|| generate(strategy.for_header()), Ok)?
                     ),
                 )),
             }
         }
 
+        let body_hints = Hints::body(|name| operation.extensions.get(name));
+        // Bodies are sent as JSON when the operation accepts it and as the
+        // first form media type with a schema otherwise
+        let form = operation
+            .request_body
+            .as_ref()
+            .map(|ref_or_body| &ref_or_body.to_item_ref().content)
+            .filter(|content| !content.keys().any(|content| content.contains("json")))
+            .and_then(|content| {
+                content
+                    .iter()
+                    .find(|(content, media)| form::is_form(content) && media.schema.is_some())
+            })
+            .map(|(content, media)| Form::new(content, media, &budget));
         let body = operation.request_body.as_ref().map(|ref_or_body| {
             let request_body = ref_or_body.to_item_ref();
             request_body
                 .content
                 .iter()
-                .filter(|(content, _)| content.contains("json"))
-                .map(|(_, media)| {
-                    media.schema.as_ref().map(|schema| {
-                        schema_kind_to_json(&schema.to_item_ref().schema_kind, &mut generator)
-                    })
+                .filter(|(content, _)| match &form {
+                    Some(form) => **content == form.media_type,
+                    None => content.contains("json"),
+                })
+                .filter_map(|(_, media)| {
+                    media
+                        .schema
+                        .as_ref()
+                        .map(|schema| match options.conforming {
+                            true => Ok(conform::generate_resolved(schema.to_item_ref(), &budget)),
+                            false => schema_kind_to_json(
+                                &schema.to_item_ref().schema_kind,
+                                Location {
+                                    name: "",
+                                    pointer: "",
+                                    hints: &body_hints,
+                                    budget: &budget,
+                                    depth: 0,
+                                },
+                                &mut generator,
+                                strategy,
+                            )
+                            .map(|mut body| {
+                                // Form fields are text whatever their type is
+                                if strategy == Strategy::Invalid && form.is_none() {
+                                    invalidate(&mut body, schema.to_item_ref());
+                                }
+                                if strategy == Strategy::MassAssignment {
+                                    assign_privileged(&mut body, schema.to_item_ref());
+                                }
+                                body
+                            }),
+                        })
                 })
-                .flatten()
                 .collect::<Result<Vec<_>>>()
         });
+        let mut body = body.unwrap_or_else(|| Ok(Vec::new()))?;
+        if strategy == Strategy::Nesting {
+            let depth = options
+                .nesting_depth
+                .unwrap_or(DEFAULT_NESTING_DEPTH)
+                .min(MAX_NESTING_DEPTH);
+            for body in &mut body {
+                nest(body, depth);
+            }
+        }
+        let duplicate_key = match (strategy, &form, body.first()) {
+            (Strategy::DuplicateKeys, None, Some(body)) => DuplicateKey::random(body),
+            _ => None,
+        };
+        let compression = match strategy {
+            Strategy::Gzip if !body.is_empty() => {
+                headers.push(("Content-Encoding".to_string(), "gzip".to_string()));
+                Some(Compression::random())
+            }
+            _ => None,
+        };
 
-        for (name, value) in extra_headers {
+        // Headers of the operation's config win over the global ones
+        for (name, value) in options
+            .extra_headers
+            .iter()
+            .map(|(name, value)| (name, value))
+            .chain(config.headers.iter())
+        {
             let index = headers
                 .iter()
                 .position(|(header_name, _)| &header_name.to_lowercase() == name);
-            match index {
-                Some(i) => headers[i] = (name, value.clone()),
-                None => headers.push((name, value.clone())),
+            match (index, value.is_empty()) {
+                (Some(i), true) => {
+                    headers.remove(i);
+                }
+                (Some(i), false) => headers[i] = (name.clone(), value.clone()),
+                (None, true) => {}
+                (None, false) => headers.push((name.clone(), value.clone())),
             }
         }
 
         Ok(Payload {
+            request_id: format!("{:032x}", rand::random::<u128>()),
             url,
             method,
             path,
             query_params,
             path_params,
             headers,
-            body: body.unwrap_or_else(|| Ok(Vec::new()))?,
+            body,
             responses: &operation.responses,
+            framing: match strategy {
+                Strategy::Framing => Some(Framing::random()),
+                _ => None,
+            },
+            form,
+            duplicate_key,
+            compression,
+            http_version: options.http_version,
+            raw_path: strategy == Strategy::Traversal,
+            strategy,
+            config,
         })
     }
 
-    pub fn for_all_methods(
-        url: &'a Url,
+    /// Payload for the operation of the path item, unless the filter skips it
+    pub fn for_operation(
+        url: &Url,
         path: &'a str,
         item: &'a PathItem,
-        extra_headers: &'a [(String, String)],
-    ) -> Result<Vec<Payload<'a>>> {
-        // TODO: Pass parameters to fuzz operation
-        let operations = vec![
-            ("GET", &item.get),
-            ("PUT", &item.put),
-            ("POST", &item.post),
-            ("DELETE", &item.delete),
-            ("OPTIONS", &item.options),
-            ("HEAD", &item.head),
-            ("PATCH", &item.patch),
-            ("TRACE", &item.trace),
-        ];
+        method: &'a str,
+        operation: &'a Operation,
+        options: &'a PayloadOptions,
+        store: &Store,
+    ) -> Result<Option<Payload<'a>>> {
+        if !options.filter.matches(path, method, operation) {
+            return Ok(None);
+        }
+        // Servers of the operation take precedence over the ones of the path
+        let url = match operation.servers.first().or_else(|| item.servers.first()) {
+            Some(server) => servers::resolve(server, Some(url), &options.server_variables)?,
+            None => url.clone(),
+        };
+        Payload::new(url, method, path, item, operation, options, store).map(Some)
+    }
 
-        let mut payloads = Vec::new();
-        for (method, op) in operations {
-            if let Some(operation) = op {
-                payloads.push(Payload::new(url, method, path, operation, extra_headers)?)
+    /// All generated string values of parameters, headers and body
+    pub fn string_values(&self) -> Vec<&str> {
+        fn collect<'v>(value: &'v serde_json::Value, values: &mut Vec<&'v str>) {
+            match value {
+                serde_json::Value::String(string) => values.push(string),
+                serde_json::Value::Array(array) => array.iter().for_each(|v| collect(v, values)),
+                serde_json::Value::Object(object) => {
+                    object.values().for_each(|v| collect(v, values))
+                }
+                _ => {}
             }
         }
 
-        Ok(payloads)
+        let mut values = self
+            .query_params
+            .iter()
+            .chain(self.path_params.iter())
+            .map(|(_, value)| value.as_str())
+            .chain(self.headers.iter().map(|(_, value)| value.as_str()))
+            .collect::<Vec<_>>();
+        self.body
+            .iter()
+            .for_each(|value| collect(value, &mut values));
+        values
+    }
+
+    /// Replaces the values of the parameters and headers and the strings of the
+    /// body that the function maps to a new value, returns whether it mapped
+    /// any of them
+    pub fn replace_strings(&mut self, replace: impl Fn(&str) -> Option<String>) -> bool {
+        fn replace_json(
+            value: &mut serde_json::Value,
+            replace: &impl Fn(&str) -> Option<String>,
+        ) -> bool {
+            match value {
+                serde_json::Value::String(string) => match replace(string) {
+                    Some(replaced) => {
+                        *string = replaced;
+                        true
+                    }
+                    None => false,
+                },
+                serde_json::Value::Array(values) => {
+                    values.iter_mut().fold(false, |replaced, value| {
+                        replace_json(value, replace) | replaced
+                    })
+                }
+                serde_json::Value::Object(object) => {
+                    object.values_mut().fold(false, |replaced, value| {
+                        replace_json(value, replace) | replaced
+                    })
+                }
+                _ => false,
+            }
+        }
+
+        let mut replaced = false;
+        for value in self
+            .query_params
+            .iter_mut()
+            .chain(self.path_params.iter_mut())
+            .map(|(_, value)| value)
+            .chain(self.headers.iter_mut().map(|(_, value)| value))
+        {
+            if let Some(new) = replace(value) {
+                *value = new;
+                replaced = true;
+            }
+        }
+        for value in &mut self.body {
+            replaced |= replace_json(value, &replace);
+        }
+        replaced
+    }
+
+    /// Url of the request with the path parameters filled in
+    pub fn request_url(&self) -> Result<Url> {
+        let mut path_with_params = self.path.to_owned();
+        for (name, value) in self.path_params.iter() {
+            path_with_params = path_with_params.replace(&format!("{{{}}}", name), value);
+        }
+        Ok(self.url.join(path_with_params.trim_start_matches('/'))?)
+    }
+
+    /// Url of the request with the path parameters filled in and the query
+    /// parameters appended
+    pub fn full_url(&self) -> Result<Url> {
+        let mut url = self.request_url()?;
+        for (name, value) in &self.query_params {
+            url.query_pairs_mut().append_pair(name, value);
+        }
+        Ok(url)
+    }
+
+    /// Target of the request line, the path with the parameters filled in and
+    /// the query. Path parameters of raw paths are put in as they are, so that
+    /// their dot segments and separators reach the server.
+    pub fn request_target(&self) -> Result<String> {
+        let url = self.full_url()?;
+        let path = match self.raw_path {
+            true => {
+                let mut path = self.path.to_owned();
+                for (name, value) in self.path_params.iter() {
+                    path = path.replace(&format!("{{{}}}", name), value);
+                }
+                format!(
+                    "{}/{}",
+                    self.url.path().trim_end_matches('/'),
+                    path.trim_start_matches('/')
+                )
+            }
+            false => url.path().to_string(),
+        };
+        Ok(match url.query() {
+            Some(query) => format!("{}?{}", path, query),
+            None => path,
+        })
+    }
+
+    /// Content type and bytes of the body, which is JSON unless the payload
+    /// has a form
+    pub fn encoded_body(&self) -> Option<(String, Vec<u8>)> {
+        Some(encode_body(
+            self.body.first()?,
+            self.form.as_ref(),
+            self.duplicate_key.as_ref(),
+            self.compression,
+        ))
     }
 
     pub fn to_curl(&self) -> Result<String> {
         let mut curl_command = format!("curl -X {} ", self.method);
-        if !self.body.is_empty() {
-            curl_command += &format!(
-                "-d '{}' ",
-                serde_json::to_string(&self.body[0]).expect("unable to serialize json")
-            );
+        match self.encoded_body() {
+            // Compressed bodies are binary, they are piped to curl
+            Some((content_type, body)) if self.compression.is_some() => {
+                let octal = body.iter().map(|byte| format!("\\{:03o}", byte));
+                curl_command = format!(
+                    "printf '{}' | {}-H 'Content-Type:{}' --data-binary @- ",
+                    octal.collect::<String>(),
+                    curl_command,
+                    content_type
+                )
+            }
+            Some((content_type, body)) if self.form.is_some() => {
+                curl_command += &format!(
+                    "-H 'Content-Type:{}' --data-binary '{}' ",
+                    content_type,
+                    String::from_utf8_lossy(&body)
+                )
+            }
+            Some((_, body)) => curl_command += &format!("-d '{}' ", String::from_utf8_lossy(&body)),
+            None => {}
         }
         for (name, value) in &self.headers {
             curl_command += &format!("-H '{}:{}' ", name, value);
         }
+        curl_command += &format!("-H '{}:{}' ", REQUEST_ID_HEADER, self.request_id);
 
-        let mut path_with_params = self.path.to_owned();
-        for (name, value) in self.path_params.iter() {
-            path_with_params = path_with_params.replace(&format!("{{{}}}", name), value);
+        match self.raw_path {
+            // curl would resolve the dot segments itself
+            true => Ok(format!(
+                "{}--path-as-is '{}'",
+                curl_command,
+                self.sent_url()?
+            )),
+            false => Ok(format!("{}'{}'", curl_command, self.sent_url()?)),
+        }
+    }
+
+    /// Url as it is sent, with the dot segments of raw paths
+    pub fn sent_url(&self) -> Result<String> {
+        let url = self.full_url()?;
+        match self.raw_path {
+            true => Ok(format!(
+                "{}{}",
+                &url[..Position::BeforePath],
+                self.request_target()?
+            )),
+            false => Ok(url.to_string()),
         }
+    }
 
-        Ok(curl_command
-            + self
-                .url
-                .join(path_with_params.trim_start_matches('/'))?
-                .as_str())
+    /// Sends the request with the method and reads its response. Framed
+    /// bodies, dot segments and versions other than HTTP/1.1 need a
+    /// connection of their own, which only plain http urls get.
+    pub fn send(&self, method: &str) -> Result<Response> {
+        let raw =
+            self.framing.is_some() || self.http_version != HttpVersion::Http11 || self.raw_path;
+        if !raw {
+            return self.send_with_client(method);
+        }
+        if self.url.scheme() != "http" {
+            bail!(
+                "requests over a connection of their own are only sent to plain http urls, not {}",
+                self.url
+            );
+        }
+        framing::send(self, method)
+    }
+
+    fn send_with_client(&self, method: &str) -> Result<Response> {
+        // Redirects of operations that document them are checked like any
+        // other response instead of being followed
+        let agent = match checks::redirects(self.responses) {
+            true => ureq::AgentBuilder::new().redirects(0).build(),
+            false => ureq::agent(),
+        };
+        let mut request = agent
+            .request_url(method, &self.request_url()?)
+            .set(REQUEST_ID_HEADER, &self.request_id);
+
+        for (param, value) in self.query_params.iter() {
+            request = request.query(param, value)
+        }
+
+        for (header, value) in self.headers.iter() {
+            request = request.set(header, value)
+        }
+
+        // Bodies are sent as encoded by the payload, which may repeat keys or
+        // compress them
+        let resp = match self.encoded_body() {
+            Some((content_type, body)) => form::send_bytes(request, &content_type, &body)?,
+            None => request.call().or_any_status()?,
+        };
+        Response::read(resp)
+    }
+}
+
+/// Request of a finding as it was saved by the fuzzer, which the resender and
+/// the reruns of the findings database send again
+#[derive(Debug, Deserialize)]
+pub struct StoredPayload {
+    pub url: Url,
+    pub method: String,
+    pub path: String,
+    pub query_params: Vec<(String, String)>,
+    pub path_params: Vec<(String, String)>,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<serde_json::Value>,
+    #[serde(default)]
+    pub framing: Option<Framing>,
+    #[serde(default)]
+    pub form: Option<Form>,
+    #[serde(default)]
+    pub duplicate_key: Option<DuplicateKey>,
+    #[serde(default)]
+    pub compression: Option<Compression>,
+    #[serde(default)]
+    pub http_version: HttpVersion,
+    #[serde(default)]
+    pub raw_path: bool,
+    #[serde(skip)]
+    responses: Responses,
+}
+
+impl StoredPayload {
+    /// Replaces the headers of the same name, or adds them
+    pub fn set_headers(&mut self, extra_headers: &[(String, String)]) {
+        for (name, value) in extra_headers {
+            let index = self
+                .headers
+                .iter()
+                .position(|(header, _)| header.eq_ignore_ascii_case(name));
+            match index {
+                Some(i) => self.headers[i] = (name.clone(), value.clone()),
+                None => self.headers.push((name.clone(), value.clone())),
+            }
+        }
+    }
+
+    /// Payload sending the request again with a new request id, so that it
+    /// can be told apart from the original one in the logs of the server
+    pub fn payload(&self) -> Payload<'_> {
+        fn params(params: &[(String, String)]) -> Vec<(&str, String)> {
+            params
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.clone()))
+                .collect()
+        }
+        Payload {
+            request_id: format!("{:032x}", rand::random::<u128>()),
+            url: self.url.clone(),
+            method: &self.method,
+            path: &self.path,
+            query_params: params(&self.query_params),
+            path_params: params(&self.path_params),
+            headers: self.headers.clone(),
+            body: self.body.clone(),
+            responses: &self.responses,
+            framing: self.framing,
+            form: self.form.clone(),
+            duplicate_key: self.duplicate_key.clone(),
+            compression: self.compression,
+            http_version: self.http_version,
+            raw_path: self.raw_path,
+            strategy: Strategy::Random,
+            config: OperationConfig::default(),
+        }
     }
 }
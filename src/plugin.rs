@@ -0,0 +1,128 @@
+use std::{cell::RefCell, fs, path::Path};
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Value};
+use wasmi::{Config, Engine, Instance, Linker, Memory, Module, Store, TypedFunc};
+
+use crate::fuzzer::Response;
+use crate::payload::Payload;
+use crate::script::{apply_request, request_value, response_value};
+
+// Fuel of a single call, runaway plugins would stop fuzzing
+const FUEL: u64 = 100_000_000;
+
+/// WebAssembly module generating requests or checking responses. It exports
+/// its `memory` and `alloc(len: i32) -> i32`, which reserves memory for the
+/// JSON passed to it, and any of
+///
+/// - `generate(ptr: i32, len: i32) -> i64`, which receives the request the
+///   fuzzer generated and returns the JSON of the request to send
+/// - `check(ptr: i32, len: i32) -> i64`, which receives the request and the
+///   response as `{"request": ..., "response": ...}` and returns the reason
+///   of a finding
+///
+/// Results are returned as `ptr << 32 | len`, 0 keeps the request or is no
+/// finding. Requests and responses have the same fields as the ones of the
+/// script hooks.
+#[derive(Debug)]
+pub struct Plugin {
+    name: String,
+    store: RefCell<Store<()>>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    generate: Option<TypedFunc<(i32, i32), i64>>,
+    check: Option<TypedFunc<(i32, i32), i64>>,
+}
+
+impl Plugin {
+    /// Loads a `.wasm` module or its `.wat` text format
+    pub fn load(path: &Path) -> Result<Plugin> {
+        let error = || format!("invalid plugin {}", path.display());
+        let wasm = fs::read(path).with_context(|| format!("unable to read {}", path.display()))?;
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, wasm).with_context(error)?;
+        let mut store = Store::new(&engine, ());
+        let instance: Instance = Linker::<()>::new(&engine)
+            .instantiate_and_start(&mut store, &module)
+            .with_context(error)?;
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or_else(|| anyhow!("plugin {} does not export its memory", path.display()))?;
+        let alloc = instance
+            .get_typed_func(&store, "alloc")
+            .with_context(error)?;
+        let generate = instance.get_typed_func(&store, "generate").ok();
+        let check = instance.get_typed_func(&store, "check").ok();
+        if generate.is_none() && check.is_none() {
+            return Err(anyhow!(
+                "plugin {} exports neither generate nor check",
+                path.display()
+            ));
+        }
+        Ok(Plugin {
+            name: path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            store: RefCell::new(store),
+            memory,
+            alloc,
+            generate,
+            check,
+        })
+    }
+
+    /// Passes the JSON to the function and returns its result
+    fn call(&self, function: &TypedFunc<(i32, i32), i64>, input: &Value) -> Result<Option<String>> {
+        let store = &mut *self.store.borrow_mut();
+        store.set_fuel(FUEL)?;
+        let input = serde_json::to_vec(input)?;
+        let ptr = self.alloc.call(&mut *store, input.len() as i32)?;
+        self.memory.write(&mut *store, ptr as usize, &input)?;
+        let result = function.call(&mut *store, (ptr, input.len() as i32))? as u64;
+        if result == 0 {
+            return Ok(None);
+        }
+        let mut output = vec![0; (result & 0xffff_ffff) as usize];
+        self.memory
+            .read(&*store, (result >> 32) as usize, &mut output)?;
+        Ok(Some(String::from_utf8_lossy(&output).into_owned()))
+    }
+
+    /// Lets `generate` change the payload
+    pub fn generate(&self, payload: &mut Payload) -> Result<()> {
+        let generate = match &self.generate {
+            Some(generate) => generate,
+            None => return Ok(()),
+        };
+        let output = self
+            .call(generate, &request_value(payload))
+            .with_context(|| format!("generate of plugin {} failed", self.name))?;
+        if let Some(output) = output {
+            match serde_json::from_str::<Value>(&output) {
+                Ok(Value::Object(request)) => apply_request(payload, &request),
+                _ => {
+                    return Err(anyhow!(
+                        "generate of plugin {} did not return a JSON request",
+                        self.name
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Reason of a finding returned by `check`
+    pub fn check(&self, payload: &Payload, resp: &Response) -> Result<Option<String>> {
+        let check = match &self.check {
+            Some(check) => check,
+            None => return Ok(None),
+        };
+        let input = json!({"request": request_value(payload), "response": response_value(resp)});
+        self.call(check, &input)
+            .map(|reason| reason.map(|reason| format!("plugin {}: {}", self.name, reason)))
+            .with_context(|| format!("check of plugin {} failed", self.name))
+    }
+}
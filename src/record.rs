@@ -0,0 +1,352 @@
+use std::{
+    collections::BTreeMap,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    path::Path,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use openapi_utils::ReferenceOrExt;
+use openapiv3::OpenAPI;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use ureq::OrAnyStatus;
+use url::Url;
+
+use crate::fuzzer::MAX_BODY_SIZE;
+use crate::payload;
+
+// Headers that only concern one connection and are not recorded or forwarded
+const HOP_BY_HOP: &[&str] = &[
+    "connection",
+    "content-length",
+    "host",
+    "keep-alive",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// Request and response captured by the recording proxy for an operation of
+/// the specification. The request and the response have the same fields as
+/// the ones of the script hooks.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Seed {
+    pub method: String,
+    pub path: String,
+    pub request: Value,
+    pub response: Value,
+}
+
+/// Recorded requests of each operation, by its path and method, which
+/// fuzzing mutates
+#[derive(Debug, Default)]
+pub struct Seeds(BTreeMap<(String, String), Vec<Value>>);
+
+impl Seeds {
+    /// Reads the seeds the recording proxy wrote, one JSON object per line
+    pub fn load(path: &Path) -> Result<Seeds> {
+        let file =
+            File::open(path).with_context(|| format!("unable to open seeds {}", path.display()))?;
+        let mut seeds = Seeds::default();
+        for (number, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let seed: Seed = serde_json::from_str(&line).with_context(|| {
+                format!("invalid seed on line {} of {}", number + 1, path.display())
+            })?;
+            seeds
+                .0
+                .entry((seed.path, seed.method))
+                .or_default()
+                .push(seed.request);
+        }
+        Ok(seeds)
+    }
+
+    /// Recorded requests of every operation by its path and method
+    pub fn operations(&self) -> impl Iterator<Item = (&(String, String), &Vec<Value>)> {
+        self.0.iter()
+    }
+
+    /// Recorded requests of the operation
+    pub fn get(&self, path: &str, method: &str) -> Option<&[Value]> {
+        self.0
+            .get(&(path.to_string(), method.to_string()))
+            .map(Vec::as_slice)
+    }
+}
+
+/// Request received by the proxy
+struct Incoming {
+    method: String,
+    target: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+fn read_request(stream: &TcpStream) -> Result<Incoming> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().unwrap_or("/").to_string();
+
+    let mut headers = Vec::new();
+    let mut length = 0;
+    let mut chunked = false;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            let (name, value) = (name.trim().to_lowercase(), value.trim().to_string());
+            match name.as_str() {
+                "content-length" => length = value.parse().unwrap_or(0),
+                "transfer-encoding" => chunked = value.to_lowercase().contains("chunked"),
+                _ => {}
+            }
+            headers.push((name, value));
+        }
+    }
+    let body = match chunked {
+        true => read_chunked(&mut reader)?,
+        false => read_body(&mut reader, length)?,
+    };
+    Ok(Incoming {
+        method,
+        target,
+        headers,
+        body,
+    })
+}
+
+/// Body of the length, which is only allocated while it arrives so that a
+/// bogus Content-Length cannot exhaust the memory
+fn read_body(reader: &mut impl Read, length: u64) -> Result<Vec<u8>> {
+    if length > MAX_BODY_SIZE {
+        bail!("body of {} bytes is too large to record", length);
+    }
+    let mut body = Vec::new();
+    reader.take(length).read_to_end(&mut body)?;
+    if (body.len() as u64) < length {
+        bail!("body ended after {} of {} bytes", body.len(), length);
+    }
+    Ok(body)
+}
+
+/// Body in chunked transfer encoding without its chunk sizes and trailers
+fn read_chunked(reader: &mut impl BufRead) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+    let mut line = String::new();
+    loop {
+        line.clear();
+        reader.read_line(&mut line)?;
+        let size = line.trim().split(';').next().unwrap_or_default();
+        let size = u64::from_str_radix(size, 16)
+            .with_context(|| format!("invalid chunk size {:?}", line.trim_end()))?;
+        if size == 0 {
+            break;
+        }
+        if body.len() as u64 + size > MAX_BODY_SIZE {
+            bail!("chunked body is too large to record");
+        }
+        body.extend(read_body(reader, size)?);
+        line.clear();
+        reader.read_line(&mut line)?;
+    }
+    // Trailers end with an empty line
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            return Ok(body);
+        }
+    }
+}
+
+/// Path template of the specification matching the path together with the
+/// values of its parameters
+fn match_path<'a>(spec: &'a OpenAPI, path: &str) -> Option<(&'a str, Map<String, Value>)> {
+    let segments = path.trim_matches('/').split('/').collect::<Vec<_>>();
+    spec.paths.keys().find_map(|template| {
+        let parts = template.trim_matches('/').split('/').collect::<Vec<_>>();
+        if parts.len() != segments.len() {
+            return None;
+        }
+        let mut params = Map::new();
+        for (part, segment) in parts.iter().zip(&segments) {
+            match part
+                .strip_prefix('{')
+                .and_then(|part| part.strip_suffix('}'))
+            {
+                Some(name) if !segment.is_empty() => {
+                    let value = percent_encoding::percent_decode_str(segment).decode_utf8_lossy();
+                    params.insert(name.to_string(), json!(value));
+                }
+                _ if part == segment => {}
+                _ => return None,
+            }
+        }
+        Some((template.as_str(), params))
+    })
+}
+
+/// Forwards the request upstream and writes the response back, returns the
+/// seed when the request belongs to an operation of the specification
+fn proxy(
+    spec: &OpenAPI,
+    upstream: &Url,
+    agent: &ureq::Agent,
+    mut stream: TcpStream,
+) -> Result<Option<Seed>> {
+    let incoming = match read_request(&stream) {
+        Ok(incoming) => incoming,
+        Err(e) => {
+            write!(
+                stream,
+                "HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+            )?;
+            return Err(e.context("unable to read the request"));
+        }
+    };
+    let url = upstream.join(&incoming.target)?;
+    let mut request = agent.request_url(&incoming.method, &url);
+    let headers = incoming
+        .headers
+        .iter()
+        .filter(|(name, _)| !HOP_BY_HOP.contains(&name.as_str()))
+        .collect::<Vec<_>>();
+    for (name, value) in &headers {
+        request = request.set(name, value);
+    }
+    let resp = match incoming.body.is_empty() {
+        true => request.call(),
+        false => request.send_bytes(&incoming.body),
+    }
+    .or_any_status();
+    let resp = match resp {
+        Ok(resp) => resp,
+        Err(e) => {
+            write!(
+                stream,
+                "HTTP/1.1 502 Bad Gateway\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+            )?;
+            return Err(anyhow!("{} {} failed: {}", incoming.method, url, e));
+        }
+    };
+
+    let status = resp.status();
+    let status_text = resp.status_text().to_string();
+    let resp_headers = resp
+        .headers_names()
+        .into_iter()
+        .filter(|name| !HOP_BY_HOP.contains(&name.to_lowercase().as_str()))
+        .flat_map(|name| {
+            resp.all(&name)
+                .into_iter()
+                .map(|value| (name.to_lowercase(), value.to_string()))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let mut body = Vec::new();
+    resp.into_reader().read_to_end(&mut body)?;
+    write!(stream, "HTTP/1.1 {} {}\r\n", status, status_text)?;
+    for (name, value) in &resp_headers {
+        write!(stream, "{}: {}\r\n", name, value)?;
+    }
+    write!(
+        stream,
+        "Content-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    )?;
+    stream.write_all(&body)?;
+
+    // Requests outside of the base path of the api are only forwarded
+    let base = upstream.path().trim_end_matches('/');
+    let relative = match url.path().strip_prefix(base) {
+        Some(relative) => relative,
+        None => return Ok(None),
+    };
+    let (template, path_params) = match match_path(spec, relative) {
+        Some(found) => found,
+        None => return Ok(None),
+    };
+    let method = incoming.method.to_uppercase();
+    let declared = spec
+        .paths
+        .get(template)
+        .map(|item| {
+            payload::operations(item.to_item_ref())
+                .iter()
+                .any(|(declared, _)| *declared == method)
+        })
+        .unwrap_or(false);
+    if !declared {
+        return Ok(None);
+    }
+    let to_object = |pairs: &mut dyn Iterator<Item = (String, String)>| {
+        Value::Object(pairs.map(|(name, value)| (name, json!(value))).collect())
+    };
+    let request = json!({
+        "method": method,
+        "path": template,
+        "url": upstream.as_str(),
+        "query": to_object(&mut url.query_pairs().into_owned()),
+        "path_params": path_params,
+        "headers": to_object(&mut headers.into_iter().cloned()),
+        "body": serde_json::from_slice::<Value>(&incoming.body).ok(),
+    });
+    let response = json!({
+        "status": status,
+        "headers": to_object(&mut resp_headers.into_iter()),
+        "body": String::from_utf8_lossy(&body),
+    });
+    Ok(Some(Seed {
+        method,
+        path: template.to_string(),
+        request,
+        response,
+    }))
+}
+
+/// Runs a reverse proxy in front of the api on the address and appends the
+/// requests and responses of the operations of the specification to the seeds
+/// file until it is stopped
+pub fn record(spec: &OpenAPI, address: &str, upstream: &Url, seeds: &Path) -> Result<()> {
+    let listener =
+        TcpListener::bind(address).with_context(|| format!("unable to listen on {}", address))?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(seeds)
+        .with_context(|| format!("unable to open seeds {}", seeds.display()))?;
+    // Redirects are passed on to the client
+    let agent = ureq::AgentBuilder::new().redirects(0).build();
+    eprintln!(
+        "recording requests to {} on http://{}",
+        upstream,
+        listener.local_addr()?
+    );
+    for stream in listener.incoming().flatten() {
+        match proxy(spec, upstream, &agent, stream) {
+            Ok(Some(seed)) => {
+                eprintln!(
+                    "recorded {} {} {}",
+                    seed.method, seed.path, seed.response["status"]
+                );
+                serde_json::to_writer(&mut file, &seed)?;
+                writeln!(file)?;
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("{:#}", e),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,150 @@
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use percent_encoding::percent_decode_str;
+use serde_json::{json, Value};
+use url::Url;
+
+use crate::spec::{self, Format};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Location {
+    File(PathBuf),
+    Url(Url),
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Location::File(path) => write!(f, "{}", path.display()),
+            Location::Url(url) => write!(f, "{}", url),
+        }
+    }
+}
+
+impl Location {
+    /// Location of the referenced document relative to this one
+    fn join(&self, reference: &str) -> Result<Location> {
+        if let Ok(url) = Url::parse(reference) {
+            return match url.scheme() {
+                "file" => url
+                    .to_file_path()
+                    .map(Location::File)
+                    .map_err(|_| anyhow!("invalid file url {}", reference)),
+                _ => Ok(Location::Url(url)),
+            };
+        }
+        match self {
+            Location::File(path) => Ok(Location::File(
+                path.parent()
+                    .unwrap_or_else(|| Path::new(""))
+                    .join(reference),
+            )),
+            Location::Url(url) => Ok(Location::Url(url.join(reference)?)),
+        }
+    }
+
+    fn load(&self) -> Result<Value> {
+        let content = match self {
+            Location::File(path) => fs::read_to_string(path)?,
+            Location::Url(url) => ureq::request_url("GET", url).call()?.into_string()?,
+        };
+        spec::parse_document(&content, Format::detect(&self.to_string(), &content))
+    }
+}
+
+#[derive(Debug, Default)]
+struct Resolver {
+    documents: HashMap<String, Value>,
+}
+
+impl Resolver {
+    fn fragment(&mut self, location: &Location, fragment: &str) -> Result<Value> {
+        let key = location.to_string();
+        if !self.documents.contains_key(&key) {
+            let document = location
+                .load()
+                .with_context(|| format!("unable to load referenced document {}", location))?;
+            self.documents.insert(key.clone(), document);
+        }
+        let pointer = percent_decode_str(fragment).decode_utf8_lossy();
+        self.documents[&key]
+            .pointer(&pointer)
+            .cloned()
+            .ok_or_else(|| anyhow!("reference {}#{} not found", location, fragment))
+    }
+
+    /// Replaces references to other documents by their content. References
+    /// within the main document are left as they are, but the ones within
+    /// referenced documents are resolved against that document. Cyclic
+    /// references are replaced by an empty schema.
+    fn resolve(
+        &mut self,
+        value: &mut Value,
+        base: &Location,
+        root: bool,
+        stack: &mut Vec<String>,
+    ) -> Result<bool> {
+        match value {
+            Value::Object(object) => {
+                let reference = match object.get("$ref") {
+                    Some(Value::String(reference)) => reference.clone(),
+                    _ => {
+                        let mut resolved = false;
+                        for value in object.values_mut() {
+                            resolved |= self.resolve(value, base, root, stack)?;
+                        }
+                        return Ok(resolved);
+                    }
+                };
+
+                let (location, fragment) = match reference.find('#') {
+                    Some(index) => (&reference[..index], &reference[index + 1..]),
+                    None => (reference.as_str(), ""),
+                };
+                if location.is_empty() && root {
+                    return Ok(false);
+                }
+                let location = match location.is_empty() {
+                    true => base.clone(),
+                    false => base.join(location)?,
+                };
+
+                let key = format!("{}#{}", location, fragment);
+                if stack.contains(&key) {
+                    *value = json!({});
+                    return Ok(true);
+                }
+                let mut target = self.fragment(&location, fragment)?;
+                stack.push(key);
+                self.resolve(&mut target, &location, false, stack)?;
+                stack.pop();
+                *value = target;
+                Ok(true)
+            }
+            Value::Array(array) => {
+                let mut resolved = false;
+                for value in array.iter_mut() {
+                    resolved |= self.resolve(value, base, root, stack)?;
+                }
+                Ok(resolved)
+            }
+            _ => Ok(false),
+        }
+    }
+}
+
+/// Inlines references to other files and urls relative to the specification
+/// at `path`. Returns whether there were any.
+pub fn resolve_external(document: &mut Value, path: &Path) -> Result<bool> {
+    Resolver::default().resolve(
+        document,
+        &Location::File(path.to_path_buf()),
+        true,
+        &mut Vec::new(),
+    )
+}
@@ -0,0 +1,143 @@
+use std::{collections::BTreeSet, time::Duration};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::config::OperationConfig;
+
+// Operations never drop below these shares of the requests of the most
+// promising operation, so that they may still turn up something new
+const MIN_ENERGY: f64 = 0.05;
+const MIN_ENERGY_DENIED: f64 = 0.02;
+
+/// Requests and time an operation gets at least and at most
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Budget {
+    pub min_requests: u32,
+    pub max_requests: Option<u32>,
+    pub min_time: Duration,
+    pub max_time: Option<Duration>,
+}
+
+impl Budget {
+    /// Budget given by the config of the operation
+    pub fn new(config: &OperationConfig) -> Budget {
+        let seconds = |seconds: f64| Duration::try_from_secs_f64(seconds).ok();
+        Budget {
+            min_requests: config.min_requests.unwrap_or(0),
+            max_requests: config.max_requests,
+            min_time: config.min_time.and_then(seconds).unwrap_or_default(),
+            max_time: config.max_time.and_then(seconds),
+        }
+    }
+}
+
+/// Energy, status codes, requests and time of an operation so far
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Progress {
+    energy: f64,
+    statuses: BTreeSet<u16>,
+    requests: u32,
+    elapsed: Duration,
+}
+
+/// Energy of each operation, which decides how many requests it gets.
+/// Operations that recently answered with a new status code, a 5xx status
+/// code, an error or a finding get the most requests, the ones that keep
+/// answering 401, 403 or 404 the fewest. The budgets of the operations take
+/// precedence over their energy.
+#[derive(Debug)]
+pub struct Scheduler {
+    energy: Vec<f64>,
+    statuses: Vec<BTreeSet<u16>>,
+    budgets: Vec<Budget>,
+    requests: Vec<u32>,
+    elapsed: Vec<Duration>,
+}
+
+impl Scheduler {
+    pub fn new(budgets: Vec<Budget>) -> Scheduler {
+        let operations = budgets.len();
+        Scheduler {
+            energy: vec![1.0; operations],
+            statuses: vec![BTreeSet::new(); operations],
+            budgets,
+            requests: vec![0; operations],
+            elapsed: vec![Duration::ZERO; operations],
+        }
+    }
+
+    pub fn progress(&self, operation: usize) -> Progress {
+        Progress {
+            energy: self.energy[operation],
+            statuses: self.statuses[operation].clone(),
+            requests: self.requests[operation],
+            elapsed: self.elapsed[operation],
+        }
+    }
+
+    /// Continues with the progress of an earlier campaign
+    pub fn restore(&mut self, operation: usize, progress: Progress) {
+        self.energy[operation] = progress.energy;
+        self.statuses[operation] = progress.statuses;
+        self.requests[operation] = progress.requests;
+        self.elapsed[operation] = progress.elapsed;
+    }
+
+    /// Whether the operation used up its budget
+    fn is_exhausted(&self, operation: usize) -> bool {
+        let budget = &self.budgets[operation];
+        budget
+            .max_requests
+            .is_some_and(|max| self.requests[operation] >= max)
+            || budget
+                .max_time
+                .is_some_and(|max| self.elapsed[operation] >= max)
+    }
+
+    /// Whether all operations used up their budgets
+    pub fn exhausted(&self) -> bool {
+        (0..self.energy.len()).all(|operation| self.is_exhausted(operation))
+    }
+
+    /// Whether the operation gets a request in this pass. Operations below
+    /// their minimum budget and the ones with the most energy always get one.
+    pub fn pick(&self, operation: usize) -> bool {
+        if self.is_exhausted(operation) {
+            return false;
+        }
+        let budget = &self.budgets[operation];
+        if self.requests[operation] < budget.min_requests
+            || self.elapsed[operation] < budget.min_time
+        {
+            return true;
+        }
+        let max = (0..self.energy.len())
+            .filter(|&operation| !self.is_exhausted(operation))
+            .map(|operation| self.energy[operation])
+            .fold(MIN_ENERGY_DENIED, f64::max);
+        rand::thread_rng().gen_bool((self.energy[operation] / max).min(1.0))
+    }
+
+    /// Updates the energy of the operation by the status code of its response,
+    /// none when the request failed, and the time the request took
+    pub fn observe(
+        &mut self,
+        operation: usize,
+        status: Option<u16>,
+        finding: bool,
+        elapsed: Duration,
+    ) {
+        self.requests[operation] += 1;
+        self.elapsed[operation] += elapsed;
+        let new = status.is_some_and(|status| self.statuses[operation].insert(status));
+        let energy = &mut self.energy[operation];
+        *energy = match status {
+            _ if new || finding => 1.0,
+            None => 1.0,
+            Some(status) if status >= 500 => 1.0,
+            Some(401) | Some(403) | Some(404) => (*energy * 0.8).max(MIN_ENERGY_DENIED),
+            Some(_) => (*energy * 0.95).max(MIN_ENERGY),
+        };
+    }
+}
@@ -0,0 +1,160 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use rhai::{Dynamic, Engine, Scope, AST};
+use serde_json::{json, Map, Value};
+
+use crate::fuzzer::Response;
+use crate::payload::Payload;
+
+/// Rhai script with hooks around the requests of the fuzzer. The script may
+/// define `pre_request(request)`, which returns the request to send, and
+/// `post_response(request, response)`, which returns the reason of a finding
+/// or nothing.
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+}
+
+impl std::fmt::Debug for Script {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Script").finish_non_exhaustive()
+    }
+}
+
+/// Name-value pairs as an object, later pairs win
+fn to_object<'a>(pairs: impl IntoIterator<Item = (&'a str, &'a String)>) -> Value {
+    Value::Object(
+        pairs
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), json!(value)))
+            .collect(),
+    )
+}
+
+/// Value of an object returned by the script as a string
+fn to_string(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// The request as a map with `method`, `path`, `url`, `query`,
+/// `path_params`, `headers` and `body`
+pub(crate) fn request_value(payload: &Payload) -> Value {
+    json!({
+        "method": payload.method,
+        "path": payload.path,
+        "url": payload.url.as_str(),
+        "query": to_object(payload.query_params.iter().map(|(name, value)| (*name, value))),
+        "path_params": to_object(payload.path_params.iter().map(|(name, value)| (*name, value))),
+        "headers": to_object(payload.headers.iter().map(|(name, value)| (name.as_str(), value))),
+        "body": payload.body.first(),
+    })
+}
+
+/// Changes the payload to the request returned by a hook. Values of the
+/// parameters may be changed, while headers and the body may be replaced as a
+/// whole.
+pub(crate) fn apply_request(payload: &mut Payload, request: &Map<String, Value>) {
+    let empty = Map::new();
+    let field = |name: &str| {
+        request
+            .get(name)
+            .and_then(Value::as_object)
+            .unwrap_or(&empty)
+    };
+    for (name, value) in payload.query_params.iter_mut() {
+        if let Some(changed) = field("query").get(*name) {
+            *value = to_string(changed);
+        }
+    }
+    for (name, value) in payload.path_params.iter_mut() {
+        if let Some(changed) = field("path_params").get(*name) {
+            *value = to_string(changed);
+        }
+    }
+    if let Some(Value::Object(headers)) = request.get("headers") {
+        payload.headers = headers
+            .iter()
+            .map(|(name, value)| (name.to_lowercase(), to_string(value)))
+            .collect();
+    }
+    match request.get("body") {
+        Some(Value::Null) | None => payload.body.clear(),
+        Some(body) => payload.body = vec![body.clone()],
+    }
+}
+
+/// The response as a map with `status`, `headers` and `body`
+pub(crate) fn response_value(resp: &Response) -> Value {
+    json!({
+        "status": resp.status,
+        "headers": to_object(resp.headers.iter().map(|(name, value)| (name.as_str(), value))),
+        "body": resp.body,
+    })
+}
+
+impl Script {
+    pub fn load(path: &Path) -> Result<Script> {
+        let mut engine = Engine::new();
+        // Runaway scripts would stop fuzzing
+        engine.set_max_operations(1_000_000);
+        let ast = engine
+            .compile_file(path.to_path_buf())
+            .map_err(|e| anyhow!("invalid script {}: {}", path.display(), e))?;
+        Ok(Script { engine, ast })
+    }
+
+    fn defines(&self, name: &str) -> bool {
+        self.ast
+            .iter_functions()
+            .any(|function| function.name == name)
+    }
+
+    fn call(&self, name: &str, args: Vec<Value>) -> Result<Value> {
+        let args = args
+            .iter()
+            .map(rhai::serde::to_dynamic)
+            .collect::<Result<Vec<Dynamic>, _>>()
+            .map_err(|e| anyhow!("{}", e))?;
+        let result = self
+            .engine
+            .call_fn::<Dynamic>(&mut Scope::new(), &self.ast, name, args)
+            .map_err(|e| anyhow!("{} of the script failed: {}", name, e))?;
+        rhai::serde::from_dynamic(&result).map_err(|e| anyhow!("{}", e))
+    }
+
+    /// Lets `pre_request` change the payload
+    pub fn pre_request(&self, payload: &mut Payload) -> Result<()> {
+        if !self.defines("pre_request") {
+            return Ok(());
+        }
+        let request = match self.call("pre_request", vec![request_value(payload)])? {
+            Value::Object(request) => request,
+            Value::Null => return Ok(()),
+            _ => {
+                return Err(anyhow!(
+                    "pre_request of the script has to return the request"
+                ))
+            }
+        };
+        apply_request(payload, &request);
+        Ok(())
+    }
+
+    /// Reason of a finding returned by `post_response`
+    pub fn post_response(&self, payload: &Payload, resp: &Response) -> Result<Option<String>> {
+        if !self.defines("post_response") {
+            return Ok(None);
+        }
+        let args = vec![request_value(payload), response_value(resp)];
+        match self.call("post_response", args)? {
+            Value::Null => Ok(None),
+            Value::Bool(false) => Ok(None),
+            Value::String(reason) => Ok(Some(reason)),
+            reason => Ok(Some(reason.to_string())),
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use anyhow::{bail, Context, Result};
+use openapiv3::Server;
+use url::Url;
+
+pub fn with_trailing_slash(mut url: Url) -> Url {
+    if !url.path().ends_with('/') {
+        url.set_path(&format!("{}/", url.path()));
+    }
+    url
+}
+
+/// Builds the base url of the server, substituting variables with the given
+/// values or their defaults. Relative server urls are resolved against the
+/// `base` url.
+pub fn resolve(server: &Server, base: Option<&Url>, values: &[(String, String)]) -> Result<Url> {
+    let mut url = server.url.clone();
+    for (name, variable) in server.variables.iter().flatten() {
+        let value = match values.iter().find(|(var, _)| var == name) {
+            Some((_, value)) => {
+                if !variable.enumeration.is_empty() && !variable.enumeration.contains(value) {
+                    bail!(
+                        "value {} of server variable {} is not one of {}",
+                        value,
+                        name,
+                        variable.enumeration.join(", ")
+                    );
+                }
+                value
+            }
+            None if variable.default.is_empty() => {
+                variable.enumeration.first().unwrap_or(&variable.default)
+            }
+            None => &variable.default,
+        };
+        url = url.replace(&format!("{{{}}}", name), value);
+    }
+    if url.contains('{') {
+        bail!("server url {} has variables without a value", server.url);
+    }
+
+    let resolved = match Url::parse(&url) {
+        Ok(url) => url,
+        Err(url::ParseError::RelativeUrlWithoutBase) => base
+            .context(format!("relative server url {} requires --url", server.url))?
+            .join(&url)?,
+        Err(e) => return Err(e).context(format!("invalid server url {}", server.url)),
+    };
+    Ok(with_trailing_slash(resolved))
+}
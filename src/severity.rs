@@ -0,0 +1,187 @@
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// How serious a finding is, from the least to the most serious
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Severity {
+    Info,
+    Low,
+    /// Findings of unknown kinds, e.g. ones of older checkpoints
+    #[default]
+    Medium,
+    High,
+    Critical,
+}
+
+impl FromStr for Severity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "info" => Ok(Severity::Info),
+            "low" => Ok(Severity::Low),
+            "medium" => Ok(Severity::Medium),
+            "high" => Ok(Severity::High),
+            "critical" => Ok(Severity::Critical),
+            _ => Err(format!(
+                "unknown severity `{}`, expected info, low, medium, high or critical",
+                s.trim()
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Severity::Info => "info",
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+            Severity::Critical => "critical",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Class of findings that share a severity unless their kind is given one of
+/// its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Class {
+    /// Server errors and crashes of the target
+    ServerError,
+    /// Responses that do not conform to the specification
+    SchemaViolation,
+    /// Stack traces and sensitive data in responses
+    DataLeak,
+    /// Requests that should have been rejected for lack of authorization
+    AuthBypass,
+    /// Requests the server neither answered nor closed the connection of
+    Timeout,
+    /// Values interpreted by the server, e.g. reflected unescaped or delaying
+    /// it with a sleep primitive
+    Injection,
+    /// Status codes that are not documented
+    UndocumentedStatus,
+    /// Responses that contradict other responses of the server or the request
+    Inconsistency,
+    /// Findings of assertions, scripts, plugins and hooks
+    Custom,
+}
+
+const CLASSES: &[(&str, Class)] = &[
+    ("server-error", Class::ServerError),
+    ("schema-violation", Class::SchemaViolation),
+    ("data-leak", Class::DataLeak),
+    ("auth-bypass", Class::AuthBypass),
+    ("timeout", Class::Timeout),
+    ("injection", Class::Injection),
+    ("undocumented-status", Class::UndocumentedStatus),
+    ("inconsistency", Class::Inconsistency),
+    ("custom", Class::Custom),
+];
+
+impl Class {
+    /// Class of the findings of the kind, None for kinds that are not known
+    pub fn of(kind: &str) -> Option<Class> {
+        let class = match kind {
+            "crash" | "compression" => Class::ServerError,
+            "content-type" | "header" | "undocumented" | "callback" | "accepted-invalid" => {
+                Class::SchemaViolation
+            }
+            "error-leak" | "sensitive-data" => Class::DataLeak,
+            "cors" | "auth-required" | "mass-assignment" => Class::AuthBypass,
+            "hang" => Class::Timeout,
+            "reflection" | "truncation" | "time-based" | "path-traversal" | "ssrf" | "oob"
+            | "open-redirect" => Class::Injection,
+            "idempotency" | "head-consistency" | "allow-header" | "numeric-precision" => {
+                Class::Inconsistency
+            }
+            "assertion" | "script" | "plugin" | "hook" => Class::Custom,
+            status => match status.parse::<u16>() {
+                Ok(500..=599) => Class::ServerError,
+                Ok(100..=499) => Class::UndocumentedStatus,
+                _ => return None,
+            },
+        };
+        Some(class)
+    }
+
+    fn default_severity(self) -> Severity {
+        match self {
+            Class::AuthBypass => Severity::Critical,
+            Class::ServerError | Class::DataLeak | Class::Injection => Severity::High,
+            Class::Timeout | Class::Custom => Severity::Medium,
+            Class::SchemaViolation | Class::Inconsistency => Severity::Low,
+            Class::UndocumentedStatus => Severity::Info,
+        }
+    }
+}
+
+impl FromStr for Class {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CLASSES
+            .iter()
+            .find(|(name, _)| *name == s)
+            .map(|(_, class)| *class)
+            .ok_or_else(|| format!("unknown finding class `{}`", s))
+    }
+}
+
+/// Severities of the findings by their kind or class
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Severities {
+    kinds: BTreeMap<String, Severity>,
+    classes: BTreeMap<Class, Severity>,
+}
+
+impl Severities {
+    /// Severity of a finding of the kind, the one given to the kind comes
+    /// before the one of its class. Unknown kinds are of medium severity.
+    pub fn of(&self, kind: &str) -> Severity {
+        if let Some(severity) = self.kinds.get(kind) {
+            return *severity;
+        }
+        match Class::of(kind) {
+            Some(class) => self
+                .classes
+                .get(&class)
+                .copied()
+                .unwrap_or_else(|| class.default_severity()),
+            None => Severity::Medium,
+        }
+    }
+}
+
+/// Comma separated `<class or kind>=<severity>` pairs, e.g.
+/// `undocumented-status=low,error-leak=critical`, the others keep their
+/// default severity
+impl FromStr for Severities {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut severities = Severities::default();
+        for pair in s.split(',') {
+            let (key, severity) = match pair.split_once('=') {
+                Some(pair) => pair,
+                None => return Err(format!("expected `<class>=<severity>`, got `{}`", pair)),
+            };
+            let key = key.trim().to_lowercase();
+            let severity = severity.parse()?;
+            match key.parse::<Class>() {
+                Ok(class) => {
+                    severities.classes.insert(class, severity);
+                }
+                Err(_) if Class::of(&key).is_some() => {
+                    severities.kinds.insert(key, severity);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(severities)
+    }
+}
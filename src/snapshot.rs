@@ -0,0 +1,184 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use ureq::OrAnyStatus;
+use url::Url;
+
+use crate::checks;
+use crate::fuzzer::Response;
+use crate::payload::REQUEST_ID_HEADER;
+use crate::record::Seeds;
+
+/// Response to a recorded request as it was when the snapshots were taken
+#[derive(Debug, Serialize, Deserialize)]
+struct Snapshot {
+    method: String,
+    path: String,
+    /// Index of the request among the recorded ones of the operation
+    seed: usize,
+    request: Value,
+    response: Response,
+}
+
+/// Values of a field of the request, which is a map of strings
+fn pairs<'a>(request: &'a Value, field: &str) -> Vec<(&'a str, String)> {
+    request[field]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .map(|(name, value)| match value {
+            Value::String(value) => (name.as_str(), value.clone()),
+            value => (name.as_str(), value.to_string()),
+        })
+        .collect()
+}
+
+/// Sends the recorded request as it is to the url, the extra headers replace
+/// the recorded ones of the same name
+fn send(
+    url: &Url,
+    request_id: &str,
+    request: &Value,
+    extra_headers: &[(String, String)],
+) -> Result<Response> {
+    let mut path = request["path"].as_str().unwrap_or("/").to_string();
+    for (name, value) in pairs(request, "path_params") {
+        path = path.replace(&format!("{{{}}}", name), &value);
+    }
+    let method = request["method"].as_str().unwrap_or("GET");
+    let mut req = ureq::request_url(method, &url.join(path.trim_start_matches('/'))?)
+        .set(REQUEST_ID_HEADER, request_id);
+    for (name, value) in pairs(request, "query") {
+        req = req.query(name, &value);
+    }
+    for (name, value) in pairs(request, "headers") {
+        req = req.set(name, &value);
+    }
+    for (name, value) in extra_headers {
+        req = req.set(name, value);
+    }
+    let resp = match &request["body"] {
+        Value::Null => req.call().or_any_status()?,
+        body => req.send_json(body.clone()).or_any_status()?,
+    };
+    Response::read(resp)
+}
+
+fn request_id() -> String {
+    format!("{:032x}", rand::random::<u128>())
+}
+
+/// Sends every recorded request to the url and saves the responses as the
+/// snapshots later runs are compared with, returns their number
+pub fn take(
+    seeds: &Seeds,
+    url: &Url,
+    extra_headers: &[(String, String)],
+    file: &Path,
+) -> Result<usize> {
+    let mut snapshots = Vec::new();
+    for ((path, method), requests) in seeds.operations() {
+        for (seed, request) in requests.iter().enumerate() {
+            let response = send(url, &request_id(), request, extra_headers)
+                .with_context(|| format!("unable to send {} {}", method, path))?;
+            snapshots.push(Snapshot {
+                method: method.clone(),
+                path: path.clone(),
+                seed,
+                request: request.clone(),
+                response,
+            });
+        }
+    }
+    serde_json::to_writer_pretty(
+        File::create(file).with_context(|| format!("unable to create {}", file.display()))?,
+        &snapshots,
+    )?;
+    Ok(snapshots.len())
+}
+
+/// Sends every recorded request to the url again and compares the responses
+/// with their snapshots. Responses that changed are saved to `results_dir`
+/// and their number is returned.
+pub fn compare(
+    seeds: &Seeds,
+    url: &Url,
+    extra_headers: &[(String, String)],
+    file: &Path,
+    results_dir: &str,
+) -> Result<usize> {
+    let snapshots: Vec<Snapshot> = serde_json::from_reader(
+        File::open(file).with_context(|| format!("unable to open {}", file.display()))?,
+    )
+    .with_context(|| format!("invalid snapshots {}", file.display()))?;
+    let snapshots = snapshots
+        .into_iter()
+        .map(|snapshot| {
+            (
+                (
+                    snapshot.path.clone(),
+                    snapshot.method.clone(),
+                    snapshot.seed,
+                ),
+                snapshot,
+            )
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    let mut findings = 0;
+    for ((path, method), requests) in seeds.operations() {
+        let mut changed = 0;
+        let mut compared = 0;
+        for (seed, request) in requests.iter().enumerate() {
+            let snapshot = match snapshots.get(&(path.clone(), method.clone(), seed)) {
+                Some(snapshot) if snapshot.request == *request => snapshot,
+                _ => {
+                    eprintln!(
+                        "{} {}: request {} has no snapshot, take them again with --update",
+                        method, path, seed
+                    );
+                    continue;
+                }
+            };
+            compared += 1;
+            let request_id = request_id();
+            let response = send(url, &request_id, request, extra_headers)
+                .with_context(|| format!("unable to send {} {}", method, path))?;
+            let reason = match checks::regression(&snapshot.response, &response) {
+                Some(reason) => reason,
+                None => continue,
+            };
+            let dir = format!(
+                "{}/{}/{}",
+                results_dir,
+                path.trim_matches('/').replace('/', "-"),
+                method
+            );
+            fs::create_dir_all(&dir)?;
+            let file = format!("{}/{:x}.json", dir, rand::random::<u32>());
+            serde_json::to_writer_pretty(
+                &File::create(&file).with_context(|| format!("unable to create {}", file))?,
+                &json!({
+                    "request_id": request_id,
+                    "reason": reason,
+                    "request": request,
+                    "snapshot": snapshot.response,
+                    "response": response,
+                }),
+            )?;
+            changed += 1;
+        }
+        eprintln!(
+            "{} {}: {} of {} responses changed",
+            method, path, changed, compared
+        );
+        findings += changed;
+    }
+    Ok(findings)
+}
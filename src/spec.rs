@@ -0,0 +1,333 @@
+use std::{
+    fmt::Display,
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use openapiv3::OpenAPI;
+use serde_json::{json, Value};
+
+use crate::{hints, overlay, refs, swagger};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Yaml,
+}
+
+impl Format {
+    /// Detects the format by the extension of the file or by its content
+    pub fn detect(name: &str, specfile: &str) -> Format {
+        match Path::new(name)
+            .extension()
+            .and_then(|extension| extension.to_str())
+        {
+            Some(extension) if extension.eq_ignore_ascii_case("json") => Format::Json,
+            Some(extension)
+                if extension.eq_ignore_ascii_case("yaml")
+                    || extension.eq_ignore_ascii_case("yml") =>
+            {
+                Format::Yaml
+            }
+            _ if specfile.trim_start().starts_with('{') => Format::Json,
+            _ => Format::Yaml,
+        }
+    }
+}
+
+/// Attaches the offending line of the specification to the error
+fn located(error: impl Display, specfile: &str, line: usize, column: usize) -> anyhow::Error {
+    let source = specfile
+        .lines()
+        .nth(line.saturating_sub(1))
+        .unwrap_or_default();
+    anyhow!(
+        "{}\n{:>5} | {}\n      | {}^",
+        error,
+        line,
+        source,
+        " ".repeat(column.saturating_sub(1))
+    )
+}
+
+fn json_error(error: serde_json::Error, specfile: &str) -> anyhow::Error {
+    let (line, column) = (error.line(), error.column());
+    located(error, specfile, line, column)
+}
+
+fn yaml_error(error: serde_yaml::Error, specfile: &str) -> anyhow::Error {
+    match error.location() {
+        Some(location) => located(error, specfile, location.line(), location.column()),
+        None => error.into(),
+    }
+}
+
+pub fn parse_document(specfile: &str, format: Format) -> Result<Value> {
+    match format {
+        Format::Json => serde_json::from_str(specfile)
+            .map_err(|e| json_error(e, specfile))
+            .context("Failed to parse schema as JSON"),
+        Format::Yaml => serde_yaml::from_str::<serde_yaml::Value>(specfile)
+            .map_err(|e| yaml_error(e, specfile))
+            .and_then(|document| Ok(serde_json::to_value(document)?))
+            .context("Failed to parse schema as YAML"),
+    }
+}
+
+/// Loads the specification from the path, `-` reads it from stdin, and applies
+/// the overlays to it in order
+pub fn load(path: &Path, overlays: &[PathBuf]) -> Result<OpenAPI> {
+    let specfile = match path.to_str() {
+        Some("-") => {
+            let mut specfile = String::new();
+            io::stdin()
+                .read_to_string(&mut specfile)
+                .context("unable to read specification from stdin")?;
+            specfile
+        }
+        _ => fs::read_to_string(path)
+            .with_context(|| format!("unable to read {}", path.display()))?,
+    };
+    let format = Format::detect(&path.to_string_lossy(), &specfile);
+    let mut document = parse_document(&specfile, format)?;
+    for overlay in overlays {
+        overlay::apply(&mut document, overlay)?;
+    }
+    let external = refs::resolve_external(&mut document, path)?;
+
+    // YAML documents may give the version unquoted, as a number
+    let converted = if document["swagger"] == "2.0" || document["swagger"].as_f64() == Some(2.0) {
+        document = swagger::convert(&document);
+        true
+    } else if matches!(document["openapi"].as_str(), Some(version) if version.starts_with("3.1")) {
+        downconvert_3_1(&mut document);
+        true
+    } else {
+        false
+    };
+    let lifted = hints::lift(&mut document);
+    if !converted && !lifted && overlays.is_empty() && !external {
+        return match format {
+            // JSON is valid YAML, which is more lenient about scalar types
+            Format::Json => serde_json::from_str(&specfile)
+                .or_else(|_| serde_yaml::from_str(&specfile).map_err(|e| yaml_error(e, &specfile))),
+            Format::Yaml => serde_yaml::from_str(&specfile).map_err(|e| yaml_error(e, &specfile)),
+        }
+        .context("Failed to parse schema");
+    }
+
+    // Going through YAML keeps the parsing of scalars as lenient as for the
+    // original file, e.g. `version: 1.0` is still accepted as a string
+    serde_yaml::from_str(&serde_yaml::to_string(&document)?)
+        .context("Failed to parse converted schema")
+}
+
+/// Rewrites an OpenAPI 3.1 document in place to its closest OpenAPI 3.0
+/// equivalent. Webhooks are kept and end up among the extensions.
+fn downconvert_3_1(document: &mut Value) {
+    document["openapi"] = json!("3.0.3");
+    if let Some(document) = document.as_object_mut() {
+        document.remove("jsonSchemaDialect");
+        document.entry("paths").or_insert_with(|| json!({}));
+    }
+    lift_definitions(document);
+    if let Some(schemas) = document
+        .pointer_mut("/components/schemas")
+        .and_then(Value::as_object_mut)
+    {
+        schemas.values_mut().for_each(downconvert_schema);
+    }
+    convert_nested_schemas(document);
+}
+
+/// Moves the `$defs` of schemas, and of the document itself, to the schemas of
+/// the components, which OpenAPI 3.0 references point to. References to them
+/// by their original pointer, or relative to the document as `#/$defs/...`,
+/// are rewritten to their new place.
+fn lift_definitions(document: &mut Value) {
+    let mut definitions = Vec::new();
+    collect_definitions(document, String::from("#"), &mut definitions);
+    if definitions.is_empty() {
+        return;
+    }
+    let schemas = document
+        .as_object_mut()
+        .map(|document| document.entry("components").or_insert_with(|| json!({})))
+        .and_then(Value::as_object_mut)
+        .map(|components| components.entry("schemas").or_insert_with(|| json!({})))
+        .and_then(Value::as_object_mut);
+    let schemas = match schemas {
+        Some(schemas) => schemas,
+        None => return,
+    };
+    let mut moved = Vec::new();
+    for (pointer, name, definition) in definitions {
+        let mut unique = name.clone();
+        let mut suffix = 1;
+        while schemas.contains_key(&unique) {
+            suffix += 1;
+            unique = format!("{}{}", name, suffix);
+        }
+        let target = format!("#/components/schemas/{}", escape_pointer(&unique));
+        moved.push((pointer, target.clone()));
+        moved.push((format!("#/$defs/{}", escape_pointer(&name)), target));
+        schemas.insert(unique, definition);
+    }
+    rewrite_references(document, &moved);
+}
+
+/// Removes the `$defs` throughout the value, together with the pointer and
+/// the name of each definition, nested ones first
+fn collect_definitions(
+    value: &mut Value,
+    pointer: String,
+    found: &mut Vec<(String, String, Value)>,
+) {
+    match value {
+        Value::Object(object) => {
+            if let Some(Value::Object(definitions)) = object.remove("$defs") {
+                for (name, mut definition) in definitions {
+                    let pointer = format!("{}/$defs/{}", pointer, escape_pointer(&name));
+                    collect_definitions(&mut definition, pointer.clone(), found);
+                    found.push((pointer, name, definition));
+                }
+            }
+            for (key, value) in object.iter_mut() {
+                collect_definitions(value, format!("{}/{}", pointer, escape_pointer(key)), found);
+            }
+        }
+        Value::Array(array) => {
+            for (index, value) in array.iter_mut().enumerate() {
+                collect_definitions(value, format!("{}/{}", pointer, index), found);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Points references into moved definitions to their new place, the first
+/// matching move wins
+fn rewrite_references(value: &mut Value, moved: &[(String, String)]) {
+    match value {
+        Value::Object(object) => {
+            if let Some(Value::String(reference)) = object.get_mut("$ref") {
+                let rewritten = moved.iter().find_map(|(from, to)| {
+                    let rest = reference.strip_prefix(from.as_str())?;
+                    (rest.is_empty() || rest.starts_with('/')).then(|| format!("{}{}", to, rest))
+                });
+                if let Some(rewritten) = rewritten {
+                    *reference = rewritten;
+                }
+            }
+            object
+                .values_mut()
+                .for_each(|value| rewrite_references(value, moved));
+        }
+        Value::Array(array) => array
+            .iter_mut()
+            .for_each(|value| rewrite_references(value, moved)),
+        _ => {}
+    }
+}
+
+fn escape_pointer(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+/// Looks for `schema` keys throughout the document, which are used by
+/// parameters, headers and media types
+fn convert_nested_schemas(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object.iter_mut() {
+                match key.as_str() {
+                    "schema" => downconvert_schema(value),
+                    _ => convert_nested_schemas(value),
+                }
+            }
+        }
+        Value::Array(array) => array.iter_mut().for_each(convert_nested_schemas),
+        _ => {}
+    }
+}
+
+fn downconvert_schema(schema: &mut Value) {
+    let object = match schema.as_object_mut() {
+        Some(object) => object,
+        None => return,
+    };
+
+    for keyword in &["$schema", "$id", "$anchor", "$comment"] {
+        object.remove(*keyword);
+    }
+
+    if let Some(Value::Array(types)) = object.get("type").filter(|t| t.is_array()).cloned() {
+        object.remove("type");
+        let nullable = types.iter().any(|t| t == "null");
+        let mut types = types
+            .into_iter()
+            .filter(|t| t != "null")
+            .collect::<Vec<_>>();
+        match types.len() {
+            0 => {}
+            1 => {
+                object.insert("type".to_string(), types.remove(0));
+            }
+            // A value has one of the types, not several of them at once
+            _ => {
+                let one_of = types
+                    .into_iter()
+                    .map(|t| match t.as_str() {
+                        Some("array") => json!({"type": t, "items": {}}),
+                        _ => json!({ "type": t }),
+                    })
+                    .collect::<Vec<_>>();
+                object.insert("oneOf".to_string(), json!(one_of));
+            }
+        }
+        if nullable {
+            object.insert("nullable".to_string(), json!(true));
+        }
+    } else if object.get("type") == Some(&json!("null")) {
+        object.remove("type");
+        object.insert("nullable".to_string(), json!(true));
+    }
+
+    if let Some(value) = object.remove("const") {
+        object.insert("enum".to_string(), json!([value]));
+    }
+    if let Some(Value::Array(examples)) = object.get("examples").filter(|e| e.is_array()).cloned() {
+        object.remove("examples");
+        if let Some(example) = examples.into_iter().next() {
+            object.insert("example".to_string(), example);
+        }
+    }
+    for (exclusive, bound) in &[
+        ("exclusiveMinimum", "minimum"),
+        ("exclusiveMaximum", "maximum"),
+    ] {
+        if let Some(limit) = object.get(*exclusive).filter(|v| v.is_number()).cloned() {
+            object.insert(bound.to_string(), limit);
+            object.insert(exclusive.to_string(), json!(true));
+        }
+    }
+    if object.get("type") == Some(&json!("array")) {
+        object.entry("items").or_insert_with(|| json!({}));
+    }
+
+    for keyword in &["items", "not", "additionalProperties"] {
+        if let Some(schema) = object.get_mut(*keyword) {
+            downconvert_schema(schema);
+        }
+    }
+    for keyword in &["allOf", "anyOf", "oneOf", "prefixItems"] {
+        if let Some(Value::Array(schemas)) = object.get_mut(*keyword) {
+            schemas.iter_mut().for_each(downconvert_schema);
+        }
+    }
+    if let Some(Value::Object(properties)) = object.get_mut("properties") {
+        properties.values_mut().for_each(downconvert_schema);
+    }
+}
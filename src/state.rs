@@ -0,0 +1,500 @@
+use std::collections::HashMap;
+
+use openapi_utils::ReferenceOrExt;
+use openapiv3::{Link, OpenAPI, PathItem, ReferenceOr, SchemaKind, StatusCode, Type};
+use rand::{seq::SliceRandom, Rng};
+use serde_json::Value;
+use url::Url;
+
+use crate::checks;
+use crate::fuzzer::Response;
+use crate::payload::{self, Payload};
+
+// Number of ids that are kept for each resource, older ones are dropped
+const MAX_IDS: usize = 16;
+
+/// Resource created by a POST on its collection or listed by its GET, and
+/// addressed by the last parameter of its item path, e.g. `/pets` and
+/// `/pets/{petId}`
+#[derive(Debug)]
+struct Resource {
+    collection: String,
+    item: String,
+    param: String,
+    /// Property of the created resource that holds its id
+    id_property: String,
+}
+
+/// Names of the properties declared by the successful responses
+fn response_properties(item: &PathItem) -> Vec<String> {
+    let responses = match &item.post {
+        Some(operation) => &operation.responses.responses,
+        None => return Vec::new(),
+    };
+    responses
+        .iter()
+        .filter(|(status, _)| matches!(status, StatusCode::Code(200..=299) | StatusCode::Range(2)))
+        .flat_map(|(_, response)| response.to_item_ref().content.values())
+        .filter_map(|media| media.schema.as_ref())
+        .flat_map(|schema| match &schema.to_item_ref().schema_kind {
+            SchemaKind::Type(Type::Object(object)) => object.properties.keys().cloned().collect(),
+            SchemaKind::Any(any) => any.properties.keys().cloned().collect(),
+            _ => Vec::new(),
+        })
+        .collect()
+}
+
+/// The property named like the parameter holds the id, otherwise `id` or one
+/// ending with it
+pub(crate) fn id_property(param: &str, properties: &[String]) -> String {
+    properties
+        .iter()
+        .find(|property| *property == param)
+        .or_else(|| properties.iter().find(|property| *property == "id"))
+        .or_else(|| {
+            properties
+                .iter()
+                .find(|property| property.to_lowercase().ends_with("id"))
+        })
+        .cloned()
+        .unwrap_or_else(|| "id".to_string())
+}
+
+fn infer_resources(schema: &OpenAPI) -> Vec<Resource> {
+    let mut resources = Vec::new();
+    for path in schema.paths.keys() {
+        let (collection, last) = match path.rsplit_once('/') {
+            Some(split) => split,
+            None => continue,
+        };
+        let param = match last
+            .strip_prefix('{')
+            .and_then(|last| last.strip_suffix('}'))
+        {
+            Some(param) => param,
+            None => continue,
+        };
+        let collection_item = match schema.paths.get(collection) {
+            Some(collection_item) => collection_item.to_item_ref(),
+            None => continue,
+        };
+        if collection_item.post.is_none() && collection_item.get.is_none() {
+            continue;
+        }
+        resources.push(Resource {
+            collection: collection.to_string(),
+            item: path.clone(),
+            param: param.to_string(),
+            id_property: id_property(param, &response_properties(collection_item)),
+        });
+    }
+    resources
+}
+
+/// Adds the value as the most recent one, dropping the oldest value when there
+/// are too many
+fn remember(values: &mut Vec<String>, value: String) {
+    values.retain(|known| known != &value);
+    values.push(value);
+    if values.len() > MAX_IDS {
+        values.remove(0);
+    }
+}
+
+/// Lower case alphanumeric characters of the name, e.g. `petid` for `pet_id`
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Whether the property probably holds the value of the parameter, e.g. `id`
+/// or `pet_id` for `petId`
+fn correlates(param: &str, property: &str) -> bool {
+    let (param, property) = (normalize(param), normalize(property));
+    param == property || (param.ends_with("id") && (property == "id" || property == "uuid"))
+}
+
+/// Values of the correlating properties of the objects in a list response,
+/// which is either an array or an object with an array, e.g. `{"items": []}`
+fn listed_values(body: &Value, param: &str) -> Vec<String> {
+    let items = match body {
+        Value::Array(items) => items.iter().collect::<Vec<_>>(),
+        Value::Object(object) => object
+            .values()
+            .filter_map(Value::as_array)
+            .flatten()
+            .collect(),
+        _ => Vec::new(),
+    };
+    items
+        .into_iter()
+        .filter_map(Value::as_object)
+        .filter_map(|item| {
+            item.iter()
+                .find(|(property, _)| correlates(param, property))
+                .and_then(|(_, value)| value_to_string(value))
+        })
+        .collect()
+}
+
+/// Whether the path is the item path of the resource or is below it
+fn is_below(path: &str, item: &str) -> bool {
+    path == item || path.starts_with(&format!("{}/", item))
+}
+
+/// Adds the resource unless it was created before, ids may be reused
+fn remember_created(created: &mut Vec<Created>, resource: Created) {
+    if !created.contains(&resource) {
+        created.push(resource);
+    }
+}
+
+/// Path parameters of the request sorted by their name
+fn params(payload: &Payload) -> Vec<(String, String)> {
+    let mut params = payload
+        .path_params
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.clone()))
+        .collect::<Vec<_>>();
+    params.sort();
+    params
+}
+
+/// Text of a string or number id
+pub(crate) fn value_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(id) => Some(id.clone()),
+        Value::Number(id) => Some(id.to_string()),
+        _ => None,
+    }
+}
+
+/// Path and method of the operation given by the `operationId` or
+/// `operationRef` of the link, such as `#/paths/~1pets~1{petId}/get`
+fn link_target(
+    link: &Link,
+    operation_ids: &HashMap<String, (String, String)>,
+) -> Option<(String, String)> {
+    // openapiv3 does not rename the fields of links, they end up in extensions
+    let field = |name: &str| link.extensions.get(name).and_then(Value::as_str);
+    if let Some(operation_id) = link
+        .operation_id
+        .as_deref()
+        .or_else(|| field("operationId"))
+    {
+        return operation_ids.get(operation_id).cloned();
+    }
+    let operation_ref = link
+        .operation_ref
+        .as_deref()
+        .or_else(|| field("operationRef"))?;
+    let pointer = operation_ref.split_once('#')?.1;
+    let mut segments = pointer
+        .trim_start_matches('/')
+        .split('/')
+        .map(|segment| segment.replace("~1", "/").replace("~0", "~"));
+    match (segments.next()?.as_str(), segments.next(), segments.next()) {
+        ("paths", Some(path), Some(method)) => Some((path, method.to_uppercase())),
+        _ => None,
+    }
+}
+
+/// Evaluates a runtime expression such as `$response.body#/id` or
+/// `$request.path.petId`
+fn evaluate(
+    expression: &str,
+    payload: &Payload,
+    resp: &Response,
+    body: &Option<Value>,
+) -> Option<String> {
+    let find = |params: &[(&str, String)], name: &str| {
+        params
+            .iter()
+            .find(|(param, _)| *param == name)
+            .map(|(_, value)| value.clone())
+    };
+    if let Some(pointer) = expression.strip_prefix("$response.body#") {
+        return body.as_ref()?.pointer(pointer).and_then(value_to_string);
+    }
+    if let Some(pointer) = expression.strip_prefix("$request.body#") {
+        return payload
+            .body
+            .first()?
+            .pointer(pointer)
+            .and_then(value_to_string);
+    }
+    if let Some(name) = expression.strip_prefix("$response.header.") {
+        return resp.header(&name.to_lowercase()).map(str::to_string);
+    }
+    if let Some(name) = expression.strip_prefix("$request.header.") {
+        return payload
+            .headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.clone());
+    }
+    if let Some(name) = expression.strip_prefix("$request.path.") {
+        return find(&payload.path_params, name);
+    }
+    if let Some(name) = expression.strip_prefix("$request.query.") {
+        return find(&payload.query_params, name);
+    }
+    match expression {
+        "$statusCode" => Some(resp.status.to_string()),
+        "$method" => Some(payload.method.to_string()),
+        _ if expression.starts_with('$') => None,
+        // Constants and expressions embedded in a string such as `id-{$request.path.id}`
+        _ => {
+            let mut value = String::new();
+            let mut rest = expression;
+            while let Some(start) = rest.find("{$") {
+                let end = start + rest[start..].find('}')?;
+                value.push_str(&rest[..start]);
+                value.push_str(&evaluate(&rest[start + 1..end], payload, resp, body)?);
+                rest = &rest[end + 1..];
+            }
+            value.push_str(rest);
+            Some(value)
+        }
+    }
+}
+
+/// Values an API has returned during fuzzing, such as the ids of created
+/// resources, which replace the generated values of parameters
+#[derive(Debug, Default)]
+pub struct Store {
+    resources: Vec<Resource>,
+    /// Ids of the created resources by item path
+    ids: HashMap<String, Vec<String>>,
+    /// Ids of the resources returned by their collection, by item path
+    listed: HashMap<String, Vec<String>>,
+    /// Path and method of the operations by their operationId
+    operation_ids: HashMap<String, (String, String)>,
+    /// Links of the components that responses may refer to
+    component_links: HashMap<String, Link>,
+    /// Values extracted from responses by the parameters they are used for
+    extracted: HashMap<String, String>,
+    /// Values of parameters by the path and method of the operation that a
+    /// followed link points to
+    linked: HashMap<(String, String), HashMap<String, Vec<String>>>,
+    /// Resources created during fuzzing that were not deleted yet, by the url
+    /// of their server, their item path and its parameters
+    created: Vec<Created>,
+}
+
+/// Resource created by a POST on its collection or a PUT on its item path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Created {
+    pub url: Url,
+    pub item: String,
+    pub path_params: Vec<(String, String)>,
+}
+
+impl Store {
+    pub fn new(schema: &OpenAPI) -> Store {
+        let mut operation_ids = HashMap::new();
+        for (path, item) in schema.paths.iter() {
+            for (method, operation) in payload::operations(item.to_item_ref()) {
+                if let Some(operation_id) = &operation.operation_id {
+                    operation_ids.insert(operation_id.clone(), (path.clone(), method.to_string()));
+                }
+            }
+        }
+        let component_links = schema
+            .components
+            .iter()
+            .flat_map(|components| components.links.iter())
+            .filter_map(|(name, link)| match link {
+                ReferenceOr::Item(link) => Some((name.clone(), link.clone())),
+                ReferenceOr::Reference { .. } => None,
+            })
+            .collect();
+        Store {
+            resources: infer_resources(schema),
+            operation_ids,
+            component_links,
+            ..Store::default()
+        }
+    }
+
+    /// Feeds the values of the links of the response into the parameters of
+    /// the operations they point to
+    fn follow_links(&mut self, payload: &Payload, resp: &Response) {
+        let documented = match checks::documented_response(payload.responses, resp.status) {
+            Some(documented) if !documented.links.is_empty() => documented,
+            _ => return,
+        };
+        let body = serde_json::from_str::<Value>(&resp.body).ok();
+        let (component_links, linked) = (&self.component_links, &mut self.linked);
+        for link in documented.links.values() {
+            let link = match link {
+                ReferenceOr::Item(link) => link,
+                ReferenceOr::Reference { reference } => {
+                    match reference
+                        .rsplit('/')
+                        .next()
+                        .and_then(|name| component_links.get(name))
+                    {
+                        Some(link) => link,
+                        None => continue,
+                    }
+                }
+            };
+            let target = match link_target(link, &self.operation_ids) {
+                Some(target) => target,
+                None => continue,
+            };
+            for (name, expression) in &link.parameters {
+                // Parameters may be qualified by their location, e.g. `path.id`
+                let name = match name.split_once('.') {
+                    Some(("path" | "query" | "header" | "cookie", name)) => name,
+                    _ => name,
+                };
+                if let Some(value) = evaluate(expression, payload, resp, &body) {
+                    let values = linked
+                        .entry(target.clone())
+                        .or_default()
+                        .entry(name.to_string())
+                        .or_default();
+                    remember(values, value);
+                }
+            }
+        }
+    }
+
+    /// Remembers the ids of created resources and forgets the deleted ones
+    pub fn observe(&mut self, payload: &Payload, resp: &Response) {
+        self.follow_links(payload, resp);
+        if !(200..300).contains(&resp.status) {
+            return;
+        }
+        if !payload.config.extract.is_empty() {
+            if let Ok(body) = serde_json::from_str::<Value>(&resp.body) {
+                for (name, path) in &payload.config.extract {
+                    if let Some(value) = path.select(&body).first().and_then(|v| value_to_string(v))
+                    {
+                        self.extracted.insert(name.clone(), value);
+                    }
+                }
+            }
+        }
+        for resource in &self.resources {
+            if payload.method == "POST" && payload.path == resource.collection {
+                let body = match serde_json::from_str::<Value>(&resp.body) {
+                    Ok(body) => body,
+                    Err(_) => continue,
+                };
+                if let Some(id) = body.get(&resource.id_property).and_then(value_to_string) {
+                    let mut path_params = params(payload);
+                    path_params.push((resource.param.clone(), id.clone()));
+                    path_params.sort();
+                    remember_created(
+                        &mut self.created,
+                        Created {
+                            url: payload.url.clone(),
+                            item: resource.item.clone(),
+                            path_params,
+                        },
+                    );
+                    remember(self.ids.entry(resource.item.clone()).or_default(), id);
+                }
+            } else if payload.method == "PUT" && payload.path == resource.item {
+                // Only 201 tells that the resource did not exist before
+                if resp.status == 201 {
+                    remember_created(
+                        &mut self.created,
+                        Created {
+                            url: payload.url.clone(),
+                            item: resource.item.clone(),
+                            path_params: params(payload),
+                        },
+                    );
+                }
+            } else if payload.method == "GET" && payload.path == resource.collection {
+                let body = match serde_json::from_str::<Value>(&resp.body) {
+                    Ok(body) => body,
+                    Err(_) => continue,
+                };
+                let listed = self.listed.entry(resource.item.clone()).or_default();
+                for id in listed_values(&body, &resource.param) {
+                    remember(listed, id);
+                }
+            } else if payload.method == "DELETE" && payload.path == resource.item {
+                if let Some((_, id)) = payload
+                    .path_params
+                    .iter()
+                    .find(|(name, _)| *name == resource.param)
+                {
+                    let path_params = params(payload);
+                    self.created.retain(|created| {
+                        created.item != resource.item || created.path_params != path_params
+                    });
+                    for ids in self
+                        .ids
+                        .get_mut(&resource.item)
+                        .into_iter()
+                        .chain(self.listed.get_mut(&resource.item))
+                    {
+                        ids.retain(|known| known != id);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Resources created during fuzzing that were not deleted, the ones that
+    /// are nested deeper come first so children are deleted before their
+    /// parents
+    pub fn created(&self) -> Vec<&Created> {
+        let mut created = self.created.iter().collect::<Vec<_>>();
+        created.sort_by_key(|created| std::cmp::Reverse(created.item.matches('/').count()));
+        created
+    }
+
+    /// Value of the parameter extracted from an earlier response
+    pub fn extracted_param(&self, name: &str) -> Option<String> {
+        self.extracted.get(name).cloned()
+    }
+
+    /// Value of the parameter given by a link of an earlier response, they
+    /// are used most of the time
+    pub fn linked_param(&self, path: &str, method: &str, name: &str) -> Option<String> {
+        let mut rng = rand::thread_rng();
+        if rng.gen_ratio(1, 4) {
+            return None;
+        }
+        self.linked
+            .get(&(path.to_string(), method.to_string()))?
+            .get(name)?
+            .choose(&mut rng)
+            .cloned()
+    }
+
+    /// Id of a created resource for the path parameter, or of a resource
+    /// listed by its collection. Generated values are still used some of the
+    /// time to exercise the handling of unknown ids.
+    pub fn path_param(&self, path: &str, name: &str) -> Option<String> {
+        let mut rng = rand::thread_rng();
+        let resources = self
+            .resources
+            .iter()
+            .filter(|resource| resource.param == name && is_below(path, &resource.item))
+            .collect::<Vec<_>>();
+        let created = resources
+            .iter()
+            .find_map(|resource| self.ids.get(&resource.item)?.choose(&mut rng).cloned());
+        if created.is_some() && !rng.gen_ratio(1, 4) {
+            return created;
+        }
+        // Observed values are less certain to exist than created ones
+        let listed = resources
+            .iter()
+            .find_map(|resource| self.listed.get(&resource.item)?.choose(&mut rng).cloned());
+        match rng.gen_bool(0.5) {
+            true => listed,
+            false => None,
+        }
+    }
+}
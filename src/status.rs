@@ -0,0 +1,77 @@
+use std::str::FromStr;
+
+/// How the status codes of a class such as `5xx` are reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Policy {
+    /// Never a finding
+    Ignore,
+    /// A finding unless the status code is documented
+    Undocumented,
+    /// Always a finding, even when the status code is documented
+    Always,
+    /// Like `Undocumented`, documented status codes that were never received
+    /// are reported after fuzzing as well
+    Missing,
+}
+
+impl FromStr for Policy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "ignore" => Ok(Policy::Ignore),
+            "undocumented" => Ok(Policy::Undocumented),
+            "always" => Ok(Policy::Always),
+            "missing" => Ok(Policy::Missing),
+            _ => Err(format!("unknown status policy `{}`", s.trim())),
+        }
+    }
+}
+
+/// Policies of the status classes 1xx to 5xx
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Policies([Policy; 5]);
+
+impl Default for Policies {
+    fn default() -> Self {
+        Policies([
+            Policy::Undocumented,
+            Policy::Undocumented,
+            Policy::Undocumented,
+            Policy::Undocumented,
+            Policy::Always,
+        ])
+    }
+}
+
+impl Policies {
+    pub fn for_status(&self, status: u16) -> Policy {
+        match status / 100 {
+            class @ 1..=5 => self.0[class as usize - 1],
+            _ => Policy::Undocumented,
+        }
+    }
+}
+
+/// Comma separated `<class>=<policy>` pairs, e.g. `5xx=undocumented,4xx=ignore`,
+/// classes that are not listed keep their default policy
+impl FromStr for Policies {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut policies = Policies::default();
+        for pair in s.split(',') {
+            let (class, policy) = match pair.split_once('=') {
+                Some(pair) => pair,
+                None => return Err(format!("expected `<class>=<policy>`, got `{}`", pair)),
+            };
+            let class = class.trim().to_lowercase();
+            let index = match class.as_bytes() {
+                [digit @ b'1'..=b'5', b'x', b'x'] => (digit - b'1') as usize,
+                _ => return Err(format!("unknown status class `{}`", class)),
+            };
+            policies.0[index] = policy.parse()?;
+        }
+        Ok(policies)
+    }
+}
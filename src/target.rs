@@ -0,0 +1,167 @@
+use std::{
+    collections::VecDeque,
+    io::{BufRead, BufReader, Read},
+    net::TcpStream,
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use url::Url;
+
+// Lines of output kept for the findings, the oldest ones are dropped first
+const MAX_LINES: usize = 200;
+// Time the target gets to accept connections after it was started
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+// Time a target gets to exit after a failed request
+const EXIT_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Exit of the target together with its last lines of output
+#[derive(Debug, Clone)]
+pub struct Crash {
+    pub reason: String,
+    pub output: Vec<String>,
+}
+
+/// Api under test run by the fuzzer, which is restarted whenever it exits.
+/// Its stdout and stderr are captured for the findings.
+#[derive(Debug)]
+pub struct Target {
+    command: String,
+    url: Url,
+    child: Child,
+    output: Arc<Mutex<VecDeque<String>>>,
+}
+
+/// Keeps the last lines of the stream
+fn capture(stream: impl Read + Send + 'static, output: Arc<Mutex<VecDeque<String>>>) {
+    thread::spawn(move || {
+        for line in BufReader::new(stream).lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => break,
+            };
+            let mut output = output.lock().expect("output is not poisoned");
+            output.push_back(line);
+            if output.len() > MAX_LINES {
+                output.pop_front();
+            }
+        }
+    });
+}
+
+impl Target {
+    /// Runs the command and waits until the url accepts connections
+    pub fn start(command: impl Into<String>, url: &Url) -> Result<Target> {
+        let command = command.into();
+        let output = Arc::new(Mutex::new(VecDeque::new()));
+        let child = Target::spawn(&command, &output)?;
+        let mut target = Target {
+            command,
+            url: url.clone(),
+            child,
+            output,
+        };
+        target.wait_ready()?;
+        Ok(target)
+    }
+
+    fn spawn(command: &str, output: &Arc<Mutex<VecDeque<String>>>) -> Result<Child> {
+        let (shell, flag) = match cfg!(windows) {
+            true => ("cmd", "/C"),
+            false => ("sh", "-c"),
+        };
+        let mut child = Command::new(shell)
+            .args([flag, command])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("unable to run target `{}`", command))?;
+        if let Some(stdout) = child.stdout.take() {
+            capture(stdout, output.clone());
+        }
+        if let Some(stderr) = child.stderr.take() {
+            capture(stderr, output.clone());
+        }
+        Ok(child)
+    }
+
+    fn wait_ready(&mut self) -> Result<()> {
+        let addresses = self
+            .url
+            .socket_addrs(|| None)
+            .with_context(|| format!("unable to resolve {}", self.url))?;
+        let start = Instant::now();
+        while start.elapsed() < STARTUP_TIMEOUT {
+            if let Some(status) = self.child.try_wait()? {
+                bail!(
+                    "target `{}` {} while starting:\n{}",
+                    self.command,
+                    exited(status),
+                    self.take_output().join("\n")
+                );
+            }
+            if addresses.iter().any(|address| {
+                TcpStream::connect_timeout(address, Duration::from_millis(100)).is_ok()
+            }) {
+                return Ok(());
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        Err(anyhow!(
+            "target `{}` did not accept connections at {} within {} seconds",
+            self.command,
+            self.url,
+            STARTUP_TIMEOUT.as_secs()
+        ))
+    }
+
+    fn take_output(&self) -> Vec<String> {
+        self.output
+            .lock()
+            .expect("output is not poisoned")
+            .drain(..)
+            .collect()
+    }
+
+    /// Restarts the target when it exited after the last request, which is
+    /// waited for a moment when the request failed
+    pub fn crashed(&mut self, failed: bool) -> Result<Option<Crash>> {
+        let start = Instant::now();
+        let status = loop {
+            match self.child.try_wait()? {
+                Some(status) => break status,
+                None if failed && start.elapsed() < EXIT_TIMEOUT => {
+                    thread::sleep(Duration::from_millis(20))
+                }
+                None => return Ok(None),
+            }
+        };
+        // The last lines may still be read
+        thread::sleep(Duration::from_millis(50));
+        let crash = Crash {
+            reason: format!("target {}", exited(status)),
+            output: self.take_output(),
+        };
+        self.child = Target::spawn(&self.command, &self.output)?;
+        self.wait_ready()?;
+        Ok(Some(crash))
+    }
+}
+
+fn exited(status: ExitStatus) -> String {
+    match status.code() {
+        Some(code) => format!("exited with status {}", code),
+        None => "was killed by a signal".to_string(),
+    }
+}
+
+impl Drop for Target {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
@@ -0,0 +1,106 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension, Row};
+use serde_json::Value;
+
+use crate::fuzzer::Response;
+use crate::payload::StoredPayload;
+
+/// Finding kept in the database
+#[derive(Debug, Clone)]
+pub struct StoredFinding {
+    pub id: i64,
+    pub api: String,
+    pub method: String,
+    pub path: String,
+    pub kind: String,
+    pub reason: Option<String>,
+    pub first_run: i64,
+    pub last_run: i64,
+    pub occurrences: u32,
+    pub file: String,
+    pub details: Value,
+    /// Response that caused the finding, findings such as crashes have none
+    pub response: Option<Value>,
+}
+
+const COLUMNS: &str = "id, api, method, path, kind, reason, first_run, last_run, occurrences, \
+                       file, details, response";
+
+impl StoredFinding {
+    fn from_row(row: &Row) -> rusqlite::Result<StoredFinding> {
+        let json = |text: Option<String>| text.and_then(|text| serde_json::from_str(&text).ok());
+        Ok(StoredFinding {
+            id: row.get(0)?,
+            api: row.get(1)?,
+            method: row.get(2)?,
+            path: row.get(3)?,
+            kind: row.get(4)?,
+            reason: row.get(5)?,
+            first_run: row.get(6)?,
+            last_run: row.get(7)?,
+            occurrences: row.get(8)?,
+            file: row.get(9)?,
+            details: json(row.get(10)?).unwrap_or_default(),
+            response: json(row.get(11)?),
+        })
+    }
+
+    /// One line summary for the list of findings
+    pub fn summary(&self) -> String {
+        let runs = match self.first_run == self.last_run {
+            true => format!("run {}", self.first_run),
+            false => format!("runs {}-{}", self.first_run, self.last_run),
+        };
+        let mut line = format!(
+            "#{} {} {} {}: {}x in {}",
+            self.id, self.kind, self.method, self.path, self.occurrences, runs
+        );
+        if let Some(reason) = &self.reason {
+            line += &format!(", {}", reason);
+        }
+        line
+    }
+}
+
+fn connect(db: &Path) -> Result<Connection> {
+    Connection::open_with_flags(db, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("unable to open database {}", db.display()))
+}
+
+/// Findings of the database, optionally only the ones of the kind, the most
+/// recent ones first
+pub fn list(db: &Path, kind: Option<&str>) -> Result<Vec<StoredFinding>> {
+    let connection = connect(db)?;
+    let mut statement = connection.prepare(&format!(
+        "SELECT {} FROM findings WHERE ?1 IS NULL OR kind = ?1 ORDER BY last_run DESC, id",
+        COLUMNS
+    ))?;
+    let findings = statement
+        .query_map(params![kind], StoredFinding::from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(findings)
+}
+
+/// Finding of the database by its id
+pub fn get(db: &Path, id: i64) -> Result<StoredFinding> {
+    connect(db)?
+        .query_row(
+            &format!("SELECT {} FROM findings WHERE id = ?1", COLUMNS),
+            params![id],
+            StoredFinding::from_row,
+        )
+        .optional()?
+        .ok_or_else(|| anyhow!("no finding #{} in {}", id, db.display()))
+}
+
+/// Sends the request of the finding again with the extra headers, which
+/// replace the ones of the same name
+pub fn rerun(finding: &StoredFinding, extra_headers: &[(String, String)]) -> Result<Response> {
+    let mut stored: StoredPayload = serde_json::from_value(finding.details["payload"].clone())
+        .with_context(|| format!("finding #{} has no request to rerun", finding.id))?;
+    stored.set_headers(extra_headers);
+    let payload = stored.payload();
+    payload.send(payload.method)
+}
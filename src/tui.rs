@@ -7,10 +7,11 @@ use std::{
     time::{Duration, Instant},
 };
 
+use crate::color;
 use crate::fuzzer::{Stats, Tries};
 use anyhow::{Context, Result};
 use crossterm::{
-    event::{self, Event as CEvent, KeyCode, KeyEvent},
+    event::{self, Event as CEvent, KeyCode, KeyEvent, KeyModifiers},
     terminal,
 };
 use tui::{
@@ -138,7 +139,7 @@ impl Tui {
                                 Cell::from(Spans::from(vec![
                                     Span::styled(
                                         tries.successful.to_string(),
-                                        Style::default().fg(Color::Red),
+                                        Tui::colored(Color::Red),
                                     ),
                                     Span::raw(format!("/{}", tries.total)),
                                 ]))
@@ -183,7 +184,7 @@ impl Tui {
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .style(Style::default().fg(Color::White))
+                .style(Tui::colored(Color::White))
                 .title("OpenAPI Fuzzer")
                 .border_type(BorderType::Plain),
         )
@@ -199,29 +200,52 @@ impl Tui {
         frame.render_stateful_widget(table_widget, rect, &mut table.state);
     }
 
-    fn render_message_box(frame: &mut Frame<CrosstermBackend<Stdout>>, rect: Rect, message: &str) {
+    /// Style with the color unless colors are turned off
+    fn colored(color: Color) -> Style {
+        match color::disabled() {
+            true => Style::default(),
+            false => Style::default().fg(color),
+        }
+    }
+
+    fn render_message_box(
+        frame: &mut Frame<CrosstermBackend<Stdout>>,
+        rect: Rect,
+        message: &str,
+        failed: bool,
+    ) {
         let message = Paragraph::new(message)
-            .style(Style::default().fg(Color::LightCyan))
+            .style(Tui::colored(match failed {
+                true => Color::Red,
+                false => Color::LightCyan,
+            }))
             .alignment(Alignment::Center)
             .block(
                 Block::default()
                     .borders(Borders::ALL)
-                    .style(Style::default().fg(Color::White))
+                    .style(Tui::colored(Color::White))
                     .title("Message")
                     .border_type(BorderType::Plain),
             );
         frame.render_widget(message, rect);
     }
 
+    fn quit(&mut self) -> Result<bool> {
+        terminal::disable_raw_mode()?;
+        self.terminal.clear()?;
+        self.terminal.show_cursor()?;
+        Ok(true)
+    }
+
     pub fn display(&mut self, stats: &Stats, message: &Option<String>) -> Result<bool> {
         for e in self.receiver.try_iter() {
             match e {
                 Event::Input(event) => match event.code {
-                    KeyCode::Char('q') => {
-                        terminal::disable_raw_mode()?;
-                        self.terminal.clear()?;
-                        self.terminal.show_cursor()?;
-                        return Ok(true);
+                    KeyCode::Char('q') => return self.quit(),
+                    // Ctrl-C does not interrupt in raw mode, it quits the
+                    // usual way instead
+                    KeyCode::Char('c') if event.modifiers.contains(KeyModifiers::CONTROL) => {
+                        return self.quit()
                     }
                     KeyCode::Down => self.table.next(),
                     KeyCode::Up => self.table.previous(),
@@ -245,10 +269,12 @@ impl Tui {
                     .split(frame.size());
 
                 Tui::render_table(frame, chunks[0], stats, table);
+                // Messages are only shown when a request failed
                 Tui::render_message_box(
                     frame,
                     chunks[1],
                     message.as_ref().unwrap_or(&default_message),
+                    message.is_some(),
                 );
             })
             .context("unable to draw tui")?;
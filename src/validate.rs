@@ -0,0 +1,221 @@
+use openapiv3::{ReferenceOr, Schema, SchemaKind, Type};
+use serde_json::Value;
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+fn check_bounds(
+    location: &str,
+    value: f64,
+    minimum: Option<f64>,
+    maximum: Option<f64>,
+    exclusive: (bool, bool),
+    errors: &mut Vec<String>,
+) {
+    if let Some(minimum) = minimum {
+        if value < minimum || (exclusive.0 && value == minimum) {
+            errors.push(format!(
+                "{}: {} is below the minimum {}",
+                location, value, minimum
+            ));
+        }
+    }
+    if let Some(maximum) = maximum {
+        if value > maximum || (exclusive.1 && value == maximum) {
+            errors.push(format!(
+                "{}: {} is above the maximum {}",
+                location, value, maximum
+            ));
+        }
+    }
+}
+
+fn schema_of(schema: &ReferenceOr<Box<Schema>>) -> Option<&Schema> {
+    match schema {
+        ReferenceOr::Item(schema) => Some(schema),
+        ReferenceOr::Reference { .. } => None,
+    }
+}
+
+/// The schemas unless one of them is a reference
+fn items(schemas: &[ReferenceOr<Schema>]) -> Option<Vec<&Schema>> {
+    schemas
+        .iter()
+        .map(|schema| match schema {
+            ReferenceOr::Item(schema) => Some(schema),
+            ReferenceOr::Reference { .. } => None,
+        })
+        .collect()
+}
+
+/// Validates the value against a dereferenced schema and collects the
+/// violations, prefixed by the location of the offending value. References
+/// left in the schema are not followed.
+pub fn validate(schema: &Schema, value: &Value, location: &str, errors: &mut Vec<String>) {
+    if value.is_null() && schema.schema_data.nullable {
+        return;
+    }
+
+    let schema_type = match &schema.schema_kind {
+        SchemaKind::Type(schema_type) => schema_type,
+        // Alternatives with references are not validated
+        SchemaKind::OneOf { one_of } => {
+            if let Some(schemas) = items(one_of) {
+                let valid = schemas
+                    .iter()
+                    .filter(|schema| is_valid(schema, value))
+                    .count();
+                if valid != 1 {
+                    errors.push(format!(
+                        "{}: matches {} instead of exactly one of the oneOf schemas",
+                        location, valid
+                    ));
+                }
+            }
+            return;
+        }
+        SchemaKind::AnyOf { any_of } => {
+            if let Some(schemas) = items(any_of) {
+                if !schemas.iter().any(|schema| is_valid(schema, value)) {
+                    errors.push(format!("{}: matches none of the anyOf schemas", location));
+                }
+            }
+            return;
+        }
+        SchemaKind::AllOf { all_of } => {
+            for schema in all_of {
+                if let ReferenceOr::Item(schema) = schema {
+                    validate(schema, value, location, errors);
+                }
+            }
+            return;
+        }
+        SchemaKind::Any(_) => return,
+    };
+
+    match (schema_type, value) {
+        (Type::String(string), Value::String(s)) => {
+            if !string.enumeration.is_empty() && !string.enumeration.contains(s) {
+                errors.push(format!(
+                    "{}: {:?} is not one of the allowed values",
+                    location, s
+                ));
+            }
+            let length = s.chars().count();
+            if string.min_length.is_some_and(|min| length < min)
+                || string.max_length.is_some_and(|max| length > max)
+            {
+                errors.push(format!("{}: length {} is out of bounds", location, length));
+            }
+        }
+        (Type::Integer(integer), Value::Number(n)) if n.is_i64() || n.is_u64() => {
+            let n = n.as_f64().unwrap_or_default();
+            check_bounds(
+                location,
+                n,
+                integer.minimum.map(|m| m as f64),
+                integer.maximum.map(|m| m as f64),
+                (integer.exclusive_minimum, integer.exclusive_maximum),
+                errors,
+            );
+            if !integer.enumeration.is_empty()
+                && !integer.enumeration.iter().any(|e| *e as f64 == n)
+            {
+                errors.push(format!(
+                    "{}: {} is not one of the allowed values",
+                    location, n
+                ));
+            }
+        }
+        (Type::Number(number), Value::Number(n)) => {
+            let n = n.as_f64().unwrap_or_default();
+            check_bounds(
+                location,
+                n,
+                number.minimum,
+                number.maximum,
+                (number.exclusive_minimum, number.exclusive_maximum),
+                errors,
+            );
+            if !number.enumeration.is_empty() && !number.enumeration.contains(&n) {
+                errors.push(format!(
+                    "{}: {} is not one of the allowed values",
+                    location, n
+                ));
+            }
+        }
+        (Type::Boolean {}, Value::Bool(_)) => {}
+        (Type::Array(array), Value::Array(items)) => {
+            if array.min_items.is_some_and(|min| items.len() < min)
+                || array.max_items.is_some_and(|max| items.len() > max)
+            {
+                errors.push(format!(
+                    "{}: {} items are out of bounds",
+                    location,
+                    items.len()
+                ));
+            }
+            if let Some(items_schema) = schema_of(&array.items) {
+                for (index, item) in items.iter().enumerate() {
+                    validate(
+                        items_schema,
+                        item,
+                        &format!("{}[{}]", location, index),
+                        errors,
+                    );
+                }
+            }
+        }
+        (Type::Object(object), Value::Object(properties)) => {
+            for required in &object.required {
+                if !properties.contains_key(required) {
+                    errors.push(format!("{}: property {} is missing", location, required));
+                }
+            }
+            for (name, property) in properties {
+                if let Some(schema) = object.properties.get(name).and_then(schema_of) {
+                    validate(schema, property, &format!("{}.{}", location, name), errors);
+                }
+            }
+        }
+        (schema_type, value) => {
+            let expected = match schema_type {
+                Type::String(_) => "a string",
+                Type::Number(_) => "a number",
+                Type::Integer(_) => "an integer",
+                Type::Boolean {} => "a boolean",
+                Type::Array(_) => "an array",
+                Type::Object(_) => "an object",
+            };
+            errors.push(format!(
+                "{}: expected {}, found {}",
+                location,
+                expected,
+                type_name(value)
+            ));
+        }
+    }
+}
+
+/// Violations of the value that are not a matter of its bounds or allowed
+/// values: values of the wrong type and missing required properties
+pub fn clear_violations(schema: &Schema, value: &Value, location: &str) -> Vec<String> {
+    let mut errors = Vec::new();
+    validate(schema, value, location, &mut errors);
+    errors.retain(|error| error.contains(": expected ") || error.ends_with(" is missing"));
+    errors
+}
+
+pub fn is_valid(schema: &Schema, value: &Value) -> bool {
+    let mut errors = Vec::new();
+    validate(schema, value, "", &mut errors);
+    errors.is_empty()
+}
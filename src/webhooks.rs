@@ -0,0 +1,189 @@
+use std::{
+    fs::{self, File},
+    io::Read,
+};
+
+use anyhow::{bail, Context, Result};
+use arbitrary::Unstructured;
+use openapi_utils::ReferenceOrExt;
+use openapiv3::{OpenAPI, Operation, PathItem, ReferenceOr};
+use rand::{seq::IteratorRandom, Rng};
+use serde_json::{json, Value};
+use ureq::OrAnyStatus;
+
+use crate::conform;
+use crate::payload;
+use crate::strategy::Strategy;
+
+/// Request the API sends to its consumers, declared by the `webhooks` of an
+/// OpenAPI 3.1 specification
+#[derive(Debug)]
+pub struct Webhook {
+    pub name: String,
+    pub method: &'static str,
+    pub operation: Operation,
+}
+
+/// Webhooks declared by the specification. openapiv3 does not know the field,
+/// it ends up in the extensions.
+pub fn webhooks(spec: &OpenAPI) -> Vec<Webhook> {
+    let webhooks = match spec.extensions.get("webhooks").and_then(Value::as_object) {
+        Some(webhooks) => webhooks,
+        None => return Vec::new(),
+    };
+    webhooks
+        .iter()
+        .filter_map(|(name, item)| {
+            Some((name, serde_json::from_value::<PathItem>(item.clone()).ok()?))
+        })
+        .flat_map(|(name, item)| {
+            payload::operations(&item)
+                .into_iter()
+                .map(|(method, operation)| Webhook {
+                    name: name.clone(),
+                    method,
+                    operation: operation.clone(),
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Body of a delivery that conforms to the schema of the webhook
+fn conforming_body(webhook: &Webhook, spec: &OpenAPI) -> Option<Value> {
+    let body = match webhook.operation.request_body.as_ref()? {
+        ReferenceOr::Item(body) => body,
+        ReferenceOr::Reference { reference } => spec
+            .components
+            .as_ref()?
+            .request_bodies
+            .get(reference.rsplit('/').next()?)
+            .and_then(|body| match body {
+                ReferenceOr::Item(body) => Some(body),
+                ReferenceOr::Reference { .. } => None,
+            })?,
+    };
+    let (_, media) = body
+        .content
+        .iter()
+        .find(|(media_type, _)| media_type.contains("json"))?;
+    let schema = match media.schema.as_ref()? {
+        ReferenceOr::Item(schema) => schema,
+        ReferenceOr::Reference { reference } => spec
+            .components
+            .as_ref()?
+            .schemas
+            .get(reference.rsplit('/').next()?)?
+            .to_item_ref(),
+    };
+    Some(conform::generate(schema, spec))
+}
+
+/// Replaces a random value of the body by a fuzzed string, which breaks the
+/// schema in the way consumers are most likely to mishandle
+pub(crate) fn mutate(body: &mut Value, gen: &mut Unstructured, strategy: Strategy) -> Result<()> {
+    let mut rng = rand::thread_rng();
+    let child = match body {
+        Value::Object(object) => object.values_mut().choose(&mut rng),
+        Value::Array(array) => array.iter_mut().choose(&mut rng),
+        _ => None,
+    };
+    match child {
+        Some(child) if rng.gen_bool(0.7) => mutate(child, gen, strategy),
+        _ => {
+            *body = json!(strategy.generate_string(gen)?);
+            Ok(())
+        }
+    }
+}
+
+/// Sends deliveries of every webhook to the receiver, `{webhook}` in its url
+/// is replaced by the name of the webhook. Every other delivery is fuzzed.
+/// Deliveries the receiver failed with a 5xx status code are saved to
+/// `results_dir` and their number is returned.
+pub fn deliver(
+    spec: &OpenAPI,
+    receiver: &str,
+    deliveries: usize,
+    strategies: &[Strategy],
+    results_dir: &str,
+) -> Result<usize> {
+    let webhooks = webhooks(spec);
+    if webhooks.is_empty() {
+        bail!("the specification declares no webhooks");
+    }
+    let mut findings = 0;
+    for webhook in webhooks {
+        let url = receiver.replace("{webhook}", &webhook.name);
+        let mut failed = 0;
+        for delivery in 0..deliveries {
+            let mut body = conforming_body(&webhook, spec).unwrap_or_else(|| json!({}));
+            let fuzzed = delivery % 2 == 1;
+            if fuzzed {
+                let input: String = rand::thread_rng()
+                    .sample_iter::<char, _>(rand::distributions::Standard)
+                    .take(1024)
+                    .collect();
+                let strategy = *strategies
+                    .iter()
+                    .choose(&mut rand::thread_rng())
+                    .unwrap_or(&Strategy::Random);
+                mutate(
+                    &mut body,
+                    &mut Unstructured::new(input.as_bytes()),
+                    strategy,
+                )?;
+            }
+            let resp = match ureq::request(webhook.method, &url)
+                .send_json(body.clone())
+                .or_any_status()
+            {
+                Ok(resp) => resp,
+                Err(e) => {
+                    eprintln!("webhook {} to {}: {}", webhook.name, url, e);
+                    continue;
+                }
+            };
+            let status = resp.status();
+            if status < 500 {
+                continue;
+            }
+            let mut response = String::new();
+            let _ = resp
+                .into_reader()
+                .take(16 * 1024)
+                .read_to_string(&mut response);
+            let dir = format!(
+                "{}/{}/{}/{}",
+                results_dir, webhook.name, webhook.method, status
+            );
+            fs::create_dir_all(&dir)?;
+            let file = format!("{}/{:x}.json", dir, rand::random::<u32>());
+            serde_json::to_writer_pretty(
+                &File::create(&file).with_context(|| format!("unable to create {}", file))?,
+                &json!({
+                    "webhook": webhook.name,
+                    "method": webhook.method,
+                    "url": url,
+                    "body": body,
+                    "fuzzed": fuzzed,
+                    "reason": format!("receiver responded with {}", status),
+                    "response": response,
+                    "curl": format!(
+                        "curl -X {} -H 'content-type:application/json' -d '{}' {}",
+                        webhook.method,
+                        serde_json::to_string(&body)?,
+                        url
+                    ),
+                }),
+            )?;
+            failed += 1;
+        }
+        eprintln!(
+            "webhook {} {}: {} of {} deliveries failed",
+            webhook.name, webhook.method, failed, deliveries
+        );
+        findings += failed;
+    }
+    Ok(findings)
+}